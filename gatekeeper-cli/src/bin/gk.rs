@@ -1,12 +1,12 @@
 use clap::Parser;
-use gatekeeper_cli::{Cli, exec};
+use gatekeeper_cli::{Cli, run};
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
-    if let Err(e) = exec(cli.sk, cli.server, cli.cmd).await {
-        eprintln!("{}", e);
+    if let Err(e) = run(cli).await {
+        eprintln!("{:#}", e);
         std::process::exit(1);
     }
 }
@@ -1,19 +1,93 @@
-use clap::Parser;
+use std::path::PathBuf;
 
-use gatekeeper::Cmd;
-use iroh::{NodeId, SecretKey};
+use clap::{Parser, Subcommand};
+
+use gatekeeper::{Cmd, ImportMode};
+use iroh::NodeId;
+
+use crate::snapshot::Format;
 
 #[derive(Debug, Parser)]
-#[command(about = "Gatekeeper CLI")]
+#[command(
+    about = "Gatekeeper CLI",
+    after_help = "The client secret key is read from --key-file, --key-stdin or the GK_SECRET_KEY \
+                  environment variable. Encrypted key files take their passphrase from \
+                  GK_PASSPHRASE, or prompt for it."
+)]
 pub struct Cli {
-    /// Client secret key
-    #[arg(long)]
-    pub sk: SecretKey,
+    /// Client secret key file, optionally passphrase-encrypted
+    #[arg(long, env = "GK_KEY_FILE", global = true)]
+    pub key_file: Option<PathBuf>,
+
+    /// Read the client secret key from stdin
+    #[arg(long, global = true, conflicts_with = "key_file")]
+    pub key_stdin: bool,
 
     /// Server node ID
-    #[arg(long)]
-    pub server: NodeId,
+    #[arg(long, global = true)]
+    pub server: Option<NodeId>,
 
     #[command(subcommand)]
-    pub cmd: Cmd,
+    pub cmd: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run a gatekeeper server
+    Serve {
+        /// Server config file
+        #[arg(long)]
+        config: PathBuf,
+    },
+    /// Generate a new secret key file and print its node ID
+    Keygen {
+        /// Key file to create
+        #[arg(long)]
+        out: PathBuf,
+        /// Encrypt the key file with a passphrase?
+        #[arg(long, default_value_t = false)]
+        encrypt: bool,
+    },
+    /// Print the node ID of the client secret key
+    NodeId,
+    /// Show the changes needed to bring the server in line with a policy file
+    Plan {
+        /// Policy file
+        #[arg(long)]
+        policy: PathBuf,
+        /// Also remove nodes and grants missing from the policy?
+        #[arg(long, default_value_t = false)]
+        prune: bool,
+    },
+    /// Bring the server in line with a policy file
+    Apply {
+        /// Policy file
+        #[arg(long)]
+        policy: PathBuf,
+        /// Also remove nodes and grants missing from the policy?
+        #[arg(long, default_value_t = false)]
+        prune: bool,
+    },
+    /// Export the full server state
+    Export {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+        /// Output file (stdout if omitted)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Restore a previously exported server state
+    Import {
+        /// Snapshot file
+        file: PathBuf,
+        /// Snapshot format
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+        /// Merge into, or replace, the existing state
+        #[arg(long, value_enum, default_value_t = ImportMode::Merge)]
+        mode: ImportMode,
+    },
+    #[command(flatten)]
+    Remote(Box<Cmd>),
 }
@@ -0,0 +1,116 @@
+use std::{
+    io::{Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use age::secrecy::SecretString;
+use anyhow::Context;
+use iroh::SecretKey;
+
+/// Environment variable holding a hex or base32 encoded secret key.
+pub const SECRET_KEY_ENV: &str = "GK_SECRET_KEY";
+
+/// Environment variable holding the passphrase for an encrypted key file.
+pub const PASSPHRASE_ENV: &str = "GK_PASSPHRASE";
+
+const ARMOR_HEADER: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Load a secret key from a file, generating and saving a new one if the file doesn't exist.
+pub fn load_or_generate(path: &Path) -> anyhow::Result<SecretKey> {
+    if path.exists() {
+        return load(path);
+    }
+
+    let sk = generate();
+    save(path, &sk, None)?;
+    Ok(sk)
+}
+
+pub fn generate() -> SecretKey {
+    SecretKey::generate(&mut rand::thread_rng())
+}
+
+/// Load a secret key from a file, decrypting it first if it's passphrase-encrypted.
+pub fn load(path: &Path) -> anyhow::Result<SecretKey> {
+    let data = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read key file {}", path.display()))?;
+
+    if !data.trim_start().starts_with(ARMOR_HEADER) {
+        return parse(&data).with_context(|| format!("invalid key file {}", path.display()));
+    }
+
+    let identity = age::scrypt::Identity::new(passphrase(false)?);
+    let plaintext = age::decrypt(&identity, data.as_bytes())
+        .with_context(|| format!("failed to decrypt key file {}", path.display()))?;
+
+    let plaintext = String::from_utf8(plaintext).context("invalid key")?;
+    parse(&plaintext).with_context(|| format!("invalid key file {}", path.display()))
+}
+
+/// Save a secret key as hex, optionally passphrase-encrypted, readable only by the
+/// current user. Refuses to overwrite an existing file.
+pub fn save(path: &Path, sk: &SecretKey, passphrase: Option<SecretString>) -> anyhow::Result<()> {
+    let encoded = data_encoding::HEXLOWER.encode(&sk.to_bytes());
+    let contents = match passphrase {
+        None => format!("{encoded}\n"),
+        Some(passphrase) => {
+            let recipient = age::scrypt::Recipient::new(passphrase);
+            age::encrypt_and_armor(&recipient, encoded.as_bytes())?
+        }
+    };
+
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create_new(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut opts, 0o600);
+
+    let mut file = opts
+        .open(path)
+        .with_context(|| format!("failed to create key file {}", path.display()))?;
+
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+pub fn from_stdin() -> anyhow::Result<SecretKey> {
+    let mut data = String::new();
+    std::io::stdin()
+        .read_to_string(&mut data)
+        .context("failed to read key from stdin")?;
+
+    parse(&data).context("invalid key on stdin")
+}
+
+pub fn from_env() -> anyhow::Result<Option<SecretKey>> {
+    match std::env::var(SECRET_KEY_ENV) {
+        Err(_) => Ok(None),
+        Ok(data) => parse(&data)
+            .map(Some)
+            .with_context(|| format!("invalid key in {SECRET_KEY_ENV}")),
+    }
+}
+
+/// Read a passphrase from the environment, or prompt for one on the terminal.
+pub fn passphrase(confirm: bool) -> anyhow::Result<SecretString> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase.into());
+    }
+
+    let passphrase = rpassword::prompt_password("Passphrase: ")?;
+    if confirm && rpassword::prompt_password("Confirm passphrase: ")? != passphrase {
+        anyhow::bail!("passphrases don't match");
+    }
+
+    if passphrase.is_empty() {
+        anyhow::bail!("passphrase must not be empty");
+    }
+
+    Ok(passphrase.into())
+}
+
+// Parse errors are deliberately opaque, so the key never ends up in an error message.
+fn parse(data: &str) -> anyhow::Result<SecretKey> {
+    SecretKey::from_str(data.trim()).map_err(|_| anyhow::anyhow!("malformed secret key"))
+}
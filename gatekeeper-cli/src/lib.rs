@@ -1,10 +1,141 @@
 mod cli;
+mod key;
+pub mod policy;
+pub mod serve;
+pub mod snapshot;
 
-use std::str::FromStr;
+use std::{io::Write, path::Path, str::FromStr, time::Duration};
 
-pub use cli::Cli;
-use gatekeeper::{Client, Cmd};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+pub use cli::{Cli, Command};
+use gatekeeper::{
+    AccessRequest, Client, Cmd, Grant, Group, Invite, InviteTicket, Node, Role, Tenant,
+};
 use iroh::{Endpoint, NodeId, SecretKey};
+use policy::Policy;
+
+pub async fn run(cli: Cli) -> anyhow::Result<()> {
+    match cli.cmd {
+        Command::Serve { ref config } => serve::serve(config.clone()).await,
+        Command::Keygen { ref out, encrypt } => {
+            let passphrase = match encrypt {
+                true => Some(key::passphrase(true)?),
+                false => None,
+            };
+
+            let sk = key::generate();
+            key::save(out, &sk, passphrase)?;
+            println!("{}", sk.public());
+            Ok(())
+        }
+        Command::NodeId => {
+            println!("{}", secret_key(&cli)?.public());
+            Ok(())
+        }
+        Command::Plan { ref policy, prune } => {
+            let (client, caller) = connect(&cli).await?;
+            let changes = plan(&client, policy, prune, caller).await?;
+
+            if changes.is_empty() {
+                println!("no changes");
+            }
+
+            for change in changes.iter() {
+                println!("{change}");
+            }
+
+            Ok(())
+        }
+        Command::Apply { ref policy, prune } => {
+            let (client, caller) = connect(&cli).await?;
+            let changes = plan(&client, policy, prune, caller).await?;
+
+            if changes.is_empty() {
+                println!("no changes");
+            }
+
+            policy::apply(&client, &changes).await
+        }
+        Command::Export { format, ref out } => {
+            let (client, _) = connect(&cli).await?;
+            let data = snapshot::encode(&client.export().await?, format)?;
+
+            match out {
+                Some(path) => std::fs::write(path, data)?,
+                None => std::io::stdout().write_all(&data)?,
+            }
+
+            Ok(())
+        }
+        Command::Import {
+            ref file,
+            format,
+            mode,
+        } => {
+            let data = std::fs::read(file)?;
+            let snapshot = snapshot::decode(&data, format)?;
+
+            let (client, _) = connect(&cli).await?;
+            client.import(snapshot, mode).await?;
+            println!("ok");
+            Ok(())
+        }
+        Command::Remote(ref cmd) => {
+            let sk = secret_key(&cli)?;
+            let server = match (cli.server, &**cmd) {
+                (Some(server), _) => server,
+                // Invite tickets carry the server to enroll with
+                (None, Cmd::Redeem { ticket, .. }) => InviteTicket::from_str(ticket)?.server,
+                (None, _) => anyhow::bail!("--server is required"),
+            };
+
+            exec(sk, server, (**cmd).clone()).await
+        }
+    }
+}
+
+async fn connect(cli: &Cli) -> anyhow::Result<(Client, NodeId)> {
+    let sk = secret_key(cli)?;
+    let server = cli.server.context("--server is required")?;
+    let caller = sk.public();
+
+    let endpoint = Endpoint::builder()
+        .discovery_n0()
+        .secret_key(sk)
+        .bind()
+        .await?;
+
+    Ok((Client::new(endpoint, server), caller))
+}
+
+async fn plan(
+    client: &Client,
+    path: &Path,
+    prune: bool,
+    caller: NodeId,
+) -> anyhow::Result<Vec<policy::Change>> {
+    let policy = Policy::load(path)?;
+    let current = policy::fetch(client).await?;
+    policy::diff(&policy, &current, prune, caller)
+}
+
+fn secret_key(cli: &Cli) -> anyhow::Result<SecretKey> {
+    if cli.key_stdin {
+        return key::from_stdin();
+    }
+
+    if let Some(path) = &cli.key_file {
+        return key::load(path);
+    }
+
+    key::from_env()?.with_context(|| {
+        format!(
+            "no secret key, use --key-file, --key-stdin or {}",
+            key::SECRET_KEY_ENV
+        )
+    })
+}
 
 pub async fn exec(sk: SecretKey, server: NodeId, cmd: Cmd) -> anyhow::Result<()> {
     let endpoint = Endpoint::builder()
@@ -16,45 +147,495 @@ pub async fn exec(sk: SecretKey, server: NodeId, cmd: Cmd) -> anyhow::Result<()>
     let client = Client::new(endpoint, server);
 
     match cmd {
-        Cmd::Roles => {
-            let roles = client.roles().await?;
-            println!("{}", roles.join("\n"));
+        Cmd::Bootstrap { token, name, node } => {
+            let node = NodeId::from_str(&node)?;
+            let node = client.bootstrap(token, name, node).await?;
+            println!("{} {} {}", node.node, node.superadmin, node.name);
+        }
+        Cmd::WhoAmI => {
+            let whoami = client.whoami().await?;
+            let node = &whoami.node;
+            println!("{} {} {}", node.node, node.superadmin, node.name);
+
+            if let Some(tenant) = &node.tenant {
+                println!("tenant {tenant}");
+            }
+
+            for (kind, grants) in [
+                ("direct", &whoami.roles),
+                ("effective", &whoami.effective_roles),
+            ] {
+                for grant in grants.iter() {
+                    println!("{kind} {}", format_grant(grant));
+                }
+            }
+
+            for group in whoami.groups.iter() {
+                println!("group {group}");
+            }
+
+            for label in whoami.labels.iter() {
+                println!("label {label}");
+            }
+        }
+        Cmd::Roles { tenant } => {
+            println!("{}", client.roles(tenant).await?.join("\n"));
+        }
+        Cmd::ShowRole { role } => {
+            print_role(&client.show_role(role).await?);
+        }
+        Cmd::CreateRole { role, description } => {
+            print_role(&client.create_role(role, description).await?);
+        }
+        Cmd::DescribeRole { role, description } => {
+            print_role(&client.describe_role(role, description).await?);
+        }
+        Cmd::RenameRole { role, new_role } => {
+            print_role(&client.rename_role(role, new_role).await?);
+        }
+        Cmd::DeleteRole { role, cascade } => {
+            client.delete_role(role, cascade).await?;
+            println!("ok");
         }
-        Cmd::Nodes => {
-            for node in client.nodes().await?.iter() {
+        Cmd::Nodes { tenant } => {
+            for node in client.nodes(tenant).await?.iter() {
                 println!("{} {} {}", node.node, node.superadmin, node.name);
             }
         }
-        Cmd::NodeRoles { node } => {
+        Cmd::NodeRoles { node, effective } => {
             let node = NodeId::from_str(&node)?;
-            let roles = client.node_roles(node).await?;
-            println!("{}", roles.join("\n"));
+            for grant in client.node_roles(node, effective).await?.iter() {
+                println!("{}", format_grant(grant));
+            }
+        }
+        Cmd::RoleNodes {
+            role,
+            indirect,
+            tenant,
+            limit,
+            offset,
+        } => {
+            let nodes = client
+                .role_nodes(role, indirect, tenant, limit, offset)
+                .await?;
+            for node in nodes.iter() {
+                println!("{} {} {}", node.node, node.superadmin, node.name);
+            }
+
+            if nodes.len() as u32 == limit {
+                eprintln!("more nodes available with --offset {}", offset + limit);
+            }
         }
         Cmd::CreateNode {
             name,
             node,
             superadmin,
+            tenant,
+        } => {
+            let node = NodeId::from_str(&node)?;
+            let node = client.create_node(name, node, superadmin, tenant).await?;
+            println!("{} {} {}", node.node, node.superadmin, node.name);
+        }
+        Cmd::UpdateNode {
+            node,
+            name,
+            superadmin,
+            description,
         } => {
             let node = NodeId::from_str(&node)?;
-            let node = client.create_node(name, node, superadmin).await?;
+            let node = client
+                .update_node(node, name, superadmin, description)
+                .await?;
             println!("{} {} {}", node.node, node.superadmin, node.name);
         }
+        Cmd::SetAdminTier { node, tier } => {
+            let node = NodeId::from_str(&node)?;
+            let node = client.set_admin_tier(node, tier).await?;
+            println!("{} {} {}", node.node, node.superadmin, node.name);
+        }
+        Cmd::SetNodeTenant { node, tenant } => {
+            let node = NodeId::from_str(&node)?;
+            let node = client.set_node_tenant(node, tenant).await?;
+            println!("{} {} {}", node.node, node.superadmin, node.name);
+        }
+        Cmd::RotateNodeKey { node, new_node } => {
+            let node = NodeId::from_str(&node)?;
+            let new_node = NodeId::from_str(&new_node)?;
+            let node = client.rotate_node_key(node, new_node).await?;
+            println!("{} {} {}", node.node, node.superadmin, node.name);
+        }
+        Cmd::NodeKeyHistory { node } => {
+            let node = NodeId::from_str(&node)?;
+            for rotation in client.node_key_history(node).await?.iter() {
+                println!(
+                    "{} {} {} {}",
+                    format_timestamp(rotation.created),
+                    rotation.old_node,
+                    rotation.new_node,
+                    rotation.rotated_by
+                );
+            }
+        }
         Cmd::DeleteNode { node } => {
             let node = NodeId::from_str(&node)?;
             client.delete_node(node).await?;
             println!("ok");
         }
-        Cmd::GrantRole { node, role } => {
+        Cmd::GrantRole {
+            node,
+            role,
+            resource,
+            ttl,
+        } => {
+            let node = NodeId::from_str(&node)?;
+            let ttl = ttl.map(Duration::from_secs);
+            client.grant_role(node, role, resource, ttl).await?;
+            println!("ok");
+        }
+        Cmd::RevokeRole {
+            node,
+            role,
+            resource,
+        } => {
+            let node = NodeId::from_str(&node)?;
+            client.revoke_role(node, role, resource).await?;
+            println!("ok");
+        }
+        Cmd::DenyRole { node, role } => {
             let node = NodeId::from_str(&node)?;
-            client.grant_role(node, role).await?;
+            client.deny_role(node, role).await?;
             println!("ok");
         }
-        Cmd::RevokeRole { node, role } => {
+        Cmd::RemoveRoleDeny { node, role } => {
             let node = NodeId::from_str(&node)?;
-            client.revoke_role(node, role).await?;
+            client.remove_role_deny(node, role).await?;
             println!("ok");
         }
+        Cmd::RoleParents { role } => {
+            let parents = client.role_parents(role).await?;
+            println!("{}", parents.join("\n"));
+        }
+        Cmd::AddRoleParent { role, parent } => {
+            client.add_role_parent(role, parent).await?;
+            println!("ok");
+        }
+        Cmd::RemoveRoleParent { role, parent } => {
+            client.remove_role_parent(role, parent).await?;
+            println!("ok");
+        }
+        Cmd::Groups { tenant } => {
+            for group in client.groups(tenant).await?.iter() {
+                print_group(group);
+            }
+        }
+        Cmd::CreateGroup { group, description } => {
+            print_group(&client.create_group(group, description).await?);
+        }
+        Cmd::DeleteGroup { group } => {
+            client.delete_group(group).await?;
+            println!("ok");
+        }
+        Cmd::GroupMembers { group } => {
+            for node in client.group_members(group).await?.iter() {
+                println!("{} {} {}", node.node, node.superadmin, node.name);
+            }
+        }
+        Cmd::AddGroupMember { group, node } => {
+            let node = NodeId::from_str(&node)?;
+            client.add_group_member(group, node).await?;
+            println!("ok");
+        }
+        Cmd::RemoveGroupMember { group, node } => {
+            let node = NodeId::from_str(&node)?;
+            client.remove_group_member(group, node).await?;
+            println!("ok");
+        }
+        Cmd::GroupRoles { group } => {
+            let roles = client.group_roles(group).await?;
+            println!("{}", roles.join("\n"));
+        }
+        Cmd::GrantGroupRole { group, role } => {
+            client.grant_group_role(group, role).await?;
+            println!("ok");
+        }
+        Cmd::RevokeGroupRole { group, role } => {
+            client.revoke_group_role(group, role).await?;
+            println!("ok");
+        }
+        Cmd::NodeLabels { node } => {
+            let node = NodeId::from_str(&node)?;
+            for label in client.node_labels(node).await?.iter() {
+                println!("{label}");
+            }
+        }
+        Cmd::SetNodeLabel { node, label } => {
+            let node = NodeId::from_str(&node)?;
+            client.set_node_label(node, label).await?;
+            println!("ok");
+        }
+        Cmd::RemoveNodeLabel { node, key } => {
+            let node = NodeId::from_str(&node)?;
+            client.remove_node_label(node, key).await?;
+            println!("ok");
+        }
+        Cmd::SelectorGrants => {
+            for grant in client.selector_grants().await?.iter() {
+                println!(
+                    "{} {} {}",
+                    grant.selector,
+                    grant.role,
+                    format_timestamp(grant.created)
+                );
+            }
+        }
+        Cmd::GrantSelectorRole { selector, role } => {
+            client.grant_selector_role(selector, role).await?;
+            println!("ok");
+        }
+        Cmd::RevokeSelectorRole { selector, role } => {
+            client.revoke_selector_role(selector, role).await?;
+            println!("ok");
+        }
+        Cmd::Tenants => {
+            for tenant in client.tenants().await?.iter() {
+                print_tenant(tenant);
+            }
+        }
+        Cmd::CreateTenant {
+            tenant,
+            description,
+        } => {
+            print_tenant(&client.create_tenant(tenant, description).await?);
+        }
+        Cmd::DeleteTenant { tenant } => {
+            client.delete_tenant(tenant).await?;
+            println!("ok");
+        }
+        Cmd::Check { checks } => {
+            let triples = checks
+                .iter()
+                .map(|c| {
+                    let node = NodeId::from_str(&c.node)?;
+                    Ok((node, c.role.clone(), c.resource.clone()))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            let res = client.check(triples).await?;
+            for (check, held) in checks.iter().zip(res) {
+                match &check.resource {
+                    None => println!("{} {} {}", check.node, check.role, held),
+                    Some(resource) => {
+                        println!("{} {}@{} {}", check.node, check.role, resource, held)
+                    }
+                }
+            }
+        }
+        Cmd::Export => {
+            let snapshot = client.export().await?;
+            println!("{}", serde_json::to_string_pretty(&snapshot)?);
+        }
+        Cmd::Import { snapshot, mode } => {
+            client.import(snapshot, mode).await?;
+            println!("ok");
+        }
+        Cmd::CreateInvite { roles, ttl, uses } => {
+            let invite = client
+                .create_invite(roles, Duration::from_secs(ttl), uses)
+                .await?;
+            let ticket = invite
+                .ticket(server)
+                .context("server did not return an invite secret")?;
+            println!("{ticket}");
+        }
+        Cmd::Invites => {
+            for invite in client.invites().await?.iter() {
+                println!(
+                    "{} {} {} {}",
+                    invite.id,
+                    format_timestamp(invite.expires_at),
+                    invite.uses_left,
+                    invite.roles.join(",")
+                );
+            }
+        }
+        Cmd::DeleteInvite { id } => {
+            client.delete_invite(id).await?;
+            println!("ok");
+        }
+        Cmd::Redeem { ticket, name } => {
+            let ticket = InviteTicket::from_str(&ticket)?;
+            let node = client.redeem(&ticket, name).await?;
+            println!("{} {} {}", node.node, node.superadmin, node.name);
+        }
+        Cmd::RequestRole {
+            role,
+            justification,
+            ttl,
+        } => {
+            let ttl = ttl.map(Duration::from_secs);
+            print_request(&client.request_role(role, justification, ttl).await?);
+        }
+        Cmd::ListRequests { all } => {
+            for request in client.list_requests(all).await?.iter() {
+                print_request(request);
+            }
+        }
+        Cmd::Approve { id, note } => {
+            print_request(&client.approve(id, note).await?);
+        }
+        Cmd::Deny { id, note } => {
+            print_request(&client.deny(id, note).await?);
+        }
+        Cmd::PendingOps => {
+            for op in client.pending_ops().await?.iter() {
+                println!(
+                    "{} {} {} {} {}",
+                    op.id,
+                    op.requested_by,
+                    op.cmd,
+                    format_remaining(op.expires_at),
+                    op.args
+                );
+            }
+        }
+        Cmd::Confirm { id } => {
+            // The response is that of the held command, so look up what it was
+            let held = client
+                .pending_ops()
+                .await?
+                .into_iter()
+                .find(|op| op.id == id)
+                .map(|op| op.cmd)
+                .unwrap_or_default();
+
+            match held.as_str() {
+                "create-node" | "update-node" | "set-admin-tier" | "set-node-tenant"
+                | "rotate-node-key" => {
+                    let node: Node = client.confirm(id).await?;
+                    println!("{} {} {}", node.node, node.superadmin, node.name);
+                }
+                "create-role" | "describe-role" | "rename-role" => {
+                    print_role(&client.confirm(id).await?);
+                }
+                "create-group" => print_group(&client.confirm(id).await?),
+                "create-tenant" => print_tenant(&client.confirm(id).await?),
+                "create-invite" => {
+                    let invite: Invite = client.confirm(id).await?;
+                    let ticket = invite
+                        .ticket(server)
+                        .context("server did not return an invite secret")?;
+                    println!("{ticket}");
+                }
+                "approve" | "deny" => print_request(&client.confirm(id).await?),
+                _ => {
+                    client.confirm::<()>(id).await?;
+                    println!("ok");
+                }
+            }
+        }
+        Cmd::CancelOp { id } => {
+            client.cancel_op(id).await?;
+            println!("ok");
+        }
+        Cmd::AuditLog(filter) => {
+            let (limit, offset) = (filter.limit, filter.offset);
+            let entries = client.audit_log(filter).await?;
+            for entry in entries.iter() {
+                println!(
+                    "{} {} {} {} {} {}",
+                    entry.id,
+                    format_timestamp(entry.created),
+                    entry.caller,
+                    entry.cmd,
+                    entry.outcome,
+                    entry.args
+                );
+            }
+
+            if entries.len() as u32 == limit {
+                eprintln!("more entries available with --offset {}", offset + limit);
+            }
+        }
     }
 
     Ok(())
 }
+
+fn print_role(role: &Role) {
+    let created = format_timestamp(role.created);
+    match &role.description {
+        None => println!("{} {}", role.role, created),
+        Some(description) => println!("{} {} {}", role.role, created, description),
+    }
+}
+
+fn print_group(group: &Group) {
+    let created = format_timestamp(group.created);
+    match &group.description {
+        None => println!("{} {}", group.name, created),
+        Some(description) => println!("{} {} {}", group.name, created, description),
+    }
+}
+
+fn print_tenant(tenant: &Tenant) {
+    let created = format_timestamp(tenant.created);
+    match &tenant.description {
+        None => println!("{} {}", tenant.name, created),
+        Some(description) => println!("{} {} {}", tenant.name, created, description),
+    }
+}
+
+fn print_request(request: &AccessRequest) {
+    println!(
+        "{} {} {} {} {} {}",
+        request.id,
+        format_timestamp(request.created),
+        request.status.as_str(),
+        request.node,
+        request.role,
+        request.justification
+    );
+}
+
+fn format_grant(grant: &Grant) -> String {
+    let role = match &grant.resource {
+        None => grant.role.clone(),
+        Some(resource) => format!("{}@{}", grant.role, resource),
+    };
+
+    match (grant.deny, grant.expires_at) {
+        (true, _) => format!("{role} denied"),
+        (false, None) => role,
+        (false, Some(expires_at)) => format!("{role} {}", format_remaining(expires_at)),
+    }
+}
+
+fn format_timestamp(t: i64) -> String {
+    match DateTime::from_timestamp(t, 0) {
+        Some(t) => t.to_rfc3339(),
+        None => t.to_string(),
+    }
+}
+
+fn format_remaining(expires_at: i64) -> String {
+    let remaining = expires_at - Utc::now().timestamp();
+    if remaining <= 0 {
+        return "(expired)".to_string();
+    }
+
+    let (d, h, m, s) = (
+        remaining / 86_400,
+        remaining % 86_400 / 3_600,
+        remaining % 3_600 / 60,
+        remaining % 60,
+    );
+
+    let left = match (d, h, m) {
+        (0, 0, 0) => format!("{s}s"),
+        (0, 0, _) => format!("{m}m{s}s"),
+        (0, _, _) => format!("{h}h{m}m"),
+        _ => format!("{d}d{h}h"),
+    };
+
+    format!("(expires in {left})")
+}
@@ -0,0 +1,249 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    path::Path,
+};
+
+use anyhow::Context;
+use gatekeeper::{Client, Grant, Node};
+use iroh::NodeId;
+use serde::Deserialize;
+
+/// Desired server state: every node listed, with exactly these superadmin flags and grants.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default)]
+    pub nodes: Vec<PolicyNode>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyNode {
+    pub name: String,
+    pub node: NodeId,
+    #[serde(default)]
+    pub superadmin: bool,
+    #[serde(default)]
+    pub roles: BTreeSet<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    /// Name or superadmin changes, applied in place so grants are kept. Fields
+    /// that don't change are `None`.
+    UpdateNode {
+        name: Option<String>,
+        node: NodeId,
+        superadmin: Option<bool>,
+    },
+    DeleteNode {
+        name: String,
+        node: NodeId,
+    },
+    CreateNode {
+        name: String,
+        node: NodeId,
+        superadmin: bool,
+    },
+    RevokeRole {
+        node: NodeId,
+        role: String,
+    },
+    GrantRole {
+        node: NodeId,
+        role: String,
+    },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UpdateNode {
+                name,
+                node,
+                superadmin,
+            } => {
+                write!(f, "~ node {node}")?;
+                if let Some(superadmin) = superadmin {
+                    write!(f, " superadmin={superadmin}")?;
+                }
+                if let Some(name) = name {
+                    write!(f, " name={name}")?;
+                }
+                Ok(())
+            }
+            Self::DeleteNode { name, node } => write!(f, "- node {node} {name}"),
+            Self::CreateNode {
+                name,
+                node,
+                superadmin,
+            } => write!(f, "+ node {node} {superadmin} {name}"),
+            Self::RevokeRole { node, role } => write!(f, "- grant {node} {role}"),
+            Self::GrantRole { node, role } => write!(f, "+ grant {node} {role}"),
+        }
+    }
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read policy file {}", path.display()))?;
+
+        let policy: Policy = toml::from_str(&data)
+            .with_context(|| format!("invalid policy file {}", path.display()))?;
+
+        let mut names = HashSet::new();
+        let mut nodes = HashSet::new();
+        for node in policy.nodes.iter() {
+            if !names.insert(&node.name) {
+                anyhow::bail!("duplicate node name {} in policy", node.name);
+            }
+
+            if !nodes.insert(node.node) {
+                anyhow::bail!("duplicate node {} in policy", node.node);
+            }
+        }
+
+        Ok(policy)
+    }
+}
+
+/// Current server state, as seen through the client.
+pub async fn fetch(client: &Client) -> anyhow::Result<Vec<(Node, Vec<Grant>)>> {
+    let mut res = vec![];
+    for node in client.nodes(None).await? {
+        let node_id: NodeId = node.node.parse()?;
+        // Denies and resource-scoped grants aren't managed by policy files
+        let mut grants = client.node_roles(node_id, false).await?;
+        grants.retain(|g| !g.deny && g.resource.is_none());
+        res.push((node, grants));
+    }
+
+    Ok(res)
+}
+
+/// Changes needed to bring the current state in line with the policy. Nodes and
+/// grants missing from the policy are only removed when pruning, and the calling
+/// node is never deleted, to avoid locking it out.
+pub fn diff(
+    policy: &Policy,
+    current: &[(Node, Vec<Grant>)],
+    prune: bool,
+    caller: NodeId,
+) -> anyhow::Result<Vec<Change>> {
+    let current: HashMap<String, &(Node, Vec<Grant>)> =
+        current.iter().map(|c| (c.0.node.clone(), c)).collect();
+
+    let mut changes = vec![];
+    for desired in policy.nodes.iter() {
+        let granted = match current.get(&format!("{}", desired.node)) {
+            None => {
+                changes.push(Change::CreateNode {
+                    name: desired.name.clone(),
+                    node: desired.node,
+                    superadmin: desired.superadmin,
+                });
+                vec![]
+            }
+            Some((node, grants)) => {
+                if node.name != desired.name || node.superadmin != desired.superadmin {
+                    if desired.node == caller && node.superadmin && !desired.superadmin {
+                        anyhow::bail!("refusing to demote the calling node {}", desired.node);
+                    }
+
+                    changes.push(Change::UpdateNode {
+                        name: (node.name != desired.name).then(|| desired.name.clone()),
+                        node: desired.node,
+                        superadmin: (node.superadmin != desired.superadmin)
+                            .then_some(desired.superadmin),
+                    });
+                }
+
+                grants.iter().collect()
+            }
+        };
+
+        for role in desired.roles.iter() {
+            // Time-bounded grants of a listed role are made permanent
+            if !granted
+                .iter()
+                .any(|g| &g.role == role && g.expires_at.is_none())
+            {
+                changes.push(Change::GrantRole {
+                    node: desired.node,
+                    role: role.clone(),
+                });
+            }
+        }
+
+        if prune {
+            for grant in granted.iter().filter(|g| !desired.roles.contains(&g.role)) {
+                changes.push(Change::RevokeRole {
+                    node: desired.node,
+                    role: grant.role.clone(),
+                });
+            }
+        }
+    }
+
+    if prune {
+        let desired: HashSet<String> = policy.nodes.iter().map(|n| format!("{}", n.node)).collect();
+        for (node, _) in current.values() {
+            if desired.contains(&node.node) {
+                continue;
+            }
+
+            let node_id: NodeId = node.node.parse()?;
+            if node_id != caller {
+                changes.push(Change::DeleteNode {
+                    name: node.name.clone(),
+                    node: node_id,
+                });
+            }
+        }
+    }
+
+    // Deletions first, so names they free up can be reused by creations
+    changes.sort_by_key(|c| match c {
+        Change::DeleteNode { .. } => 0,
+        Change::UpdateNode { .. } => 1,
+        Change::CreateNode { .. } => 2,
+        Change::RevokeRole { .. } => 3,
+        Change::GrantRole { .. } => 4,
+    });
+
+    Ok(changes)
+}
+
+/// Make the changes one at a time, in order. This isn't atomic: a failure stops
+/// the run with the earlier changes already applied, and running `apply` again
+/// picks up from there. Renames are made in place, so swapping the names of two
+/// nodes fails on the second rename and needs an intermediate name.
+pub async fn apply(client: &Client, changes: &[Change]) -> anyhow::Result<()> {
+    for change in changes.iter() {
+        match change.clone() {
+            Change::UpdateNode {
+                name,
+                node,
+                superadmin,
+            } => {
+                client.update_node(node, name, superadmin, None).await?;
+            }
+            Change::DeleteNode { node, .. } => client.delete_node(node).await?,
+            Change::CreateNode {
+                name,
+                node,
+                superadmin,
+            } => {
+                client.create_node(name, node, superadmin, None).await?;
+            }
+            Change::RevokeRole { node, role } => client.revoke_role(node, role, None).await?,
+            Change::GrantRole { node, role } => client.grant_role(node, role, None, None).await?,
+        }
+
+        println!("{change}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use gatekeeper::{ALPN, Arbiter, Cmd, Server, parse_ttl};
+use iroh::{Endpoint, RelayMap, RelayMode, RelayUrl, protocol::Router};
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::key;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// SQLite database, created if missing
+    pub db_path: PathBuf,
+    /// Server secret key, generated if missing
+    pub secret_key_path: PathBuf,
+    /// Generate a one-time bootstrap token on an empty database, letting a
+    /// remote node create the first superadmin
+    #[serde(default)]
+    pub remote_setup: bool,
+    /// File to write the bootstrap token to, rather than logging it. Deleted
+    /// once the token has been used.
+    pub bootstrap_token_path: Option<PathBuf>,
+    /// Require roles to be created before they can be granted or inherited
+    #[serde(default)]
+    pub strict_roles: bool,
+    /// Let nodes rotate their own key, authenticating with the old key
+    #[serde(default)]
+    pub self_service_rotation: bool,
+    /// Commands that must be confirmed by a second superadmin, e.g. `delete-node`
+    /// or `create-superadmin`
+    #[serde(default)]
+    pub two_person_rule: Vec<String>,
+    /// How long a held command can be confirmed for, e.g. `15m`
+    #[serde(default = "default_confirm_window")]
+    pub confirm_window: String,
+    #[serde(default)]
+    pub relay_mode: RelayModeConfig,
+    /// Relay servers to use when `relay_mode` is `custom`
+    #[serde(default)]
+    pub relay_urls: Vec<RelayUrl>,
+    /// Log filter, e.g. `info` or `gatekeeper=debug,iroh=warn`
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// How often to purge expired grants, e.g. `5m`
+    #[serde(default = "default_sweep_interval")]
+    pub sweep_interval: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RelayModeConfig {
+    #[default]
+    Default,
+    Staging,
+    Disabled,
+    Custom,
+}
+
+impl Config {
+    pub fn load(path: &PathBuf) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+
+        let config: Config = toml::from_str(&data)
+            .with_context(|| format!("invalid config file {}", path.display()))?;
+
+        // A misspelt entry would silently leave the command unprotected
+        if let Some(rule) = config.two_person_rule.iter().find(|r| !Cmd::is_rule(r)) {
+            anyhow::bail!(
+                "unknown two_person_rule entry {rule} in config file {}",
+                path.display()
+            );
+        }
+
+        if let Err(e) = parse_ttl(&config.confirm_window) {
+            anyhow::bail!(
+                "invalid confirm_window in config file {}: {e}",
+                path.display()
+            );
+        }
+
+        Ok(config)
+    }
+
+    fn relay_mode(&self) -> anyhow::Result<RelayMode> {
+        let mode = match self.relay_mode {
+            RelayModeConfig::Default => RelayMode::Default,
+            RelayModeConfig::Staging => RelayMode::Staging,
+            RelayModeConfig::Disabled => RelayMode::Disabled,
+            RelayModeConfig::Custom if self.relay_urls.is_empty() => {
+                anyhow::bail!("relay_mode is custom but no relay_urls are configured")
+            }
+            RelayModeConfig::Custom => {
+                RelayMode::Custom(RelayMap::from_iter(self.relay_urls.iter().cloned()))
+            }
+        };
+
+        Ok(mode)
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_confirm_window() -> String {
+    "15m".to_string()
+}
+
+fn default_sweep_interval() -> String {
+    "5m".to_string()
+}
+
+pub async fn serve(config: PathBuf) -> anyhow::Result<()> {
+    let config = Config::load(&config)?;
+
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_new(&config.log_level)?)
+        .with_writer(std::io::stderr)
+        .init();
+
+    let sweep_interval = parse_ttl(&config.sweep_interval).map_err(anyhow::Error::msg)?;
+    let confirm_window = parse_ttl(&config.confirm_window).map_err(anyhow::Error::msg)?;
+    let sk = key::load_or_generate(&config.secret_key_path)?;
+
+    let endpoint = Endpoint::builder()
+        .discovery_n0()
+        .relay_mode(config.relay_mode()?)
+        .secret_key(sk)
+        .bind()
+        .await?;
+
+    let arbiter = Arbiter::new(config.db_path.clone(), config.remote_setup)
+        .await?
+        .with_strict_roles(config.strict_roles)
+        .with_self_service_rotation(config.self_service_rotation)
+        .with_two_person_rule(
+            config.two_person_rule.clone(),
+            Duration::from_secs(confirm_window),
+        );
+    if let Some(token) = arbiter.bootstrap_token().await {
+        match &config.bootstrap_token_path {
+            None => tracing::warn!(token = %token, "bootstrap_token"),
+            Some(path) => {
+                write_bootstrap_token(path, &token)?;
+                tracing::warn!(path = %path.display(), "bootstrap_token_written");
+                tokio::spawn(remove_spent_bootstrap_token(arbiter.clone(), path.clone()));
+            }
+        }
+    }
+
+    let sweeper = arbiter.spawn_sweeper(Duration::from_secs(sweep_interval));
+
+    let router = Router::builder(endpoint)
+        .accept(ALPN, Server::new(arbiter))
+        .spawn();
+
+    let node_id = router.endpoint().node_id();
+    tracing::info!(node_id = %node_id, "serving");
+    println!("{node_id}");
+
+    shutdown_signal().await?;
+
+    tracing::info!("shutting_down");
+    sweeper.abort();
+    router.shutdown().await?;
+
+    Ok(())
+}
+
+/// Wait for SIGTERM or ctrl-c.
+#[cfg(unix)]
+async fn shutdown_signal() -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = sigterm.recv() => {}
+        res = tokio::signal::ctrl_c() => res?,
+    }
+
+    Ok(())
+}
+
+/// Wait for ctrl-c.
+#[cfg(not(unix))]
+async fn shutdown_signal() -> anyhow::Result<()> {
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+fn write_bootstrap_token(path: &Path, token: &str) -> anyhow::Result<()> {
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut opts, 0o600);
+
+    let mut file = opts
+        .open(path)
+        .with_context(|| format!("failed to write bootstrap token to {}", path.display()))?;
+
+    file.write_all(format!("{token}\n").as_bytes())?;
+    Ok(())
+}
+
+/// Delete the bootstrap token file once the token has been used or invalidated.
+pub async fn remove_spent_bootstrap_token(arbiter: Arbiter, path: PathBuf) {
+    arbiter.bootstrap_spent().await;
+
+    match std::fs::remove_file(&path) {
+        Ok(()) => tracing::info!(path = %path.display(), "bootstrap_token_removed"),
+        Err(e) => tracing::warn!(path = %path.display(), err = ?e, "bootstrap_token_remove_failed"),
+    }
+}
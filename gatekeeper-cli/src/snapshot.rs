@@ -0,0 +1,347 @@
+use anyhow::Context;
+use clap::ValueEnum;
+use gatekeeper::{
+    AdminTier, SNAPSHOT_VERSION, Snapshot, SnapshotDeny, SnapshotGrant, SnapshotGroup,
+    SnapshotLabel, SnapshotNode, SnapshotRole, SnapshotRoleParent, SnapshotSelectorGrant,
+    SnapshotTenant,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Format {
+    Json,
+    /// One row per node, role, role parent, grant, group, group member, group
+    /// role, deny, label, selector grant and tenant, distinguished by the
+    /// `record` column
+    Csv,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CsvRecord {
+    version: u32,
+    record: String,
+    name: Option<String>,
+    node: Option<String>,
+    superadmin: Option<bool>,
+    #[serde(default)]
+    admin_tier: Option<AdminTier>,
+    role: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    parent: Option<String>,
+    expires_at: Option<i64>,
+    created: Option<i64>,
+    #[serde(default)]
+    resource: Option<String>,
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default)]
+    selector: Option<String>,
+    #[serde(default)]
+    tenant: Option<String>,
+}
+
+pub fn encode(snapshot: &Snapshot, format: Format) -> anyhow::Result<Vec<u8>> {
+    match format {
+        Format::Json => Ok(serde_json::to_vec_pretty(snapshot)?),
+        Format::Csv => to_csv(snapshot),
+    }
+}
+
+pub fn decode(data: &[u8], format: Format) -> anyhow::Result<Snapshot> {
+    match format {
+        Format::Json => Ok(serde_json::from_slice(data)?),
+        Format::Csv => from_csv(data),
+    }
+}
+
+fn to_csv(snapshot: &Snapshot) -> anyhow::Result<Vec<u8>> {
+    let version = snapshot.version;
+    let mut writer = csv::Writer::from_writer(vec![]);
+
+    for tenant in snapshot.tenants.iter() {
+        writer.serialize(CsvRecord {
+            version,
+            record: "tenant".to_string(),
+            name: Some(tenant.name.clone()),
+            description: tenant.description.clone(),
+            created: Some(tenant.created),
+            ..Default::default()
+        })?;
+    }
+
+    for node in snapshot.nodes.iter() {
+        writer.serialize(CsvRecord {
+            version,
+            record: "node".to_string(),
+            name: Some(node.name.clone()),
+            node: Some(node.node.clone()),
+            superadmin: Some(node.superadmin),
+            admin_tier: node.admin_tier,
+            description: node.description.clone(),
+            tenant: node.tenant.clone(),
+            created: Some(node.created),
+            ..Default::default()
+        })?;
+    }
+
+    for role in snapshot.roles.iter() {
+        writer.serialize(CsvRecord {
+            version,
+            record: "role".to_string(),
+            role: Some(role.role.clone()),
+            description: role.description.clone(),
+            created: Some(role.created),
+            ..Default::default()
+        })?;
+    }
+
+    for role_parent in snapshot.role_parents.iter() {
+        writer.serialize(CsvRecord {
+            version,
+            record: "role_parent".to_string(),
+            role: Some(role_parent.role.clone()),
+            parent: Some(role_parent.parent.clone()),
+            ..Default::default()
+        })?;
+    }
+
+    for grant in snapshot.grants.iter() {
+        writer.serialize(CsvRecord {
+            version,
+            record: "grant".to_string(),
+            node: Some(grant.node.clone()),
+            role: Some(grant.role.clone()),
+            expires_at: grant.expires_at,
+            created: grant.created,
+            resource: grant.resource.clone(),
+            ..Default::default()
+        })?;
+    }
+
+    for group in snapshot.groups.iter() {
+        writer.serialize(CsvRecord {
+            version,
+            record: "group".to_string(),
+            name: Some(group.name.clone()),
+            description: group.description.clone(),
+            created: Some(group.created),
+            ..Default::default()
+        })?;
+
+        for node in group.members.iter() {
+            writer.serialize(CsvRecord {
+                version,
+                record: "group_member".to_string(),
+                name: Some(group.name.clone()),
+                node: Some(node.clone()),
+                ..Default::default()
+            })?;
+        }
+
+        for role in group.roles.iter() {
+            writer.serialize(CsvRecord {
+                version,
+                record: "group_role".to_string(),
+                name: Some(group.name.clone()),
+                role: Some(role.clone()),
+                ..Default::default()
+            })?;
+        }
+    }
+
+    for deny in snapshot.denies.iter() {
+        writer.serialize(CsvRecord {
+            version,
+            record: "deny".to_string(),
+            node: Some(deny.node.clone()),
+            role: Some(deny.role.clone()),
+            created: Some(deny.created),
+            ..Default::default()
+        })?;
+    }
+
+    for label in snapshot.labels.iter() {
+        writer.serialize(CsvRecord {
+            version,
+            record: "label".to_string(),
+            node: Some(label.node.clone()),
+            key: Some(label.key.clone()),
+            value: Some(label.value.clone()),
+            created: Some(label.created),
+            ..Default::default()
+        })?;
+    }
+
+    for grant in snapshot.selector_grants.iter() {
+        writer.serialize(CsvRecord {
+            version,
+            record: "selector_grant".to_string(),
+            selector: Some(grant.selector.clone()),
+            role: Some(grant.role.clone()),
+            created: Some(grant.created),
+            ..Default::default()
+        })?;
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+fn from_csv(data: &[u8]) -> anyhow::Result<Snapshot> {
+    let mut snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        nodes: vec![],
+        roles: vec![],
+        role_parents: vec![],
+        grants: vec![],
+        groups: vec![],
+        denies: vec![],
+        labels: vec![],
+        selector_grants: vec![],
+        tenants: vec![],
+    };
+
+    let mut reader = csv::Reader::from_reader(data);
+    for (i, record) in reader.deserialize::<CsvRecord>().enumerate() {
+        let record = record?;
+        let line = i + 2;
+
+        if i == 0 {
+            snapshot.version = record.version;
+        } else if record.version != snapshot.version {
+            anyhow::bail!("line {line}: mixed snapshot versions");
+        }
+
+        match record.record.as_str() {
+            "node" => snapshot.nodes.push(SnapshotNode {
+                name: record
+                    .name
+                    .with_context(|| format!("line {line}: missing name"))?,
+                node: record
+                    .node
+                    .with_context(|| format!("line {line}: missing node"))?,
+                superadmin: record.superadmin.unwrap_or(false),
+                admin_tier: record.admin_tier,
+                description: record.description,
+                tenant: record.tenant,
+                created: record
+                    .created
+                    .with_context(|| format!("line {line}: missing created"))?,
+            }),
+            "role" => snapshot.roles.push(SnapshotRole {
+                role: record
+                    .role
+                    .with_context(|| format!("line {line}: missing role"))?,
+                description: record.description,
+                created: record
+                    .created
+                    .with_context(|| format!("line {line}: missing created"))?,
+            }),
+            "role_parent" => snapshot.role_parents.push(SnapshotRoleParent {
+                role: record
+                    .role
+                    .with_context(|| format!("line {line}: missing role"))?,
+                parent: record
+                    .parent
+                    .with_context(|| format!("line {line}: missing parent"))?,
+            }),
+            "grant" => snapshot.grants.push(SnapshotGrant {
+                node: record
+                    .node
+                    .with_context(|| format!("line {line}: missing node"))?,
+                role: record
+                    .role
+                    .with_context(|| format!("line {line}: missing role"))?,
+                expires_at: record.expires_at,
+                created: record.created,
+                resource: record.resource,
+            }),
+            "group" => snapshot.groups.push(SnapshotGroup {
+                name: record
+                    .name
+                    .with_context(|| format!("line {line}: missing name"))?,
+                description: record.description,
+                members: vec![],
+                roles: vec![],
+                created: record
+                    .created
+                    .with_context(|| format!("line {line}: missing created"))?,
+            }),
+            "group_member" => group(&mut snapshot, record.name, line)?.members.push(
+                record
+                    .node
+                    .with_context(|| format!("line {line}: missing node"))?,
+            ),
+            "group_role" => group(&mut snapshot, record.name, line)?.roles.push(
+                record
+                    .role
+                    .with_context(|| format!("line {line}: missing role"))?,
+            ),
+            "deny" => snapshot.denies.push(SnapshotDeny {
+                node: record
+                    .node
+                    .with_context(|| format!("line {line}: missing node"))?,
+                role: record
+                    .role
+                    .with_context(|| format!("line {line}: missing role"))?,
+                created: record
+                    .created
+                    .with_context(|| format!("line {line}: missing created"))?,
+            }),
+            "tenant" => snapshot.tenants.push(SnapshotTenant {
+                name: record
+                    .name
+                    .with_context(|| format!("line {line}: missing name"))?,
+                description: record.description,
+                created: record
+                    .created
+                    .with_context(|| format!("line {line}: missing created"))?,
+            }),
+            "label" => snapshot.labels.push(SnapshotLabel {
+                node: record
+                    .node
+                    .with_context(|| format!("line {line}: missing node"))?,
+                key: record
+                    .key
+                    .with_context(|| format!("line {line}: missing key"))?,
+                value: record
+                    .value
+                    .with_context(|| format!("line {line}: missing value"))?,
+                created: record
+                    .created
+                    .with_context(|| format!("line {line}: missing created"))?,
+            }),
+            "selector_grant" => snapshot.selector_grants.push(SnapshotSelectorGrant {
+                selector: record
+                    .selector
+                    .with_context(|| format!("line {line}: missing selector"))?,
+                role: record
+                    .role
+                    .with_context(|| format!("line {line}: missing role"))?,
+                created: record
+                    .created
+                    .with_context(|| format!("line {line}: missing created"))?,
+            }),
+            other => anyhow::bail!("line {line}: unknown record type {other:?}"),
+        }
+    }
+
+    Ok(snapshot)
+}
+
+/// The group a `group_member` or `group_role` row belongs to, which must
+/// appear in an earlier `group` row.
+fn group(
+    snapshot: &mut Snapshot,
+    name: Option<String>,
+    line: usize,
+) -> anyhow::Result<&mut SnapshotGroup> {
+    let name = name.with_context(|| format!("line {line}: missing name"))?;
+    snapshot
+        .groups
+        .iter_mut()
+        .find(|g| g.name == name)
+        .with_context(|| format!("line {line}: unknown group {name:?}"))
+}
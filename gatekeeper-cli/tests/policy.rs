@@ -0,0 +1,154 @@
+use gatekeeper::{Grant, Node};
+use gatekeeper_cli::policy::{Change, Policy, PolicyNode, diff};
+use iroh::{NodeId, SecretKey};
+
+fn node_id() -> NodeId {
+    SecretKey::generate(&mut rand::thread_rng()).public()
+}
+
+fn node(name: &str, node: NodeId, superadmin: bool) -> Node {
+    Node {
+        name: name.to_string(),
+        node: format!("{node}"),
+        superadmin,
+        admin_tier: None,
+        description: None,
+        tenant: None,
+    }
+}
+
+fn grant(role: &str, expires_at: Option<i64>) -> Grant {
+    Grant {
+        role: role.to_string(),
+        expires_at,
+        deny: false,
+        resource: None,
+    }
+}
+
+fn policy_node(name: &str, node: NodeId, superadmin: bool, roles: &[&str]) -> PolicyNode {
+    PolicyNode {
+        name: name.to_string(),
+        node,
+        superadmin,
+        roles: roles.iter().map(|r| r.to_string()).collect(),
+    }
+}
+
+#[test]
+fn policy_diff() {
+    let (admin, kept, changed, missing, extra) =
+        (node_id(), node_id(), node_id(), node_id(), node_id());
+
+    let policy = Policy {
+        nodes: vec![
+            policy_node("admin", admin, true, &[]),
+            policy_node("kept", kept, false, &["a", "b", "c"]),
+            policy_node("changed", changed, true, &["a"]),
+            policy_node("missing", missing, false, &["a"]),
+        ],
+    };
+
+    let current = vec![
+        (node("admin", admin, true), vec![]),
+        (
+            node("kept", kept, false),
+            vec![grant("a", None), grant("b", Some(0)), grant("z", None)],
+        ),
+        (node("changed", changed, false), vec![grant("a", None)]),
+        (node("extra", extra, false), vec![]),
+    ];
+
+    let changes = diff(&policy, &current, false, admin).unwrap();
+    assert_eq!(
+        changes,
+        vec![
+            Change::UpdateNode {
+                name: None,
+                node: changed,
+                superadmin: Some(true)
+            },
+            Change::CreateNode {
+                name: "missing".to_string(),
+                node: missing,
+                superadmin: false
+            },
+            Change::GrantRole {
+                node: kept,
+                role: "b".to_string()
+            },
+            Change::GrantRole {
+                node: kept,
+                role: "c".to_string()
+            },
+            Change::GrantRole {
+                node: missing,
+                role: "a".to_string()
+            },
+        ]
+    );
+
+    let changes = diff(&policy, &current, true, admin).unwrap();
+    assert_eq!(
+        changes[0],
+        Change::DeleteNode {
+            name: "extra".to_string(),
+            node: extra
+        }
+    );
+    assert!(changes.contains(&Change::RevokeRole {
+        node: kept,
+        role: "z".to_string()
+    }));
+    assert_eq!(changes.len(), 7);
+}
+
+#[test]
+fn policy_diff_protects_caller() {
+    let (admin, other) = (node_id(), node_id());
+
+    let policy = Policy {
+        nodes: vec![policy_node("other", other, false, &[])],
+    };
+
+    let current = vec![
+        (node("admin", admin, true), vec![]),
+        (node("other", other, false), vec![]),
+    ];
+
+    let changes = diff(&policy, &current, true, admin).unwrap();
+    assert!(changes.is_empty());
+
+    let policy = Policy {
+        nodes: vec![policy_node("admin", admin, false, &[])],
+    };
+
+    assert!(diff(&policy, &current, true, admin).is_err());
+}
+
+#[test]
+fn policy_diff_only_updates_changed_fields() {
+    let (admin, other) = (node_id(), node_id());
+
+    let policy = Policy {
+        nodes: vec![
+            policy_node("admin", admin, true, &[]),
+            policy_node("renamed", other, true, &[]),
+        ],
+    };
+
+    let current = vec![
+        (node("admin", admin, true), vec![]),
+        (node("other", other, true), vec![]),
+    ];
+
+    let changes = diff(&policy, &current, false, admin).unwrap();
+    assert_eq!(
+        changes,
+        vec![Change::UpdateNode {
+            name: Some("renamed".to_string()),
+            node: other,
+            superadmin: None
+        }]
+    );
+}
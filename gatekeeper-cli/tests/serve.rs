@@ -0,0 +1,49 @@
+use std::{path::PathBuf, time::Duration};
+
+use gatekeeper::Arbiter;
+use gatekeeper_cli::serve::{Config, remove_spent_bootstrap_token};
+
+#[tokio::test]
+async fn bootstrap_token_file_removed() {
+    let id: u64 = rand::random();
+    let db_path = PathBuf::from(format!("test-{id}.db"));
+    let token_path = PathBuf::from(format!("test-{id}.token"));
+
+    let arbiter = Arbiter::new(db_path.clone(), true).await.unwrap();
+    let token = arbiter.bootstrap_token().await.unwrap();
+    std::fs::write(&token_path, &token).unwrap();
+
+    let task = tokio::spawn(remove_spent_bootstrap_token(
+        arbiter.clone(),
+        token_path.clone(),
+    ));
+
+    // A failed attempt leaves the token usable
+    assert!(arbiter.bootstrap("wrong", "self", "node").await.is_err());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(token_path.exists());
+
+    arbiter.bootstrap(&token, "self", "node").await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), task)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!token_path.exists());
+
+    std::fs::remove_file(&db_path).unwrap();
+}
+
+#[test]
+fn confirm_window_validated() {
+    let path = PathBuf::from(format!("test-{}.toml", rand::random::<u64>()));
+
+    for (window, valid) in [("15m", true), ("36501d", false), ("soon", false)] {
+        let config = format!(
+            "db_path = \"gatekeeper.db\"\nsecret_key_path = \"server.key\"\nconfirm_window = \"{window}\"\n"
+        );
+        std::fs::write(&path, config).unwrap();
+        assert_eq!(Config::load(&path).is_ok(), valid, "{window}");
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
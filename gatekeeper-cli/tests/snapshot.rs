@@ -0,0 +1,96 @@
+use gatekeeper::{
+    AdminTier, SNAPSHOT_VERSION, Snapshot, SnapshotDeny, SnapshotGrant, SnapshotGroup,
+    SnapshotLabel, SnapshotNode, SnapshotRole, SnapshotRoleParent, SnapshotSelectorGrant,
+    SnapshotTenant,
+};
+use gatekeeper_cli::snapshot::{Format, decode, encode};
+
+fn snapshot() -> Snapshot {
+    Snapshot {
+        version: SNAPSHOT_VERSION,
+        nodes: vec![SnapshotNode {
+            name: "a, with a comma".to_string(),
+            node: "node-a".to_string(),
+            superadmin: true,
+            admin_tier: Some(AdminTier::Operator),
+            description: Some("Admin laptop".to_string()),
+            tenant: Some("infra".to_string()),
+            created: 1_754_000_000,
+        }],
+        roles: vec![
+            SnapshotRole {
+                role: "read".to_string(),
+                description: Some("Read, but not write".to_string()),
+                created: 1_754_000_001,
+            },
+            SnapshotRole {
+                role: "write".to_string(),
+                description: None,
+                created: 1_754_000_002,
+            },
+        ],
+        role_parents: vec![SnapshotRoleParent {
+            role: "read".to_string(),
+            parent: "write".to_string(),
+        }],
+        grants: vec![
+            SnapshotGrant {
+                node: "node-a".to_string(),
+                role: "write".to_string(),
+                expires_at: Some(1_754_003_600),
+                created: Some(1_754_000_003),
+                resource: Some("bucket/*".to_string()),
+            },
+            SnapshotGrant {
+                node: "node-a".to_string(),
+                role: "read".to_string(),
+                expires_at: None,
+                created: None,
+                resource: None,
+            },
+        ],
+        groups: vec![SnapshotGroup {
+            name: "builders".to_string(),
+            description: Some("CI agents".to_string()),
+            members: vec!["node-a".to_string()],
+            roles: vec!["read".to_string(), "write".to_string()],
+            created: 1_754_000_004,
+        }],
+        denies: vec![SnapshotDeny {
+            node: "node-a".to_string(),
+            role: "read".to_string(),
+            created: 1_754_000_005,
+        }],
+        labels: vec![SnapshotLabel {
+            node: "node-a".to_string(),
+            key: "env".to_string(),
+            value: "prod".to_string(),
+            created: 1_754_000_006,
+        }],
+        selector_grants: vec![SnapshotSelectorGrant {
+            selector: "env=prod,team=infra".to_string(),
+            role: "deploy".to_string(),
+            created: 1_754_000_007,
+        }],
+        tenants: vec![SnapshotTenant {
+            name: "infra".to_string(),
+            description: None,
+            created: 1_753_999_999,
+        }],
+    }
+}
+
+#[test]
+fn snapshot_round_trip() {
+    for format in [Format::Json, Format::Csv] {
+        let data = encode(&snapshot(), format).unwrap();
+        assert_eq!(decode(&data, format).unwrap(), snapshot());
+    }
+}
+
+#[test]
+fn snapshot_csv_rejects_unknown_records() {
+    let data =
+        b"version,record,name,node,superadmin,role,parent,expires_at,created\n1,bogus,,,,,,,\n";
+    assert!(decode(data, Format::Csv).is_err());
+}
@@ -0,0 +1,10 @@
+CREATE TABLE role_parents (
+    id INTEGER PRIMARY KEY,
+    role_id INTEGER NOT NULL,
+    parent_id INTEGER NOT NULL,
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE,
+    FOREIGN KEY (parent_id) REFERENCES roles(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX ix_role_parents_role_parent ON role_parents (role_id, parent_id);
+CREATE INDEX ix_role_parents_parent ON role_parents (parent_id);
@@ -0,0 +1,3 @@
+ALTER TABLE node_roles ADD COLUMN expires_at TEXT;
+
+CREATE INDEX ix_node_roles_expires_at ON node_roles (expires_at);
@@ -0,0 +1,15 @@
+CREATE TABLE audit_log (
+    id INTEGER PRIMARY KEY,
+    caller TEXT NOT NULL,
+    cmd TEXT NOT NULL,
+    args TEXT NOT NULL,
+    node TEXT,
+    role TEXT,
+    outcome TEXT NOT NULL,
+    created TEXT NOT NULL
+);
+
+CREATE INDEX ix_audit_log_created ON audit_log (created);
+CREATE INDEX ix_audit_log_caller ON audit_log (caller);
+CREATE INDEX ix_audit_log_node ON audit_log (node);
+CREATE INDEX ix_audit_log_role ON audit_log (role);
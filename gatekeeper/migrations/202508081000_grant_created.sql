@@ -0,0 +1 @@
+ALTER TABLE node_roles ADD COLUMN created TEXT;
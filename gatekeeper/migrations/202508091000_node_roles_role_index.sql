@@ -0,0 +1 @@
+CREATE INDEX ix_node_roles_role ON node_roles (role_id);
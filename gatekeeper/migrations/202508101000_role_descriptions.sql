@@ -0,0 +1 @@
+ALTER TABLE roles ADD COLUMN description TEXT;
@@ -0,0 +1,11 @@
+CREATE TABLE key_rotations (
+    id INTEGER PRIMARY KEY,
+    node_id INTEGER NOT NULL,
+    old_node TEXT NOT NULL,
+    new_node TEXT NOT NULL,
+    rotated_by TEXT NOT NULL,
+    created TEXT NOT NULL,
+    FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX ix_key_rotations_node_id ON key_rotations (node_id);
@@ -0,0 +1 @@
+ALTER TABLE nodes ADD COLUMN admin_tier TEXT;
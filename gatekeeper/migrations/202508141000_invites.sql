@@ -0,0 +1,20 @@
+CREATE TABLE invites (
+    id INTEGER PRIMARY KEY,
+    secret TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    uses_left INTEGER NOT NULL,
+    created_by TEXT NOT NULL,
+    created TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX ix_invites_secret ON invites (secret);
+
+CREATE TABLE invite_roles (
+    id INTEGER PRIMARY KEY,
+    invite_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    FOREIGN KEY (invite_id) REFERENCES invites(id) ON DELETE CASCADE,
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX ix_invite_roles_invite_role ON invite_roles (invite_id, role_id);
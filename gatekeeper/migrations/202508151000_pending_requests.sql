@@ -0,0 +1,16 @@
+CREATE TABLE pending_requests (
+    id INTEGER PRIMARY KEY,
+    node_id INTEGER NOT NULL,
+    role TEXT NOT NULL,
+    justification TEXT NOT NULL,
+    ttl INTEGER,
+    status TEXT NOT NULL,
+    reviewed_by TEXT,
+    note TEXT,
+    created TEXT NOT NULL,
+    reviewed TEXT,
+    FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX ix_pending_requests_status ON pending_requests (status);
+CREATE INDEX ix_pending_requests_node_id ON pending_requests (node_id);
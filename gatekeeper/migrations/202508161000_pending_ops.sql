@@ -0,0 +1,9 @@
+CREATE TABLE pending_ops (
+    id INTEGER PRIMARY KEY,
+    cmd_name TEXT NOT NULL,
+    cmd BLOB NOT NULL,
+    args TEXT NOT NULL,
+    requested_by TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    created TEXT NOT NULL
+);
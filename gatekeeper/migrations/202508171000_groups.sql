@@ -0,0 +1,30 @@
+CREATE TABLE groups (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    created TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX ix_groups_name ON groups (name);
+
+CREATE TABLE group_members (
+    id INTEGER PRIMARY KEY,
+    group_id INTEGER NOT NULL,
+    node_id INTEGER NOT NULL,
+    FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+    FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX ix_group_members_group_node ON group_members (group_id, node_id);
+CREATE INDEX ix_group_members_node ON group_members (node_id);
+
+CREATE TABLE group_roles (
+    id INTEGER PRIMARY KEY,
+    group_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX ix_group_roles_group_role ON group_roles (group_id, role_id);
+CREATE INDEX ix_group_roles_role ON group_roles (role_id);
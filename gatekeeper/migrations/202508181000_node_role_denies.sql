@@ -0,0 +1,11 @@
+CREATE TABLE node_role_denies (
+    id INTEGER PRIMARY KEY,
+    node_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    created TEXT NOT NULL,
+    FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE,
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX ix_node_role_denies_node_role ON node_role_denies (node_id, role_id);
+CREATE INDEX ix_node_role_denies_role ON node_role_denies (role_id);
@@ -0,0 +1,6 @@
+-- Empty for grants that apply to every resource, so that the unique index
+-- treats unscoped grants as equal.
+ALTER TABLE node_roles ADD COLUMN resource TEXT NOT NULL DEFAULT '';
+
+DROP INDEX ix_node_roles_node_role;
+CREATE UNIQUE INDEX ix_node_roles_node_role_resource ON node_roles (node_id, role_id, resource);
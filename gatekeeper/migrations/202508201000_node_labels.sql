@@ -0,0 +1,35 @@
+CREATE TABLE node_labels (
+    id INTEGER PRIMARY KEY,
+    node_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    created TEXT NOT NULL,
+    FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX ix_node_labels_node_key ON node_labels (node_id, key);
+CREATE INDEX ix_node_labels_key_value ON node_labels (key, value);
+
+-- A role granted to every node carrying all of the selector's labels. The
+-- selector is kept in canonical form, labels sorted by key, and broken out
+-- into terms for matching.
+CREATE TABLE selector_roles (
+    id INTEGER PRIMARY KEY,
+    selector TEXT NOT NULL,
+    role_id INTEGER NOT NULL,
+    created TEXT NOT NULL,
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX ix_selector_roles_selector_role ON selector_roles (selector, role_id);
+CREATE INDEX ix_selector_roles_role ON selector_roles (role_id);
+
+CREATE TABLE selector_role_terms (
+    id INTEGER PRIMARY KEY,
+    selector_role_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    FOREIGN KEY (selector_role_id) REFERENCES selector_roles(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX ix_selector_role_terms_selector_role_key ON selector_role_terms (selector_role_id, key);
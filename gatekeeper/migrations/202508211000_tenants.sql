@@ -0,0 +1,14 @@
+CREATE TABLE tenants (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    created TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX ix_tenants_name ON tenants (name);
+
+-- Nodes outside any tenant are left NULL. Roles and groups aren't tied to a
+-- tenant here: they belong to one by name, e.g. infra/deploy.
+ALTER TABLE nodes ADD COLUMN tenant TEXT REFERENCES tenants(name);
+
+CREATE INDEX ix_nodes_tenant ON nodes (tenant);
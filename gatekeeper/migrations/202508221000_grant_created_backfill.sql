@@ -0,0 +1,2 @@
+-- Grants made before creation times were recorded are dated to the migration.
+UPDATE node_roles SET created = CURRENT_TIMESTAMP WHERE created IS NULL;
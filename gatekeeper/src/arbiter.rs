@@ -1,60 +1,512 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap, fmt::Debug, path::PathBuf, str::FromStr, sync::Arc, time::Duration,
+};
 
+use chrono::{DateTime, Utc};
+use data_encoding::BASE32_NOPAD;
 use iroh::NodeId;
 use sqlx::{SqlitePool, sqlite::SqliteConnectOptions};
+use subtle::ConstantTimeEq;
+use tokio::{
+    sync::{Mutex, Notify},
+    task::JoinHandle,
+};
 
-use crate::{Error, Node, db};
+use crate::{
+    AccessRequest, AdminTier, AuditEntry, AuditFilter, Cmd, Error, Grant, Group, ImportMode,
+    Invite, InviteTicket, KeyRotation, Label, LabelSelector, MAX_TTL, Node, PendingOp,
+    RequestStatus, Role, RoleCheck, SNAPSHOT_VERSION, SelectorGrant, Snapshot, SnapshotDeny,
+    SnapshotGrant, SnapshotGroup, SnapshotLabel, SnapshotNode, SnapshotRole, SnapshotRoleParent,
+    SnapshotSelectorGrant, SnapshotTenant, Tenant, WhoAmI, common::resource_matches, db,
+    tenant_owns,
+};
 
 #[derive(Clone, Debug)]
 pub struct Arbiter {
     db: SqlitePool,
-    remote_setup: bool,
+    bootstrap_token: Arc<BootstrapToken>,
+    strict_roles: bool,
+    self_service_rotation: bool,
+    two_person_rule: Vec<String>,
+    confirm_window: Duration,
+}
+
+/// One-time token for creating the first node, redacted from debug output.
+struct BootstrapToken {
+    token: Mutex<Option<String>>,
+    /// Woken once the token is used or invalidated
+    spent: Notify,
+}
+
+impl Debug for BootstrapToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BootstrapToken(..)")
+    }
 }
 
 impl Arbiter {
+    /// With `remote_setup` enabled and an empty database, a one-time bootstrap
+    /// token is generated (see [`Arbiter::bootstrap_token`]) that lets a remote
+    /// node create the first superadmin with [`Arbiter::bootstrap`].
     pub async fn new(db_path: PathBuf, remote_setup: bool) -> Result<Self, sqlx::Error> {
         let db = init_db(db_path).await?;
-        Ok(Self { db, remote_setup })
+        let token = match remote_setup && !db::Node::any(&db).await? {
+            true => Some(generate_token()),
+            false => None,
+        };
+
+        Ok(Self {
+            db,
+            bootstrap_token: Arc::new(BootstrapToken {
+                token: Mutex::new(token),
+                spent: Notify::new(),
+            }),
+            strict_roles: false,
+            self_service_rotation: false,
+            two_person_rule: vec![],
+            confirm_window: Duration::from_secs(15 * 60),
+        })
     }
 
-    pub async fn roles(&self) -> Result<Vec<String>, Error> {
-        let res = db::Role::all(&self.db)
+    /// The bootstrap token, until it has been used.
+    pub async fn bootstrap_token(&self) -> Option<String> {
+        self.bootstrap_token.token.lock().await.clone()
+    }
+
+    /// Wait until the bootstrap token has been used or invalidated, returning
+    /// straight away if there is none.
+    pub async fn bootstrap_spent(&self) {
+        loop {
+            let spent = self.bootstrap_token.spent.notified();
+            if self.bootstrap_token().await.is_none() {
+                return;
+            }
+            spent.await;
+        }
+    }
+
+    /// Create the first node as a superadmin, given the bootstrap token. The
+    /// token is invalidated once used, or if the database is no longer empty.
+    pub async fn bootstrap(&self, token: &str, name: &str, node: &str) -> Result<Node, Error> {
+        let mut bootstrap_token = self.bootstrap_token.token.lock().await;
+        // Compared in constant time, since anyone can attempt a bootstrap
+        let valid = match bootstrap_token.as_deref() {
+            Some(expected) => bool::from(expected.as_bytes().ct_eq(token.as_bytes())),
+            None => false,
+        };
+        if !valid {
+            return Err(Error::UnauthorizedError);
+        }
+
+        if db::Node::any(&self.db).await? {
+            *bootstrap_token = None;
+            self.bootstrap_token.spent.notify_waiters();
+            return Err(Error::UnauthorizedError);
+        }
+
+        let res = db::Node::insert(&self.db, name, node, true, None)
+            .await?
+            .into();
+        *bootstrap_token = None;
+        self.bootstrap_token.spent.notify_waiters();
+
+        Ok(res)
+    }
+
+    /// In strict mode roles must be created with [`Arbiter::create_role`] before
+    /// they can be granted or inherited, rather than being created on first use.
+    pub fn with_strict_roles(mut self, strict_roles: bool) -> Self {
+        self.strict_roles = strict_roles;
+        self
+    }
+
+    /// Let a registered node rotate its own key with [`Arbiter::rotate_node_key`],
+    /// authenticating with the key being replaced.
+    pub fn with_self_service_rotation(mut self, self_service_rotation: bool) -> Self {
+        self.self_service_rotation = self_service_rotation;
+        self
+    }
+
+    /// Hold commands matching any of `rules` (see [`Cmd::matches_rule`]) until a
+    /// second, distinct superadmin confirms them within `window`.
+    pub fn with_two_person_rule(mut self, rules: Vec<String>, window: Duration) -> Self {
+        self.two_person_rule = rules;
+        self.confirm_window = window;
+        self
+    }
+
+    /// Must the command be confirmed by a second superadmin before it runs?
+    /// Besides [`Cmd::matches_rule`], rotating a superadmin's key counts as
+    /// creating a superadmin, since the new key inherits its access.
+    pub async fn requires_confirmation(&self, cmd: &Cmd) -> Result<bool, Error> {
+        if let Cmd::PendingOps | Cmd::Confirm { .. } | Cmd::CancelOp { .. } = cmd {
+            return Ok(false);
+        }
+
+        if self
+            .two_person_rule
+            .iter()
+            .any(|rule| cmd.matches_rule(rule))
+        {
+            return Ok(true);
+        }
+
+        if let Cmd::RotateNodeKey { node, .. } = cmd
+            && self
+                .two_person_rule
+                .iter()
+                .any(|r| r == "create-superadmin")
+        {
+            let node = db::Node::find(&self.db, node).await?;
+            return Ok(node.is_some_and(|n| n.superadmin));
+        }
+
+        Ok(false)
+    }
+
+    /// Store a command for later confirmation with [`Arbiter::confirm_op`].
+    pub async fn hold_op(&self, caller: NodeId, cmd: &Cmd) -> Result<PendingOp, Error> {
+        let encoded = bincode::encode_to_vec(cmd, bincode::config::standard())?;
+        let expires_at = expires_in(self.confirm_window)?;
+
+        let op = db::PendingOp::insert(
+            &self.db,
+            cmd.name(),
+            &encoded,
+            &cmd.audit_args(),
+            &format!("{caller}"),
+            expires_at,
+        )
+        .await?;
+
+        tracing::info!(id = op.id, cmd = op.cmd_name, "op_held");
+        Ok(op.into())
+    }
+
+    pub async fn pending_ops(&self) -> Result<Vec<PendingOp>, Error> {
+        let res = db::PendingOp::all_open(&self.db)
             .await?
             .into_iter()
-            .map(|r| r.role)
+            .map(PendingOp::from)
             .collect();
 
         Ok(res)
     }
 
-    pub async fn nodes(&self) -> Result<Vec<Node>, Error> {
-        let res = db::Node::all(&self.db)
+    /// Take a held command for execution, returning the node that requested it
+    /// and the command itself. The confirming node must differ from the requester.
+    pub async fn confirm_op(&self, caller: NodeId, id: i64) -> Result<(NodeId, Cmd), Error> {
+        let op = self.get_op(id).await?;
+        if op.requested_by == format!("{caller}") {
+            return Err(Error::UnauthorizedError);
+        }
+
+        let requester = NodeId::from_str(&op.requested_by)
+            .map_err(|e| Error::ServerError(format!("invalid requester: {e}")))?;
+        let (cmd, _) = bincode::decode_from_slice(&op.cmd, bincode::config::standard())?;
+
+        if !db::PendingOp::delete(&self.db, id).await? {
+            return Err(Error::NoSuchOpError);
+        }
+
+        Ok((requester, cmd))
+    }
+
+    /// Drop a held command. Only the requester or a superadmin may cancel it.
+    pub async fn cancel_op(&self, caller: NodeId, id: i64) -> Result<(), Error> {
+        let op = self.get_op(id).await?;
+        let caller = format!("{caller}");
+
+        if op.requested_by != caller && !self.get_node(&caller).await?.superadmin {
+            return Err(Error::UnauthorizedError);
+        }
+
+        if !db::PendingOp::delete(&self.db, id).await? {
+            return Err(Error::NoSuchOpError);
+        }
+
+        Ok(())
+    }
+
+    pub async fn whoami(&self, caller: NodeId) -> Result<WhoAmI, Error> {
+        let node = self.get_node(&format!("{caller}")).await?;
+        let groups = db::Group::for_node(&self.db, node.id)
             .await?
             .into_iter()
-            .map(Node::from)
+            .map(|g| g.name)
+            .collect();
+        let labels = db::NodeLabel::for_node(&self.db, node.id)
+            .await?
+            .into_iter()
+            .map(Label::from)
+            .collect();
+
+        let res = WhoAmI {
+            roles: self.node_roles(&node.node, false).await?,
+            effective_roles: self.node_roles(&node.node, true).await?,
+            node: node.into(),
+            groups,
+            labels,
+        };
+
+        Ok(res)
+    }
+
+    /// Every role, or only those belonging to a tenant (see [`tenant_owns`]).
+    pub async fn roles(&self, tenant: Option<&str>) -> Result<Vec<String>, Error> {
+        let res = db::Role::all(&self.db)
+            .await?
+            .into_iter()
+            .map(|r| r.role)
+            .filter(|r| tenant.is_none_or(|t| tenant_owns(t, r)))
             .collect();
 
         Ok(res)
     }
 
-    pub async fn node_roles(&self, node: &str) -> Result<Vec<String>, Error> {
-        let res = db::Node::roles(&self.db, node).await?;
+    pub async fn show_role(&self, role: &str) -> Result<Role, Error> {
+        Ok(self.get_role(role).await?.into())
+    }
+
+    pub async fn create_role(&self, role: &str, description: Option<&str>) -> Result<Role, Error> {
+        if db::Role::find(&self.db, role).await?.is_some() {
+            return Err(Error::RoleExistsError);
+        }
+
+        let res = db::Role::insert(&self.db, role, description).await?.into();
+        Ok(res)
+    }
+
+    pub async fn describe_role(
+        &self,
+        role: &str,
+        description: Option<&str>,
+    ) -> Result<Role, Error> {
+        let role = self.get_role(role).await?;
+        let res = db::Role::set_description(&self.db, role.id, description)
+            .await?
+            .into();
+
+        Ok(res)
+    }
+
+    pub async fn rename_role(&self, role: &str, new_role: &str) -> Result<Role, Error> {
+        let role = self.get_role(role).await?;
+        if db::Role::find(&self.db, new_role).await?.is_some() {
+            return Err(Error::RoleExistsError);
+        }
+
+        let res = db::Role::rename(&self.db, role.id, new_role).await?.into();
+        Ok(res)
+    }
+
+    /// Delete a role along with its inheritance edges. Refuses to delete a role
+    /// that's still granted, unless cascading, in which case the grants go too.
+    pub async fn delete_role(&self, role: &str, cascade: bool) -> Result<(), Error> {
+        let role = self.get_role(role).await?;
+        if !cascade && db::Role::grant_count(&self.db, role.id).await? > 0 {
+            return Err(Error::RoleInUseError);
+        }
+
+        db::Role::delete(&self.db, role.id).await?;
+        Ok(())
+    }
+
+    pub async fn nodes(&self, tenant: Option<&str>) -> Result<Vec<Node>, Error> {
+        let nodes = match tenant {
+            None => db::Node::all(&self.db).await?,
+            Some(tenant) => db::Node::in_tenant(&self.db, tenant).await?,
+        };
+
+        let res = nodes.into_iter().map(Node::from).collect();
+
+        Ok(res)
+    }
+
+    pub async fn node_roles(&self, node: &str, effective: bool) -> Result<Vec<Grant>, Error> {
+        let res = if effective {
+            db::Node::effective_roles(&self.db, node).await?
+        } else {
+            db::Node::roles(&self.db, node).await?
+        };
+
+        Ok(res.into_iter().map(Grant::from).collect())
+    }
+
+    pub async fn role_nodes(
+        &self,
+        role: &str,
+        indirect: bool,
+        tenant: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Node>, Error> {
+        let res = db::Node::with_role(
+            &self.db,
+            role,
+            indirect,
+            tenant,
+            limit.into(),
+            offset.into(),
+        )
+        .await?
+        .into_iter()
+        .map(Node::from)
+        .collect();
+
+        Ok(res)
+    }
+
+    pub async fn role_parents(&self, role: &str) -> Result<Vec<String>, Error> {
+        let res = match db::Role::find(&self.db, role).await? {
+            None => vec![],
+            Some(role) => db::Role::parents(&self.db, role.id)
+                .await?
+                .into_iter()
+                .map(|r| r.role)
+                .collect(),
+        };
+
         Ok(res)
     }
 
+    /// Create a node, optionally in a tenant. Superadmins can't belong to a
+    /// tenant, since their access isn't confined to one.
     pub async fn create_node(
         &self,
         name: &str,
         node: &str,
         superadmin: bool,
+        tenant: Option<&str>,
+    ) -> Result<Node, Error> {
+        if let Some(tenant) = tenant {
+            if superadmin {
+                return Err(Error::InvalidTenantError(
+                    "superadmins can't belong to a tenant".to_string(),
+                ));
+            }
+
+            self.get_tenant(tenant).await?;
+        }
+
+        let res = db::Node::insert(&self.db, name, node, superadmin, tenant)
+            .await?
+            .into();
+
+        Ok(res)
+    }
+
+    /// Update a node in place, keeping its grants. Fields that are `None` are
+    /// left unchanged, and an empty description clears it. Nodes in a tenant
+    /// can't be made superadmins.
+    pub async fn update_node(
+        &self,
+        node: &str,
+        name: Option<&str>,
+        superadmin: Option<bool>,
+        description: Option<&str>,
+    ) -> Result<Node, Error> {
+        let node = self.get_node(node).await?;
+        if let Some(name) = name
+            && let Some(other) = db::Node::find_by_name(&self.db, name).await?
+            && other.id != node.id
+        {
+            return Err(Error::NodeExistsError);
+        }
+
+        if superadmin == Some(true) && node.tenant.is_some() {
+            return Err(Error::InvalidTenantError(
+                "superadmins can't belong to a tenant".to_string(),
+            ));
+        }
+
+        let description = description.map(|d| Some(d).filter(|d| !d.is_empty()));
+        let res = db::Node::update(&self.db, node.id, name, superadmin, description)
+            .await?
+            .into();
+
+        Ok(res)
+    }
+
+    /// Move a node to a new key, keeping its id, name and grants. The old key is
+    /// recorded so the audit trail can be followed across rotations.
+    pub async fn rotate_node_key(
+        &self,
+        caller: NodeId,
+        node: &str,
+        new_node: &str,
     ) -> Result<Node, Error> {
-        let res = db::Node::insert(&self.db, name, node, superadmin)
+        let node = self.get_node(node).await?;
+        if db::Node::find(&self.db, new_node).await?.is_some() {
+            return Err(Error::NodeExistsError);
+        }
+
+        let mut tx = self.db.begin().await?;
+        db::KeyRotation::insert(
+            &mut *tx,
+            node.id,
+            &node.node,
+            new_node,
+            &format!("{caller}"),
+        )
+        .await?;
+        let res = db::Node::set_key(&mut *tx, node.id, new_node).await?.into();
+        tx.commit().await?;
+
+        Ok(res)
+    }
+
+    /// Key rotations of a node, oldest first. The node can be given by its
+    /// current key or any key it has been rotated away from.
+    pub async fn node_key_history(&self, node: &str) -> Result<Vec<KeyRotation>, Error> {
+        let node_id = match db::Node::find(&self.db, node).await? {
+            Some(node) => node.id,
+            None => match db::KeyRotation::find_node_id(&self.db, node).await? {
+                Some(node_id) => node_id,
+                None => return Err(Error::NoSuchNodeError),
+            },
+        };
+
+        let res = db::KeyRotation::for_node(&self.db, node_id)
+            .await?
+            .into_iter()
+            .map(KeyRotation::from)
+            .collect();
+
+        Ok(res)
+    }
+
+    /// Set or clear the administrative tier of a node. Superadmins have full
+    /// access regardless of their tier.
+    pub async fn set_admin_tier(&self, node: &str, tier: Option<AdminTier>) -> Result<Node, Error> {
+        let node = self.get_node(node).await?;
+        let res = db::Node::set_admin_tier(&self.db, node.id, tier.map(AdminTier::as_str))
             .await?
             .into();
 
         Ok(res)
     }
 
+    /// Move a node into a tenant, or out of its tenant. Its grants are kept.
+    /// Superadmins can't be moved into a tenant.
+    pub async fn set_node_tenant(&self, node: &str, tenant: Option<&str>) -> Result<Node, Error> {
+        let node = self.get_node(node).await?;
+        if let Some(tenant) = tenant {
+            if node.superadmin {
+                return Err(Error::InvalidTenantError(
+                    "superadmins can't belong to a tenant".to_string(),
+                ));
+            }
+
+            self.get_tenant(tenant).await?;
+        }
+
+        let res = db::Node::set_tenant(&self.db, node.id, tenant)
+            .await?
+            .into();
+        Ok(res)
+    }
+
     pub async fn delete_node(&self, node: &str) -> Result<(), Error> {
         let node = self.get_node(node).await?;
         db::Node::delete(&self.db, node.id).await?;
@@ -62,44 +514,1171 @@ impl Arbiter {
         Ok(())
     }
 
-    pub async fn grant_role(&self, node: &str, role: &str) -> Result<(), Error> {
+    /// Grant a role to a node, on every resource or only on resources matching
+    /// `resource` (see [`Arbiter::has_role_on`]). Grants of the same role on
+    /// different resources are independent of each other.
+    pub async fn grant_role(
+        &self,
+        node: &str,
+        role: &str,
+        resource: Option<&str>,
+        ttl: Option<Duration>,
+    ) -> Result<(), Error> {
         let node = self.get_node(node).await?;
-        let role = db::Role::ensure(&self.db, role).await?;
+        let role = self.use_role(role).await?;
+        let expires_at = ttl.map(expires_in).transpose()?;
+        let resource = resource.unwrap_or_default();
 
-        db::NodeRole::ensure(&self.db, node.id, role.id).await?;
+        db::NodeRole::upsert(&self.db, node.id, role.id, resource, expires_at).await?;
         Ok(())
     }
 
-    pub async fn revoke_role(&self, node: &str, role: &str) -> Result<(), Error> {
+    /// Revoke the grant of a role on exactly `resource`, or the unscoped grant.
+    pub async fn revoke_role(
+        &self,
+        node: &str,
+        role: &str,
+        resource: Option<&str>,
+    ) -> Result<(), Error> {
         let node = self.get_node(node).await?;
-        let role = db::Role::ensure(&self.db, role).await?;
+        let resource = resource.unwrap_or_default();
 
-        if let Some(node_role) = db::NodeRole::find(&self.db, node.id, role.id).await? {
+        if let Some(role) = db::Role::find(&self.db, role).await?
+            && let Some(node_role) =
+                db::NodeRole::find(&self.db, node.id, role.id, resource).await?
+        {
             db::NodeRole::delete(&self.db, node_role.id).await?;
         }
 
         Ok(())
     }
 
-    pub async fn allow(&self, caller: NodeId) -> Result<bool, Error> {
-        if self.remote_setup && !db::Node::any(&self.db).await? {
-            return Ok(true);
+    /// Deny a role to a node. Denies override allows: the node doesn't hold the
+    /// role while the deny stands, whether it's granted directly, through a
+    /// group or through a parent role.
+    pub async fn deny_role(&self, node: &str, role: &str) -> Result<(), Error> {
+        let node = self.get_node(node).await?;
+        let role = self.use_role(role).await?;
+
+        db::NodeRoleDeny::ensure(&self.db, node.id, role.id).await?;
+        Ok(())
+    }
+
+    pub async fn remove_role_deny(&self, node: &str, role: &str) -> Result<(), Error> {
+        let node = self.get_node(node).await?;
+
+        if let Some(role) = db::Role::find(&self.db, role).await? {
+            db::NodeRoleDeny::delete(&self.db, node.id, role.id).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn add_role_parent(&self, role: &str, parent: &str) -> Result<(), Error> {
+        let role = self.use_role(role).await?;
+        let parent = self.use_role(parent).await?;
+
+        if db::RoleParent::implies(&self.db, role.id, parent.id).await? {
+            return Err(Error::RoleCycleError);
+        }
+
+        db::RoleParent::ensure(&self.db, role.id, parent.id).await?;
+        Ok(())
+    }
+
+    pub async fn remove_role_parent(&self, role: &str, parent: &str) -> Result<(), Error> {
+        let role = db::Role::find(&self.db, role).await?;
+        let parent = db::Role::find(&self.db, parent).await?;
+
+        if let (Some(role), Some(parent)) = (role, parent)
+            && let Some(role_parent) = db::RoleParent::find(&self.db, role.id, parent.id).await?
+        {
+            db::RoleParent::delete(&self.db, role_parent.id).await?;
         }
 
-        let res = db::Node::find(&self.db, &format!("{caller}"))
+        Ok(())
+    }
+
+    /// Every group, or only those belonging to a tenant (see [`tenant_owns`]).
+    pub async fn groups(&self, tenant: Option<&str>) -> Result<Vec<Group>, Error> {
+        let res = db::Group::all(&self.db)
             .await?
-            .map(|n| n.superadmin)
-            .unwrap_or(false);
+            .into_iter()
+            .filter(|g| tenant.is_none_or(|t| tenant_owns(t, &g.name)))
+            .map(Group::from)
+            .collect();
 
         Ok(res)
     }
 
-    async fn get_node(&self, node: &str) -> Result<db::Node, Error> {
-        match db::Node::find(&self.db, node).await? {
-            None => Err(Error::NoSuchNodeError),
-            Some(node) => Ok(node),
+    pub async fn create_group(
+        &self,
+        group: &str,
+        description: Option<&str>,
+    ) -> Result<Group, Error> {
+        if db::Group::find(&self.db, group).await?.is_some() {
+            return Err(Error::GroupExistsError);
+        }
+
+        let res = db::Group::insert(&self.db, group, description)
+            .await?
+            .into();
+        Ok(res)
+    }
+
+    /// Delete a group along with its memberships and grants. Its members keep
+    /// any roles granted to them directly.
+    pub async fn delete_group(&self, group: &str) -> Result<(), Error> {
+        let group = self.get_group(group).await?;
+        db::Group::delete(&self.db, group.id).await?;
+
+        Ok(())
+    }
+
+    pub async fn group_members(&self, group: &str) -> Result<Vec<Node>, Error> {
+        let group = self.get_group(group).await?;
+        let res = db::Group::members(&self.db, group.id)
+            .await?
+            .into_iter()
+            .map(Node::from)
+            .collect();
+
+        Ok(res)
+    }
+
+    pub async fn add_group_member(&self, group: &str, node: &str) -> Result<(), Error> {
+        let group = self.get_group(group).await?;
+        let node = self.get_node(node).await?;
+
+        db::Group::add_member(&self.db, group.id, node.id).await?;
+        Ok(())
+    }
+
+    pub async fn remove_group_member(&self, group: &str, node: &str) -> Result<(), Error> {
+        let group = self.get_group(group).await?;
+        let node = self.get_node(node).await?;
+
+        db::Group::remove_member(&self.db, group.id, node.id).await?;
+        Ok(())
+    }
+
+    pub async fn group_roles(&self, group: &str) -> Result<Vec<String>, Error> {
+        let group = self.get_group(group).await?;
+        let res = db::Group::roles(&self.db, group.id)
+            .await?
+            .into_iter()
+            .map(|r| r.role)
+            .collect();
+
+        Ok(res)
+    }
+
+    pub async fn grant_group_role(&self, group: &str, role: &str) -> Result<(), Error> {
+        let group = self.get_group(group).await?;
+        let role = self.use_role(role).await?;
+
+        db::Group::add_role(&self.db, group.id, role.id).await?;
+        Ok(())
+    }
+
+    pub async fn revoke_group_role(&self, group: &str, role: &str) -> Result<(), Error> {
+        let group = self.get_group(group).await?;
+
+        if let Some(role) = db::Role::find(&self.db, role).await? {
+            db::Group::remove_role(&self.db, group.id, role.id).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn tenants(&self) -> Result<Vec<Tenant>, Error> {
+        let res = db::Tenant::all(&self.db)
+            .await?
+            .into_iter()
+            .map(Tenant::from)
+            .collect();
+
+        Ok(res)
+    }
+
+    /// Create a tenant. Tenant names prefix the names of the tenant's roles and
+    /// groups, so they're limited to letters, digits, `-`, `_` and `.`.
+    pub async fn create_tenant(
+        &self,
+        tenant: &str,
+        description: Option<&str>,
+    ) -> Result<Tenant, Error> {
+        let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+        if tenant.is_empty() || !tenant.chars().all(valid) {
+            return Err(Error::InvalidTenantError(format!(
+                "invalid tenant name {tenant:?}, expected letters, digits, '-', '_' or '.'"
+            )));
+        }
+
+        if db::Tenant::find(&self.db, tenant).await?.is_some() {
+            return Err(Error::TenantExistsError);
         }
+
+        let res = db::Tenant::insert(&self.db, tenant, description)
+            .await?
+            .into();
+        Ok(res)
     }
+
+    /// Delete a tenant. Refuses while nodes remain in the tenant. Its roles and
+    /// groups are left as they are, and belong to the tenant again if it's
+    /// recreated.
+    pub async fn delete_tenant(&self, tenant: &str) -> Result<(), Error> {
+        let tenant = self.get_tenant(tenant).await?;
+        if db::Tenant::node_count(&self.db, &tenant.name).await? > 0 {
+            return Err(Error::TenantInUseError);
+        }
+
+        db::Tenant::delete(&self.db, tenant.id).await?;
+        Ok(())
+    }
+
+    pub async fn node_labels(&self, node: &str) -> Result<Vec<Label>, Error> {
+        let node = self.get_node(node).await?;
+        let res = db::NodeLabel::for_node(&self.db, node.id)
+            .await?
+            .into_iter()
+            .map(Label::from)
+            .collect();
+
+        Ok(res)
+    }
+
+    /// Attach a label to a node, replacing its existing value for the key. The
+    /// node immediately holds the roles of every selector grant it now matches,
+    /// and loses those it no longer does.
+    pub async fn set_node_label(&self, node: &str, label: &Label) -> Result<(), Error> {
+        label.validate().map_err(Error::InvalidLabelError)?;
+        let node = self.get_node(node).await?;
+
+        db::NodeLabel::set(&self.db, node.id, &label.key, &label.value).await?;
+        Ok(())
+    }
+
+    pub async fn remove_node_label(&self, node: &str, key: &str) -> Result<(), Error> {
+        let node = self.get_node(node).await?;
+        db::NodeLabel::delete(&self.db, node.id, key).await?;
+
+        Ok(())
+    }
+
+    pub async fn selector_grants(&self) -> Result<Vec<SelectorGrant>, Error> {
+        let res = db::SelectorRole::all_named(&self.db)
+            .await?
+            .into_iter()
+            .map(|sr| SelectorGrant {
+                selector: sr.selector,
+                role: sr.role,
+                created: sr.created.and_utc().timestamp(),
+            })
+            .collect();
+
+        Ok(res)
+    }
+
+    /// Grant a role to every node carrying all of the selector's labels,
+    /// including nodes labeled later. Selector grants never expire, and apply
+    /// to every resource.
+    pub async fn grant_selector_role(
+        &self,
+        selector: &LabelSelector,
+        role: &str,
+    ) -> Result<(), Error> {
+        let selector = selector.normalize().map_err(Error::InvalidLabelError)?;
+        let role = self.use_role(role).await?;
+        let canonical = selector.to_string();
+
+        let mut tx = self.db.begin().await?;
+        if db::SelectorRole::find(&mut *tx, &canonical, role.id)
+            .await?
+            .is_none()
+        {
+            let row = db::SelectorRole::insert(&mut *tx, &canonical, role.id).await?;
+            for label in selector.labels.iter() {
+                db::SelectorRole::add_term(&mut *tx, row.id, &label.key, &label.value).await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn revoke_selector_role(
+        &self,
+        selector: &LabelSelector,
+        role: &str,
+    ) -> Result<(), Error> {
+        let selector = selector.normalize().map_err(Error::InvalidLabelError)?;
+
+        if let Some(role) = db::Role::find(&self.db, role).await?
+            && let Some(row) =
+                db::SelectorRole::find(&self.db, &selector.to_string(), role.id).await?
+        {
+            db::SelectorRole::delete(&self.db, row.id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Does the node hold the role on every resource? Grants scoped to a
+    /// resource don't count.
+    pub async fn has_role(&self, node: &str, role: &str) -> Result<bool, Error> {
+        let roles = db::Node::effective_roles(&self.db, node).await?;
+        Ok(roles
+            .iter()
+            .any(|g| g.role == role && g.resource.is_empty()))
+    }
+
+    /// Does the node hold the role on the resource, either through an unscoped
+    /// grant or through a grant whose resource pattern covers it?
+    pub async fn has_role_on(&self, node: &str, role: &str, resource: &str) -> Result<bool, Error> {
+        let roles = db::Node::effective_roles(&self.db, node).await?;
+        let res = roles.iter().any(|g| {
+            g.role == role && (g.resource.is_empty() || resource_matches(&g.resource, resource))
+        });
+
+        Ok(res)
+    }
+
+    pub async fn has_any_role(&self, node: &str, roles: &[String]) -> Result<bool, Error> {
+        let held = db::Node::effective_roles(&self.db, node).await?;
+        let held: Vec<_> = held.iter().filter(|g| g.resource.is_empty()).collect();
+        Ok(roles.iter().any(|r| held.iter().any(|g| &g.role == r)))
+    }
+
+    pub async fn has_all_roles(&self, node: &str, roles: &[String]) -> Result<bool, Error> {
+        let held = db::Node::effective_roles(&self.db, node).await?;
+        let held: Vec<_> = held.iter().filter(|g| g.resource.is_empty()).collect();
+        Ok(roles.iter().all(|r| held.iter().any(|g| &g.role == r)))
+    }
+
+    pub async fn check(&self, checks: &[RoleCheck]) -> Result<Vec<bool>, Error> {
+        let mut res = Vec::with_capacity(checks.len());
+        for check in checks {
+            let held = match &check.resource {
+                None => self.has_role(&check.node, &check.role).await?,
+                Some(resource) => self.has_role_on(&check.node, &check.role, resource).await?,
+            };
+            res.push(held);
+        }
+
+        Ok(res)
+    }
+
+    /// Create an invite that lets up to `uses` nodes enroll themselves with the
+    /// given roles until it expires. The secret is only returned here.
+    pub async fn create_invite(
+        &self,
+        caller: NodeId,
+        roles: &[String],
+        ttl: Duration,
+        uses: u32,
+    ) -> Result<Invite, Error> {
+        let expires_at = expires_in(ttl)?;
+        let mut role_ids = vec![];
+        for role in roles.iter() {
+            role_ids.push(self.use_role(role).await?.id);
+        }
+
+        let secret = generate_token();
+
+        let mut tx = self.db.begin().await?;
+        let invite = db::Invite::insert(
+            &mut *tx,
+            &secret,
+            expires_at,
+            uses.into(),
+            &format!("{caller}"),
+        )
+        .await?;
+
+        for role_id in role_ids {
+            db::Invite::add_role(&mut *tx, invite.id, role_id).await?;
+        }
+
+        let roles = db::Invite::roles(&mut *tx, invite.id).await?;
+        tx.commit().await?;
+
+        let roles = roles.into_iter().map(|r| r.role).collect();
+        Ok(Invite {
+            secret: Some(secret),
+            ..Invite::from_db(invite, roles)
+        })
+    }
+
+    pub async fn invites(&self) -> Result<Vec<Invite>, Error> {
+        let mut res = vec![];
+        for invite in db::Invite::all(&self.db).await? {
+            let roles = db::Invite::roles(&self.db, invite.id).await?;
+            let roles = roles.into_iter().map(|r| r.role).collect();
+            res.push(Invite::from_db(invite, roles));
+        }
+
+        Ok(res)
+    }
+
+    pub async fn delete_invite(&self, id: i64) -> Result<(), Error> {
+        db::Invite::delete(&self.db, id).await?;
+        Ok(())
+    }
+
+    /// Enroll the caller as a new node with the roles of the invite. Unknown,
+    /// expired and used up tickets are all rejected as invalid.
+    pub async fn redeem(&self, caller: NodeId, ticket: &str, name: &str) -> Result<Node, Error> {
+        let ticket = InviteTicket::from_str(ticket)?;
+        let caller = format!("{caller}");
+
+        let mut tx = self.db.begin().await?;
+        let Some(invite) = db::Invite::find_valid(&mut *tx, &ticket.secret).await? else {
+            return Err(Error::InvalidTicketError);
+        };
+
+        if db::Node::find(&mut *tx, &caller).await?.is_some()
+            || db::Node::find_by_name(&mut *tx, name).await?.is_some()
+        {
+            return Err(Error::NodeExistsError);
+        }
+
+        if !db::Invite::redeem(&mut *tx, invite.id).await? {
+            return Err(Error::InvalidTicketError);
+        }
+
+        let node = db::Node::insert(&mut *tx, name, &caller, false, None).await?;
+        for role in db::Invite::roles(&mut *tx, invite.id).await? {
+            db::NodeRole::upsert(&mut *tx, node.id, role.role_id, "", None).await?;
+        }
+
+        tx.commit().await?;
+        tracing::info!(invite = invite.id, node = %caller, name, "invite_redeemed");
+
+        Ok(node.into())
+    }
+
+    /// Ask for a role on behalf of the caller. An open request by the same node
+    /// for the same role is returned rather than duplicated.
+    pub async fn request_role(
+        &self,
+        caller: NodeId,
+        role: &str,
+        justification: &str,
+        ttl: Option<Duration>,
+    ) -> Result<AccessRequest, Error> {
+        let node = self.get_node(&format!("{caller}")).await?;
+        if self.strict_roles {
+            self.get_role(role).await?;
+        }
+
+        // Bounded up front, so that approving the request can't fail on it
+        if let Some(ttl) = ttl {
+            expires_in(ttl)?;
+        }
+
+        if let Some(existing) = db::AccessRequest::find_pending(&self.db, node.id, role).await? {
+            return Ok(existing.into());
+        }
+
+        let ttl = ttl.map(|ttl| ttl.as_secs() as i64);
+        let id = db::AccessRequest::insert(&self.db, node.id, role, justification, ttl).await?;
+
+        Ok(self.get_request(id).await?.into())
+    }
+
+    /// Requests awaiting review, oldest first, or every request if `all` is set.
+    pub async fn list_requests(&self, all: bool) -> Result<Vec<AccessRequest>, Error> {
+        let status = (!all).then_some(RequestStatus::Pending.as_str());
+        let res = db::AccessRequest::search(&self.db, status)
+            .await?
+            .into_iter()
+            .map(AccessRequest::from)
+            .collect();
+
+        Ok(res)
+    }
+
+    /// Approve a pending request, granting the role for the requested lifetime.
+    pub async fn approve(
+        &self,
+        caller: NodeId,
+        id: i64,
+        note: Option<&str>,
+    ) -> Result<AccessRequest, Error> {
+        let request = self.get_pending_request(id).await?;
+        let ttl = request
+            .ttl
+            .and_then(|ttl| u64::try_from(ttl).ok())
+            .map(Duration::from_secs);
+
+        self.grant_role(&request.node, &request.role, None, ttl)
+            .await?;
+        self.review(caller, id, RequestStatus::Approved, note).await
+    }
+
+    pub async fn deny(
+        &self,
+        caller: NodeId,
+        id: i64,
+        note: Option<&str>,
+    ) -> Result<AccessRequest, Error> {
+        self.get_pending_request(id).await?;
+        self.review(caller, id, RequestStatus::Denied, note).await
+    }
+
+    async fn review(
+        &self,
+        caller: NodeId,
+        id: i64,
+        status: RequestStatus,
+        note: Option<&str>,
+    ) -> Result<AccessRequest, Error> {
+        let reviewer = format!("{caller}");
+        if !db::AccessRequest::review(&self.db, id, status.as_str(), &reviewer, note).await? {
+            return Err(Error::NoSuchRequestError);
+        }
+
+        Ok(self.get_request(id).await?.into())
+    }
+
+    pub async fn audit_log(&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>, Error> {
+        let filter = db::AuditLogFilter {
+            node: filter.node.as_deref(),
+            role: filter.role.as_deref(),
+            caller: filter.caller.as_deref(),
+            since: filter.since.and_then(from_timestamp),
+            until: filter.until.and_then(from_timestamp),
+            limit: filter.limit.into(),
+            offset: filter.offset.into(),
+        };
+
+        let res = db::AuditLog::search(&self.db, &filter)
+            .await?
+            .into_iter()
+            .map(AuditEntry::from)
+            .collect();
+
+        Ok(res)
+    }
+
+    pub async fn record_audit(
+        &self,
+        caller: NodeId,
+        cmd: &Cmd,
+        err: Option<&Error>,
+    ) -> Result<(), Error> {
+        let (node, role) = cmd.subject();
+        let outcome = match err {
+            None => "ok".to_string(),
+            Some(e) => e.to_string(),
+        };
+
+        let entry = db::NewAuditLog {
+            caller: &format!("{caller}"),
+            cmd: cmd.name(),
+            args: &cmd.audit_args(),
+            node,
+            role,
+            outcome: &outcome,
+        };
+
+        db::AuditLog::insert(&self.db, &entry).await?;
+        Ok(())
+    }
+
+    pub async fn export(&self) -> Result<Snapshot, Error> {
+        let mut tx = self.db.begin().await?;
+
+        let nodes = db::Node::all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|n| SnapshotNode {
+                name: n.name,
+                node: n.node,
+                superadmin: n.superadmin,
+                admin_tier: n.admin_tier.as_deref().and_then(AdminTier::parse),
+                description: n.description,
+                tenant: n.tenant,
+                created: n.created.and_utc().timestamp(),
+            })
+            .collect();
+
+        let roles = db::Role::all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|r| SnapshotRole {
+                role: r.role,
+                description: r.description,
+                created: r.created.and_utc().timestamp(),
+            })
+            .collect();
+
+        let role_parents = db::RoleParent::all_named(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|rp| SnapshotRoleParent {
+                role: rp.role,
+                parent: rp.parent,
+            })
+            .collect();
+
+        let grants = db::NodeRole::all_named(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|g| SnapshotGrant {
+                node: g.node,
+                role: g.role,
+                expires_at: g.expires_at.map(|t| t.and_utc().timestamp()),
+                created: Some(g.created.and_utc().timestamp()),
+                resource: Some(g.resource).filter(|r| !r.is_empty()),
+            })
+            .collect();
+
+        let mut members: HashMap<String, Vec<String>> = HashMap::new();
+        for member in db::Group::all_members_named(&mut *tx).await? {
+            members
+                .entry(member.group_name)
+                .or_default()
+                .push(member.node);
+        }
+
+        let mut group_roles: HashMap<String, Vec<String>> = HashMap::new();
+        for group_role in db::Group::all_roles_named(&mut *tx).await? {
+            group_roles
+                .entry(group_role.group_name)
+                .or_default()
+                .push(group_role.role);
+        }
+
+        let groups = db::Group::all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|g| SnapshotGroup {
+                members: members.remove(&g.name).unwrap_or_default(),
+                roles: group_roles.remove(&g.name).unwrap_or_default(),
+                name: g.name,
+                description: g.description,
+                created: g.created.and_utc().timestamp(),
+            })
+            .collect();
+
+        let denies = db::NodeRoleDeny::all_named(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|d| SnapshotDeny {
+                node: d.node,
+                role: d.role,
+                created: d.created.and_utc().timestamp(),
+            })
+            .collect();
+
+        let labels = db::NodeLabel::all_named(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|l| SnapshotLabel {
+                node: l.node,
+                key: l.key,
+                value: l.value,
+                created: l.created.and_utc().timestamp(),
+            })
+            .collect();
+
+        let selector_grants = db::SelectorRole::all_named(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|sr| SnapshotSelectorGrant {
+                selector: sr.selector,
+                role: sr.role,
+                created: sr.created.and_utc().timestamp(),
+            })
+            .collect();
+
+        let tenants = db::Tenant::all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|t| SnapshotTenant {
+                name: t.name,
+                description: t.description,
+                created: t.created.and_utc().timestamp(),
+            })
+            .collect();
+
+        tx.commit().await?;
+
+        Ok(Snapshot {
+            version: SNAPSHOT_VERSION,
+            nodes,
+            roles,
+            role_parents,
+            grants,
+            groups,
+            denies,
+            labels,
+            selector_grants,
+            tenants,
+        })
+    }
+
+    /// Restore a snapshot atomically: either all of it is applied, or none of it.
+    pub async fn import(&self, snapshot: &Snapshot, mode: ImportMode) -> Result<(), Error> {
+        if !(1..=SNAPSHOT_VERSION).contains(&snapshot.version) {
+            return Err(Error::InvalidSnapshotError(format!(
+                "unsupported version {}, expected at most {}",
+                snapshot.version, SNAPSHOT_VERSION
+            )));
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        if mode == ImportMode::Replace {
+            db::Node::delete_all(&mut *tx).await?;
+            db::Role::delete_all(&mut *tx).await?;
+            db::Group::delete_all(&mut *tx).await?;
+            db::Tenant::delete_all(&mut *tx).await?;
+        }
+
+        // Tenants come first, as nodes refer to them
+        for tenant in snapshot.tenants.iter() {
+            let created = snapshot_time(tenant.created)?;
+            db::Tenant::restore(
+                &mut *tx,
+                &tenant.name,
+                tenant.description.as_deref(),
+                created,
+            )
+            .await?;
+        }
+
+        let mut node_ids = HashMap::new();
+        for node in snapshot.nodes.iter() {
+            if let Some(tenant) = node.tenant.as_deref()
+                && db::Tenant::find(&mut *tx, tenant).await?.is_none()
+            {
+                return Err(snapshot_error(format!("unknown tenant {tenant}")));
+            }
+
+            if node.superadmin && node.tenant.is_some() {
+                return Err(snapshot_error(format!(
+                    "superadmin {} can't belong to a tenant",
+                    node.name
+                )));
+            }
+
+            let created = snapshot_time(node.created)?;
+            let row = db::Node::restore(
+                &mut *tx,
+                &node.name,
+                &node.node,
+                node.superadmin,
+                node.admin_tier.map(AdminTier::as_str),
+                node.description.as_deref(),
+                created,
+            )
+            .await?;
+            db::Node::set_tenant(&mut *tx, row.id, node.tenant.as_deref()).await?;
+            node_ids.insert(node.node.as_str(), row.id);
+        }
+
+        let mut role_ids = HashMap::new();
+        for role in snapshot.roles.iter() {
+            let created = snapshot_time(role.created)?;
+            let row = db::Role::restore(&mut *tx, &role.role, role.description.as_deref(), created)
+                .await?;
+            role_ids.insert(role.role.as_str(), row.id);
+        }
+
+        // Grants and role parents may refer to nodes and roles that already
+        // exist, when merging, but not to ones that don't exist at all.
+        for role_parent in snapshot.role_parents.iter() {
+            let mut ids = [0; 2];
+            for (id, role) in ids.iter_mut().zip([&role_parent.role, &role_parent.parent]) {
+                *id = match role_ids.get(role.as_str()) {
+                    Some(id) => *id,
+                    None => match db::Role::find(&mut *tx, role).await? {
+                        Some(row) => row.id,
+                        None => return Err(snapshot_error(format!("unknown role {role}"))),
+                    },
+                };
+            }
+
+            let [role_id, parent_id] = ids;
+            if db::RoleParent::implies(&mut *tx, role_id, parent_id).await? {
+                return Err(Error::RoleCycleError);
+            }
+
+            db::RoleParent::insert_if_missing(&mut *tx, role_id, parent_id).await?;
+        }
+
+        for grant in snapshot.grants.iter() {
+            let node_id = match node_ids.get(grant.node.as_str()) {
+                Some(id) => *id,
+                None => match db::Node::find(&mut *tx, &grant.node).await? {
+                    Some(row) => row.id,
+                    None => return Err(snapshot_error(format!("unknown node {}", grant.node))),
+                },
+            };
+
+            let role_id = match role_ids.get(grant.role.as_str()) {
+                Some(id) => *id,
+                None => match db::Role::find(&mut *tx, &grant.role).await? {
+                    Some(row) => row.id,
+                    None => return Err(snapshot_error(format!("unknown role {}", grant.role))),
+                },
+            };
+
+            let expires_at = grant.expires_at.map(snapshot_time).transpose()?;
+            let created = grant.created.map(snapshot_time).transpose()?;
+            let resource = grant.resource.as_deref().unwrap_or_default();
+            db::NodeRole::restore(&mut *tx, node_id, role_id, resource, expires_at, created)
+                .await?;
+        }
+
+        for group in snapshot.groups.iter() {
+            let created = snapshot_time(group.created)?;
+            let row =
+                db::Group::restore(&mut *tx, &group.name, group.description.as_deref(), created)
+                    .await?;
+
+            for node in group.members.iter() {
+                let node_id = match node_ids.get(node.as_str()) {
+                    Some(id) => *id,
+                    None => match db::Node::find(&mut *tx, node).await? {
+                        Some(row) => row.id,
+                        None => return Err(snapshot_error(format!("unknown node {node}"))),
+                    },
+                };
+
+                db::Group::add_member(&mut *tx, row.id, node_id).await?;
+            }
+
+            for role in group.roles.iter() {
+                let role_id = match role_ids.get(role.as_str()) {
+                    Some(id) => *id,
+                    None => match db::Role::find(&mut *tx, role).await? {
+                        Some(row) => row.id,
+                        None => return Err(snapshot_error(format!("unknown role {role}"))),
+                    },
+                };
+
+                db::Group::add_role(&mut *tx, row.id, role_id).await?;
+            }
+        }
+
+        for deny in snapshot.denies.iter() {
+            let node_id = match node_ids.get(deny.node.as_str()) {
+                Some(id) => *id,
+                None => match db::Node::find(&mut *tx, &deny.node).await? {
+                    Some(row) => row.id,
+                    None => return Err(snapshot_error(format!("unknown node {}", deny.node))),
+                },
+            };
+
+            let role_id = match role_ids.get(deny.role.as_str()) {
+                Some(id) => *id,
+                None => match db::Role::find(&mut *tx, &deny.role).await? {
+                    Some(row) => row.id,
+                    None => return Err(snapshot_error(format!("unknown role {}", deny.role))),
+                },
+            };
+
+            let created = snapshot_time(deny.created)?;
+            db::NodeRoleDeny::restore(&mut *tx, node_id, role_id, created).await?;
+        }
+
+        for label in snapshot.labels.iter() {
+            let node_id = match node_ids.get(label.node.as_str()) {
+                Some(id) => *id,
+                None => match db::Node::find(&mut *tx, &label.node).await? {
+                    Some(row) => row.id,
+                    None => return Err(snapshot_error(format!("unknown node {}", label.node))),
+                },
+            };
+
+            let parsed = Label {
+                key: label.key.clone(),
+                value: label.value.clone(),
+            };
+            parsed.validate().map_err(snapshot_error)?;
+
+            let created = snapshot_time(label.created)?;
+            db::NodeLabel::restore(&mut *tx, node_id, &label.key, &label.value, created).await?;
+        }
+
+        for grant in snapshot.selector_grants.iter() {
+            let selector: LabelSelector = grant.selector.parse().map_err(snapshot_error)?;
+
+            let role_id = match role_ids.get(grant.role.as_str()) {
+                Some(id) => *id,
+                None => match db::Role::find(&mut *tx, &grant.role).await? {
+                    Some(row) => row.id,
+                    None => return Err(snapshot_error(format!("unknown role {}", grant.role))),
+                },
+            };
+
+            let created = snapshot_time(grant.created)?;
+            let row = db::SelectorRole::restore(&mut *tx, &selector.to_string(), role_id, created)
+                .await?;
+            for label in selector.labels.iter() {
+                db::SelectorRole::add_term(&mut *tx, row.id, &label.key, &label.value).await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Whether the caller may run the command. Other than superadmins, callers
+    /// need [`Cmd::required_tier`] and must stay within their tenant.
+    pub async fn authorize(&self, caller: NodeId, cmd: &Cmd) -> Result<bool, Error> {
+        if let Cmd::Bootstrap { .. } | Cmd::Redeem { .. } = cmd {
+            return Ok(true);
+        }
+
+        let caller = format!("{caller}");
+        let Some(node) = db::Node::find(&self.db, &caller).await? else {
+            return Ok(false);
+        };
+
+        if node.superadmin
+            || matches!(
+                cmd,
+                Cmd::WhoAmI | Cmd::RequestRole { .. } | Cmd::CancelOp { .. }
+            )
+        {
+            return Ok(true);
+        }
+
+        if let Cmd::RotateNodeKey { node, .. } = cmd
+            && self.self_service_rotation
+            && *node == caller
+        {
+            return Ok(true);
+        }
+
+        let tier = node.admin_tier.as_deref().and_then(AdminTier::parse);
+        let (Some(tier), Some(required)) = (tier, cmd.required_tier()) else {
+            return Ok(false);
+        };
+
+        if tier < required {
+            return Ok(false);
+        }
+
+        if let Some(tenant) = node.tenant.as_deref()
+            && !self.within_tenant(tenant, cmd).await?
+        {
+            return Ok(false);
+        }
+
+        if let Cmd::UpdateNode { node, .. }
+        | Cmd::RotateNodeKey { node, .. }
+        | Cmd::DeleteNode { node } = cmd
+            && let Some(target) = db::Node::find(&self.db, node).await?
+            && (target.superadmin || target.admin_tier.is_some())
+        {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    pub async fn purge_expired_grants(&self) -> Result<u64, Error> {
+        let res = db::NodeRole::delete_expired(&self.db).await?;
+        Ok(res)
+    }
+
+    /// Periodically purge expired grants and pending operations in the background.
+    /// Expired rows are ignored by every lookup regardless, this just keeps the
+    /// tables tidy.
+    pub fn spawn_sweeper(&self, period: Duration) -> JoinHandle<()> {
+        let arbiter = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                match arbiter.purge_expired_grants().await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::info!(purged, "purged_expired_grants"),
+                    Err(e) => tracing::warn!(err = ?e, "purge_expired_grants_failed"),
+                }
+                match db::PendingOp::delete_expired(&arbiter.db).await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::info!(purged, "purged_expired_ops"),
+                    Err(e) => tracing::warn!(err = ?e, "purge_expired_ops_failed"),
+                }
+            }
+        })
+    }
+
+    /// Does the command only touch nodes, roles and groups the tenant owns?
+    async fn within_tenant(&self, tenant: &str, cmd: &Cmd) -> Result<bool, Error> {
+        let scoped = |t: &Option<String>| t.as_deref() == Some(tenant);
+        let (nodes, names): (Vec<&str>, Vec<&str>) = match cmd {
+            Cmd::Roles { tenant: t }
+            | Cmd::Nodes { tenant: t }
+            | Cmd::Groups { tenant: t }
+            | Cmd::CreateNode { tenant: t, .. } => return Ok(scoped(t)),
+            Cmd::RoleNodes {
+                role, tenant: t, ..
+            } if scoped(t) => (vec![], vec![role]),
+            Cmd::ShowRole { role }
+            | Cmd::CreateRole { role, .. }
+            | Cmd::DescribeRole { role, .. }
+            | Cmd::DeleteRole { role, .. }
+            | Cmd::RoleParents { role } => (vec![], vec![role]),
+            Cmd::RenameRole { role, new_role } => (vec![], vec![role, new_role]),
+            Cmd::AddRoleParent { role, parent } | Cmd::RemoveRoleParent { role, parent } => {
+                (vec![], vec![role, parent])
+            }
+            Cmd::NodeRoles { node, .. }
+            | Cmd::UpdateNode { node, .. }
+            | Cmd::RotateNodeKey { node, .. }
+            | Cmd::NodeKeyHistory { node }
+            | Cmd::DeleteNode { node }
+            | Cmd::NodeLabels { node }
+            | Cmd::SetNodeLabel { node, .. }
+            | Cmd::RemoveNodeLabel { node, .. } => (vec![node], vec![]),
+            Cmd::GrantRole { node, role, .. }
+            | Cmd::RevokeRole { node, role, .. }
+            | Cmd::DenyRole { node, role }
+            | Cmd::RemoveRoleDeny { node, role } => (vec![node], vec![role]),
+            Cmd::CreateGroup { group, .. }
+            | Cmd::DeleteGroup { group }
+            | Cmd::GroupMembers { group }
+            | Cmd::GroupRoles { group } => (vec![], vec![group]),
+            Cmd::AddGroupMember { group, node } | Cmd::RemoveGroupMember { group, node } => {
+                (vec![node], vec![group])
+            }
+            Cmd::GrantGroupRole { group, role } | Cmd::RevokeGroupRole { group, role } => {
+                (vec![], vec![group, role])
+            }
+            Cmd::Check { checks } => checks
+                .iter()
+                .map(|c| (c.node.as_str(), c.role.as_str()))
+                .unzip(),
+            _ => return Ok(false),
+        };
+
+        if !names.iter().all(|name| tenant_owns(tenant, name)) {
+            return Ok(false);
+        }
+
+        for node in nodes {
+            match db::Node::find(&self.db, node).await? {
+                Some(node) if node.tenant.as_deref() == Some(tenant) => {}
+                _ => return Ok(false),
+            }
+        }
+
+        // Roles and groups can be granted to nodes outside the tenant, whose
+        // grants and memberships must survive the tenant's changes
+        match cmd {
+            Cmd::DeleteRole { role, .. } | Cmd::RenameRole { role, .. } => {
+                if let Some(role) = db::Role::find(&self.db, role).await? {
+                    let outside = db::Role::links_outside_tenant(&self.db, role.id, tenant).await?;
+                    return Ok(outside == 0);
+                }
+            }
+            Cmd::DeleteGroup { group } => {
+                if let Some(group) = db::Group::find(&self.db, group).await? {
+                    let members = db::Group::members(&self.db, group.id).await?;
+                    return Ok(members.iter().all(|n| n.tenant.as_deref() == Some(tenant)));
+                }
+            }
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    async fn get_tenant(&self, tenant: &str) -> Result<db::Tenant, Error> {
+        match db::Tenant::find(&self.db, tenant).await? {
+            None => Err(Error::NoSuchTenantError),
+            Some(tenant) => Ok(tenant),
+        }
+    }
+
+    async fn get_group(&self, group: &str) -> Result<db::Group, Error> {
+        match db::Group::find(&self.db, group).await? {
+            None => Err(Error::NoSuchGroupError),
+            Some(group) => Ok(group),
+        }
+    }
+
+    async fn get_op(&self, id: i64) -> Result<db::PendingOp, Error> {
+        match db::PendingOp::find_open(&self.db, id).await? {
+            None => Err(Error::NoSuchOpError),
+            Some(op) => Ok(op),
+        }
+    }
+
+    async fn get_request(&self, id: i64) -> Result<db::AccessRequest, Error> {
+        match db::AccessRequest::find(&self.db, id).await? {
+            None => Err(Error::NoSuchRequestError),
+            Some(request) => Ok(request),
+        }
+    }
+
+    async fn get_pending_request(&self, id: i64) -> Result<db::AccessRequest, Error> {
+        let request = self.get_request(id).await?;
+        if request.status != RequestStatus::Pending.as_str() {
+            return Err(Error::NoSuchRequestError);
+        }
+
+        Ok(request)
+    }
+
+    async fn get_role(&self, role: &str) -> Result<db::Role, Error> {
+        match db::Role::find(&self.db, role).await? {
+            None => Err(Error::NoSuchRoleError),
+            Some(role) => Ok(role),
+        }
+    }
+
+    /// Look up a role about to be granted or inherited, creating it unless in strict mode.
+    async fn use_role(&self, role: &str) -> Result<db::Role, Error> {
+        if self.strict_roles {
+            return self.get_role(role).await;
+        }
+
+        Ok(db::Role::ensure(&self.db, role).await?)
+    }
+
+    async fn get_node(&self, node: &str) -> Result<db::Node, Error> {
+        match db::Node::find(&self.db, node).await? {
+            None => Err(Error::NoSuchNodeError),
+            Some(node) => Ok(node),
+        }
+    }
+}
+
+fn from_timestamp(t: i64) -> Option<chrono::NaiveDateTime> {
+    DateTime::from_timestamp(t, 0).map(|t| t.naive_utc())
+}
+
+/// The time `ttl` from now, or an error if it is longer than [`MAX_TTL`].
+fn expires_in(ttl: Duration) -> Result<chrono::NaiveDateTime, Error> {
+    chrono::TimeDelta::from_std(ttl)
+        .ok()
+        .filter(|_| ttl.as_secs() <= MAX_TTL)
+        .and_then(|ttl| Utc::now().naive_utc().checked_add_signed(ttl))
+        .ok_or_else(|| Error::InvalidTtlError(format!("{}s is too long", ttl.as_secs())))
+}
+
+fn snapshot_time(t: i64) -> Result<chrono::NaiveDateTime, Error> {
+    from_timestamp(t).ok_or_else(|| snapshot_error(format!("invalid timestamp {t}")))
+}
+
+fn snapshot_error(e: String) -> Error {
+    Error::InvalidSnapshotError(e)
+}
+
+fn generate_token() -> String {
+    let bytes: [u8; 20] = rand::random();
+    BASE32_NOPAD.encode(&bytes).to_ascii_lowercase()
 }
 
 async fn init_db(path: PathBuf) -> Result<SqlitePool, sqlx::Error> {
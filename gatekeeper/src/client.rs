@@ -1,7 +1,13 @@
+use std::{fmt::Debug, time::Duration};
+
 use bincode::Decode;
 use iroh::{Endpoint, NodeAddr, NodeId};
 
-use crate::{ALPN, Cmd, Either, Error, Node};
+use crate::{
+    ALPN, AccessRequest, AdminTier, AuditEntry, AuditFilter, Cmd, Either, Error, ErrorCode, Grant,
+    Group, ImportMode, Invite, InviteTicket, KeyRotation, Label, LabelSelector, Node, PendingOp,
+    Role, RoleCheck, SelectorGrant, Snapshot, Tenant, WhoAmI,
+};
 
 const CHUNK_SIZE: usize = 1_000_000;
 
@@ -12,6 +18,13 @@ pub struct Client {
     bincode_config: bincode::config::Configuration,
 }
 
+impl Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Client {{ server: {:?} }}", self.server)?;
+        Ok(())
+    }
+}
+
 impl Client {
     pub fn new(endpoint: Endpoint, server: NodeId) -> Self {
         Self {
@@ -29,31 +42,284 @@ impl Client {
         }
     }
 
-    pub async fn roles(&self) -> Result<Vec<String>, Error> {
-        self.send(Cmd::Roles).await
+    pub async fn bootstrap(
+        &self,
+        token: String,
+        name: String,
+        node: NodeId,
+    ) -> Result<Node, Error> {
+        self.send(Cmd::Bootstrap {
+            token,
+            name,
+            node: format!("{node}"),
+        })
+        .await
     }
 
-    pub async fn nodes(&self) -> Result<Vec<Node>, Error> {
-        self.send(Cmd::Nodes).await
+    pub async fn whoami(&self) -> Result<WhoAmI, Error> {
+        self.send(Cmd::WhoAmI).await
     }
 
-    pub async fn node_roles(&self, node: NodeId) -> Result<Vec<String>, Error> {
+    /// Every role, or only those belonging to `tenant`. Tenant administrators
+    /// can only list their own tenant's roles.
+    pub async fn roles(&self, tenant: Option<String>) -> Result<Vec<String>, Error> {
+        self.send(Cmd::Roles { tenant }).await
+    }
+
+    pub async fn show_role(&self, role: String) -> Result<Role, Error> {
+        self.send(Cmd::ShowRole { role }).await
+    }
+
+    pub async fn create_role(
+        &self,
+        role: String,
+        description: Option<String>,
+    ) -> Result<Role, Error> {
+        self.send(Cmd::CreateRole { role, description }).await
+    }
+
+    pub async fn describe_role(
+        &self,
+        role: String,
+        description: Option<String>,
+    ) -> Result<Role, Error> {
+        self.send(Cmd::DescribeRole { role, description }).await
+    }
+
+    pub async fn rename_role(&self, role: String, new_role: String) -> Result<Role, Error> {
+        self.send(Cmd::RenameRole { role, new_role }).await
+    }
+
+    pub async fn delete_role(&self, role: String, cascade: bool) -> Result<(), Error> {
+        self.send(Cmd::DeleteRole { role, cascade }).await
+    }
+
+    /// Every node, or only those in `tenant`. Tenant administrators can only
+    /// list their own tenant's nodes.
+    pub async fn nodes(&self, tenant: Option<String>) -> Result<Vec<Node>, Error> {
+        self.send(Cmd::Nodes { tenant }).await
+    }
+
+    pub async fn node_roles(&self, node: NodeId, effective: bool) -> Result<Vec<Grant>, Error> {
         self.send(Cmd::NodeRoles {
             node: format!("{node}"),
+            effective,
+        })
+        .await
+    }
+
+    pub async fn role_nodes(
+        &self,
+        role: String,
+        indirect: bool,
+        tenant: Option<String>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Node>, Error> {
+        self.send(Cmd::RoleNodes {
+            role,
+            indirect,
+            tenant,
+            limit,
+            offset,
+        })
+        .await
+    }
+
+    /// Every group, or only those belonging to `tenant`. Tenant administrators
+    /// can only list their own tenant's groups.
+    pub async fn groups(&self, tenant: Option<String>) -> Result<Vec<Group>, Error> {
+        self.send(Cmd::Groups { tenant }).await
+    }
+
+    pub async fn tenants(&self) -> Result<Vec<Tenant>, Error> {
+        self.send(Cmd::Tenants).await
+    }
+
+    pub async fn create_tenant(
+        &self,
+        tenant: String,
+        description: Option<String>,
+    ) -> Result<Tenant, Error> {
+        self.send(Cmd::CreateTenant {
+            tenant,
+            description,
         })
         .await
     }
 
+    pub async fn delete_tenant(&self, tenant: String) -> Result<(), Error> {
+        self.send(Cmd::DeleteTenant { tenant }).await
+    }
+
+    pub async fn set_node_tenant(
+        &self,
+        node: NodeId,
+        tenant: Option<String>,
+    ) -> Result<Node, Error> {
+        self.send(Cmd::SetNodeTenant {
+            node: format!("{node}"),
+            tenant,
+        })
+        .await
+    }
+
+    pub async fn create_group(
+        &self,
+        group: String,
+        description: Option<String>,
+    ) -> Result<Group, Error> {
+        self.send(Cmd::CreateGroup { group, description }).await
+    }
+
+    pub async fn delete_group(&self, group: String) -> Result<(), Error> {
+        self.send(Cmd::DeleteGroup { group }).await
+    }
+
+    pub async fn group_members(&self, group: String) -> Result<Vec<Node>, Error> {
+        self.send(Cmd::GroupMembers { group }).await
+    }
+
+    pub async fn add_group_member(&self, group: String, node: NodeId) -> Result<(), Error> {
+        self.send(Cmd::AddGroupMember {
+            group,
+            node: format!("{node}"),
+        })
+        .await
+    }
+
+    pub async fn remove_group_member(&self, group: String, node: NodeId) -> Result<(), Error> {
+        self.send(Cmd::RemoveGroupMember {
+            group,
+            node: format!("{node}"),
+        })
+        .await
+    }
+
+    pub async fn group_roles(&self, group: String) -> Result<Vec<String>, Error> {
+        self.send(Cmd::GroupRoles { group }).await
+    }
+
+    pub async fn grant_group_role(&self, group: String, role: String) -> Result<(), Error> {
+        self.send(Cmd::GrantGroupRole { group, role }).await
+    }
+
+    pub async fn revoke_group_role(&self, group: String, role: String) -> Result<(), Error> {
+        self.send(Cmd::RevokeGroupRole { group, role }).await
+    }
+
+    pub async fn node_labels(&self, node: NodeId) -> Result<Vec<Label>, Error> {
+        self.send(Cmd::NodeLabels {
+            node: format!("{node}"),
+        })
+        .await
+    }
+
+    pub async fn set_node_label(&self, node: NodeId, label: Label) -> Result<(), Error> {
+        self.send(Cmd::SetNodeLabel {
+            node: format!("{node}"),
+            label,
+        })
+        .await
+    }
+
+    pub async fn remove_node_label(&self, node: NodeId, key: String) -> Result<(), Error> {
+        self.send(Cmd::RemoveNodeLabel {
+            node: format!("{node}"),
+            key,
+        })
+        .await
+    }
+
+    pub async fn selector_grants(&self) -> Result<Vec<SelectorGrant>, Error> {
+        self.send(Cmd::SelectorGrants).await
+    }
+
+    pub async fn grant_selector_role(
+        &self,
+        selector: LabelSelector,
+        role: String,
+    ) -> Result<(), Error> {
+        self.send(Cmd::GrantSelectorRole { selector, role }).await
+    }
+
+    pub async fn revoke_selector_role(
+        &self,
+        selector: LabelSelector,
+        role: String,
+    ) -> Result<(), Error> {
+        self.send(Cmd::RevokeSelectorRole { selector, role }).await
+    }
+
+    pub async fn role_parents(&self, role: String) -> Result<Vec<String>, Error> {
+        self.send(Cmd::RoleParents { role }).await
+    }
+
+    pub async fn add_role_parent(&self, role: String, parent: String) -> Result<(), Error> {
+        self.send(Cmd::AddRoleParent { role, parent }).await
+    }
+
+    pub async fn remove_role_parent(&self, role: String, parent: String) -> Result<(), Error> {
+        self.send(Cmd::RemoveRoleParent { role, parent }).await
+    }
+
+    /// Create a node, optionally in `tenant`, which tenant administrators must
+    /// give as their own.
     pub async fn create_node(
         &self,
         name: String,
         node: NodeId,
         superadmin: bool,
+        tenant: Option<String>,
     ) -> Result<Node, Error> {
         self.send(Cmd::CreateNode {
             name,
             node: format!("{node}"),
             superadmin,
+            tenant,
+        })
+        .await
+    }
+
+    pub async fn update_node(
+        &self,
+        node: NodeId,
+        name: Option<String>,
+        superadmin: Option<bool>,
+        description: Option<String>,
+    ) -> Result<Node, Error> {
+        self.send(Cmd::UpdateNode {
+            node: format!("{node}"),
+            name,
+            superadmin,
+            description,
+        })
+        .await
+    }
+
+    pub async fn set_admin_tier(
+        &self,
+        node: NodeId,
+        tier: Option<AdminTier>,
+    ) -> Result<Node, Error> {
+        self.send(Cmd::SetAdminTier {
+            node: format!("{node}"),
+            tier,
+        })
+        .await
+    }
+
+    pub async fn rotate_node_key(&self, node: NodeId, new_node: NodeId) -> Result<Node, Error> {
+        self.send(Cmd::RotateNodeKey {
+            node: format!("{node}"),
+            new_node: format!("{new_node}"),
+        })
+        .await
+    }
+
+    pub async fn node_key_history(&self, node: NodeId) -> Result<Vec<KeyRotation>, Error> {
+        self.send(Cmd::NodeKeyHistory {
+            node: format!("{node}"),
         })
         .await
     }
@@ -65,26 +331,167 @@ impl Client {
         .await
     }
 
-    pub async fn grant_role(&self, node: NodeId, role: String) -> Result<(), Error> {
+    /// Grant a role, on resources matching `resource` only if given (see
+    /// [`crate::Arbiter::has_role_on`]), expiring after `ttl` if given.
+    pub async fn grant_role(
+        &self,
+        node: NodeId,
+        role: String,
+        resource: Option<String>,
+        ttl: Option<Duration>,
+    ) -> Result<(), Error> {
         self.send(Cmd::GrantRole {
             node: format!("{node}"),
             role,
+            resource,
+            ttl: ttl.map(|ttl| ttl.as_secs()),
         })
         .await
     }
 
-    pub async fn revoke_role(&self, node: NodeId, role: String) -> Result<(), Error> {
+    pub async fn revoke_role(
+        &self,
+        node: NodeId,
+        role: String,
+        resource: Option<String>,
+    ) -> Result<(), Error> {
         self.send(Cmd::RevokeRole {
             node: format!("{node}"),
             role,
+            resource,
+        })
+        .await
+    }
+
+    pub async fn deny_role(&self, node: NodeId, role: String) -> Result<(), Error> {
+        self.send(Cmd::DenyRole {
+            node: format!("{node}"),
+            role,
         })
         .await
     }
 
+    pub async fn remove_role_deny(&self, node: NodeId, role: String) -> Result<(), Error> {
+        self.send(Cmd::RemoveRoleDeny {
+            node: format!("{node}"),
+            role,
+        })
+        .await
+    }
+
+    /// Check whether nodes hold roles on resources, given as (node, role,
+    /// resource). A resource of `None` requires the role on every resource.
+    pub async fn check(
+        &self,
+        checks: Vec<(NodeId, String, Option<String>)>,
+    ) -> Result<Vec<bool>, Error> {
+        let checks = checks
+            .into_iter()
+            .map(|(node, role, resource)| RoleCheck {
+                node: format!("{node}"),
+                role,
+                resource,
+            })
+            .collect();
+
+        self.send(Cmd::Check { checks }).await
+    }
+
+    pub async fn create_invite(
+        &self,
+        roles: Vec<String>,
+        ttl: Duration,
+        uses: u32,
+    ) -> Result<Invite, Error> {
+        self.send(Cmd::CreateInvite {
+            roles,
+            ttl: ttl.as_secs(),
+            uses,
+        })
+        .await
+    }
+
+    pub async fn invites(&self) -> Result<Vec<Invite>, Error> {
+        self.send(Cmd::Invites).await
+    }
+
+    pub async fn delete_invite(&self, id: i64) -> Result<(), Error> {
+        self.send(Cmd::DeleteInvite { id }).await
+    }
+
+    pub async fn redeem(&self, ticket: &InviteTicket, name: String) -> Result<Node, Error> {
+        self.send(Cmd::Redeem {
+            ticket: format!("{ticket}"),
+            name,
+        })
+        .await
+    }
+
+    pub async fn request_role(
+        &self,
+        role: String,
+        justification: String,
+        ttl: Option<Duration>,
+    ) -> Result<AccessRequest, Error> {
+        self.send(Cmd::RequestRole {
+            role,
+            justification,
+            ttl: ttl.map(|ttl| ttl.as_secs()),
+        })
+        .await
+    }
+
+    pub async fn list_requests(&self, all: bool) -> Result<Vec<AccessRequest>, Error> {
+        self.send(Cmd::ListRequests { all }).await
+    }
+
+    pub async fn approve(&self, id: i64, note: Option<String>) -> Result<AccessRequest, Error> {
+        self.send(Cmd::Approve { id, note }).await
+    }
+
+    pub async fn deny(&self, id: i64, note: Option<String>) -> Result<AccessRequest, Error> {
+        self.send(Cmd::Deny { id, note }).await
+    }
+
+    pub async fn pending_ops(&self) -> Result<Vec<PendingOp>, Error> {
+        self.send(Cmd::PendingOps).await
+    }
+
+    /// Confirm a command held by the two-person rule, returning the held
+    /// command's response. Use `()` when the response isn't needed.
+    pub async fn confirm<R: Decode<()>>(&self, id: i64) -> Result<R, Error> {
+        self.send(Cmd::Confirm { id }).await
+    }
+
+    pub async fn cancel_op(&self, id: i64) -> Result<(), Error> {
+        self.send(Cmd::CancelOp { id }).await
+    }
+
+    /// Node ID of the server this client talks to.
+    pub fn server_id(&self) -> NodeId {
+        match &self.server {
+            Either::Left(node_addr) => node_addr.node_id,
+            Either::Right(node_id) => *node_id,
+        }
+    }
+
+    pub async fn audit_log(&self, filter: AuditFilter) -> Result<Vec<AuditEntry>, Error> {
+        self.send(Cmd::AuditLog(filter)).await
+    }
+
+    pub async fn export(&self) -> Result<Snapshot, Error> {
+        self.send(Cmd::Export).await
+    }
+
+    pub async fn import(&self, snapshot: Snapshot, mode: ImportMode) -> Result<(), Error> {
+        self.send(Cmd::Import { snapshot, mode }).await
+    }
+
     async fn send<R: Decode<()>>(&self, cmd: Cmd) -> Result<R, Error> {
         let json = bincode::encode_to_vec(&cmd, self.bincode_config)?;
         let conn = match &self.server {
             Either::Left(node_id) => self.endpoint.connect(node_id.clone(), ALPN).await?,
+            #[allow(clippy::clone_on_copy)]
             Either::Right(node_addr) => self.endpoint.connect(node_addr.clone(), ALPN).await?,
         };
 
@@ -100,7 +507,7 @@ impl Client {
 
         conn.close(0u32.into(), b"bye");
 
-        let rsp = bincode::decode_from_slice(&data, self.bincode_config)?.0;
-        Ok(rsp)
+        let rsp: Result<R, ErrorCode> = bincode::decode_from_slice(&data, self.bincode_config)?.0;
+        rsp.map_err(Error::from)
     }
 }
@@ -1,7 +1,12 @@
+use std::str::FromStr;
+
 use bincode::{Decode, Encode};
-use clap::Subcommand;
+use chrono::{DateTime, NaiveDate};
+use clap::{Args, Subcommand, ValueEnum};
+use iroh::NodeId;
+use serde::{Deserialize, Serialize};
 
-use crate::db;
+use crate::{ImportMode, InviteTicket, Snapshot, db};
 
 pub const ALPN: &[u8] = b"gatekeeper";
 
@@ -13,14 +18,91 @@ pub enum Either<A, B> {
 
 #[derive(Clone, Debug, Decode, Encode, Subcommand)]
 pub enum Cmd {
+    /// Create the first node as a superadmin, using the server's bootstrap token
+    Bootstrap {
+        /// Bootstrap token printed or written by the server on startup
+        token: String,
+        /// Node name (unique)
+        #[arg(long)]
+        name: String,
+        /// Node public key (unique)
+        #[arg(long)]
+        node: String,
+    },
+    /// Show the calling node and the roles it holds
+    #[command(name = "whoami")]
+    WhoAmI,
     /// List roles
-    Roles,
+    Roles {
+        /// Only list roles belonging to this tenant
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+    /// Show a role and its description
+    ShowRole {
+        /// Role
+        role: String,
+    },
+    /// Create a new role
+    CreateRole {
+        /// Role (unique)
+        role: String,
+        /// What the role is for
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Set or clear the description of a role
+    DescribeRole {
+        /// Role
+        role: String,
+        /// What the role is for (cleared if omitted)
+        description: Option<String>,
+    },
+    /// Rename a role, keeping its grants
+    RenameRole {
+        /// Role
+        role: String,
+        /// New role name (unique)
+        new_role: String,
+    },
+    /// Delete a role
+    DeleteRole {
+        /// Role
+        role: String,
+        /// Also revoke the role from every node holding it, rather than refusing
+        #[arg(long, default_value_t = false)]
+        cascade: bool,
+    },
     /// List nodes
-    Nodes,
+    Nodes {
+        /// Only list nodes in this tenant
+        #[arg(long)]
+        tenant: Option<String>,
+    },
     /// List roles granted to the specified node
     NodeRoles {
         /// Node public key
         node: String,
+        /// Include roles inherited through parent roles?
+        #[arg(long, default_value_t = false)]
+        effective: bool,
+    },
+    /// List nodes holding the specified role
+    RoleNodes {
+        /// Role
+        role: String,
+        /// Include nodes holding the role through parent roles, groups or selector grants?
+        #[arg(long, default_value_t = false)]
+        indirect: bool,
+        /// Only list nodes in this tenant
+        #[arg(long)]
+        tenant: Option<String>,
+        /// Maximum number of nodes to return
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+        /// Number of nodes to skip
+        #[arg(long, default_value_t = 0)]
+        offset: u32,
     },
     /// Create a new node
     CreateNode {
@@ -33,6 +115,50 @@ pub enum Cmd {
         /// Grant superadmin access to node?
         #[arg(long, default_value_t = false)]
         superadmin: bool,
+        /// Tenant the node belongs to
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+    /// Rename a node, change its superadmin flag or set its description, keeping its grants
+    UpdateNode {
+        /// Node public key
+        node: String,
+        /// New node name (unique)
+        #[arg(long)]
+        name: Option<String>,
+        /// Grant or remove superadmin access
+        #[arg(long)]
+        superadmin: Option<bool>,
+        /// Free-form notes about the node (cleared if empty)
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Set or clear the administrative tier of a node
+    SetAdminTier {
+        /// Node public key
+        node: String,
+        /// Administrative tier (cleared if omitted)
+        tier: Option<AdminTier>,
+    },
+    /// Move a node into a tenant, or out of its tenant
+    SetNodeTenant {
+        /// Node public key
+        node: String,
+        /// Tenant (cleared if omitted)
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+    /// Move a node to a new public key, keeping its name and grants
+    RotateNodeKey {
+        /// Current node public key
+        node: String,
+        /// New node public key (unique)
+        new_node: String,
+    },
+    /// List the past keys of a node
+    NodeKeyHistory {
+        /// Current or past node public key
+        node: String,
     },
     /// Delete a node
     DeleteNode {
@@ -45,6 +171,12 @@ pub enum Cmd {
         node: String,
         /// Role
         role: String,
+        /// Only grant the role on this resource, e.g. bucket/a or bucket/*/logs (every resource if omitted)
+        #[arg(long)]
+        resource: Option<String>,
+        /// Grant lifetime, e.g. 30m, 8h or 7d (permanent if omitted)
+        #[arg(long, value_parser = parse_ttl)]
+        ttl: Option<u64>,
     },
     /// Revoke a previously granted role
     RevokeRole {
@@ -52,7 +184,702 @@ pub enum Cmd {
         node: String,
         /// Role
         role: String,
+        /// Resource the grant is scoped to (the unscoped grant if omitted)
+        #[arg(long)]
+        resource: Option<String>,
+    },
+    /// Deny a role to a node, overriding any grant of it, directly, through a group or a parent role
+    DenyRole {
+        /// Node public key
+        node: String,
+        /// Role
+        role: String,
+    },
+    /// Remove a previously added deny
+    RemoveRoleDeny {
+        /// Node public key
+        node: String,
+        /// Role
+        role: String,
+    },
+    /// List the parents of a role
+    RoleParents {
+        /// Role
+        role: String,
+    },
+    /// Make a role inherit from a parent role, so that nodes granted the parent also hold the role
+    AddRoleParent {
+        /// Role
+        role: String,
+        /// Parent role
+        parent: String,
+    },
+    /// Remove a previously added parent role
+    RemoveRoleParent {
+        /// Role
+        role: String,
+        /// Parent role
+        parent: String,
+    },
+    /// List node groups
+    Groups {
+        /// Only list groups belonging to this tenant
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+    /// Create a node group, whose members hold the roles granted to it
+    CreateGroup {
+        /// Group name (unique)
+        group: String,
+        /// What the group is for
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Delete a node group, revoking its roles from its members
+    DeleteGroup {
+        /// Group name
+        group: String,
+    },
+    /// List the members of a group
+    GroupMembers {
+        /// Group name
+        group: String,
+    },
+    /// Add a node to a group
+    AddGroupMember {
+        /// Group name
+        group: String,
+        /// Node public key
+        node: String,
+    },
+    /// Remove a node from a group
+    RemoveGroupMember {
+        /// Group name
+        group: String,
+        /// Node public key
+        node: String,
     },
+    /// List the roles granted to a group
+    GroupRoles {
+        /// Group name
+        group: String,
+    },
+    /// Grant a role to every member of a group
+    GrantGroupRole {
+        /// Group name
+        group: String,
+        /// Role
+        role: String,
+    },
+    /// Revoke a role previously granted to a group
+    RevokeGroupRole {
+        /// Group name
+        group: String,
+        /// Role
+        role: String,
+    },
+    /// List the labels attached to a node
+    NodeLabels {
+        /// Node public key
+        node: String,
+    },
+    /// Attach a label to a node, replacing any existing value for its key
+    SetNodeLabel {
+        /// Node public key
+        node: String,
+        /// Label, in the form <key>=<value>, e.g. env=prod
+        label: Label,
+    },
+    /// Remove a label from a node
+    RemoveNodeLabel {
+        /// Node public key
+        node: String,
+        /// Label key
+        key: String,
+    },
+    /// List selector grants
+    SelectorGrants,
+    /// Grant a role to every node carrying all of a selector's labels, now or later
+    GrantSelectorRole {
+        /// Label selector, in the form <key>=<value>[,<key>=<value>...]
+        selector: LabelSelector,
+        /// Role
+        role: String,
+    },
+    /// Revoke a role previously granted to a selector
+    RevokeSelectorRole {
+        /// Label selector
+        selector: LabelSelector,
+        /// Role
+        role: String,
+    },
+    /// List tenants
+    Tenants,
+    /// Create a tenant, whose administrators can only manage its nodes, roles and groups
+    CreateTenant {
+        /// Tenant name (unique), also the prefix of its roles and groups, e.g. infra/deploy
+        tenant: String,
+        /// What the tenant is for
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Delete a tenant that no longer has any nodes
+    DeleteTenant {
+        /// Tenant name
+        tenant: String,
+    },
+    /// Check whether nodes hold roles
+    Check {
+        /// Checks to perform, in the form <node>:<role> or <node>:<role>@<resource>
+        #[arg(required = true)]
+        checks: Vec<RoleCheck>,
+    },
+    /// Create an invite ticket that lets a new node enroll itself
+    CreateInvite {
+        /// Roles granted to nodes redeeming the invite
+        #[arg(long = "role")]
+        roles: Vec<String>,
+        /// How long the invite can be redeemed for, e.g. 30m, 8h or 7d
+        #[arg(long, value_parser = parse_ttl, default_value = "1d")]
+        ttl: u64,
+        /// How many nodes can redeem the invite
+        #[arg(long, default_value_t = 1)]
+        uses: u32,
+    },
+    /// List invites
+    Invites,
+    /// Delete an invite, so it can no longer be redeemed
+    DeleteInvite {
+        /// Invite ID
+        id: i64,
+    },
+    /// Enroll the calling node using an invite ticket
+    Redeem {
+        /// Invite ticket
+        ticket: String,
+        /// Node name (unique)
+        #[arg(long)]
+        name: String,
+    },
+    /// Ask for a role to be granted to the calling node
+    RequestRole {
+        /// Role
+        role: String,
+        /// Why the role is needed
+        #[arg(long)]
+        justification: String,
+        /// Grant lifetime, e.g. 30m, 8h or 7d (permanent if omitted)
+        #[arg(long, value_parser = parse_ttl)]
+        ttl: Option<u64>,
+    },
+    /// List role requests awaiting review
+    ListRequests {
+        /// Include approved and denied requests
+        #[arg(long, default_value_t = false)]
+        all: bool,
+    },
+    /// Approve a role request, granting the role
+    Approve {
+        /// Request ID
+        id: i64,
+        /// Note for the requester
+        #[arg(long)]
+        note: Option<String>,
+    },
+    /// Deny a role request
+    Deny {
+        /// Request ID
+        id: i64,
+        /// Note for the requester
+        #[arg(long)]
+        note: Option<String>,
+    },
+    /// List commands held for confirmation by a second superadmin
+    PendingOps,
+    /// Confirm and run a command held by the two-person rule
+    Confirm {
+        /// Pending operation ID
+        id: i64,
+    },
+    /// Cancel a command held by the two-person rule
+    CancelOp {
+        /// Pending operation ID
+        id: i64,
+    },
+    /// Page through the audit log of administrative actions, most recent first
+    AuditLog(AuditFilter),
+    /// Export the full server state
+    #[command(skip)]
+    Export,
+    /// Restore a previously exported server state
+    #[command(skip)]
+    Import {
+        snapshot: Snapshot,
+        mode: ImportMode,
+    },
+}
+
+impl Cmd {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bootstrap { .. } => "bootstrap",
+            Self::WhoAmI => "whoami",
+            Self::Roles { .. } => "roles",
+            Self::ShowRole { .. } => "show-role",
+            Self::CreateRole { .. } => "create-role",
+            Self::DescribeRole { .. } => "describe-role",
+            Self::RenameRole { .. } => "rename-role",
+            Self::DeleteRole { .. } => "delete-role",
+            Self::Nodes { .. } => "nodes",
+            Self::NodeRoles { .. } => "node-roles",
+            Self::RoleNodes { .. } => "role-nodes",
+            Self::CreateNode { .. } => "create-node",
+            Self::UpdateNode { .. } => "update-node",
+            Self::SetAdminTier { .. } => "set-admin-tier",
+            Self::SetNodeTenant { .. } => "set-node-tenant",
+            Self::RotateNodeKey { .. } => "rotate-node-key",
+            Self::NodeKeyHistory { .. } => "node-key-history",
+            Self::DeleteNode { .. } => "delete-node",
+            Self::GrantRole { .. } => "grant-role",
+            Self::RevokeRole { .. } => "revoke-role",
+            Self::DenyRole { .. } => "deny-role",
+            Self::RemoveRoleDeny { .. } => "remove-role-deny",
+            Self::RoleParents { .. } => "role-parents",
+            Self::AddRoleParent { .. } => "add-role-parent",
+            Self::RemoveRoleParent { .. } => "remove-role-parent",
+            Self::Groups { .. } => "groups",
+            Self::CreateGroup { .. } => "create-group",
+            Self::DeleteGroup { .. } => "delete-group",
+            Self::GroupMembers { .. } => "group-members",
+            Self::AddGroupMember { .. } => "add-group-member",
+            Self::RemoveGroupMember { .. } => "remove-group-member",
+            Self::GroupRoles { .. } => "group-roles",
+            Self::GrantGroupRole { .. } => "grant-group-role",
+            Self::RevokeGroupRole { .. } => "revoke-group-role",
+            Self::NodeLabels { .. } => "node-labels",
+            Self::SetNodeLabel { .. } => "set-node-label",
+            Self::RemoveNodeLabel { .. } => "remove-node-label",
+            Self::SelectorGrants => "selector-grants",
+            Self::GrantSelectorRole { .. } => "grant-selector-role",
+            Self::RevokeSelectorRole { .. } => "revoke-selector-role",
+            Self::Tenants => "tenants",
+            Self::CreateTenant { .. } => "create-tenant",
+            Self::DeleteTenant { .. } => "delete-tenant",
+            Self::Check { .. } => "check",
+            Self::CreateInvite { .. } => "create-invite",
+            Self::Invites => "invites",
+            Self::DeleteInvite { .. } => "delete-invite",
+            Self::Redeem { .. } => "redeem",
+            Self::RequestRole { .. } => "request-role",
+            Self::ListRequests { .. } => "list-requests",
+            Self::Approve { .. } => "approve",
+            Self::Deny { .. } => "deny",
+            Self::PendingOps => "pending-ops",
+            Self::Confirm { .. } => "confirm",
+            Self::CancelOp { .. } => "cancel-op",
+            Self::AuditLog(_) => "audit-log",
+            Self::Export => "export",
+            Self::Import { .. } => "import",
+        }
+    }
+
+    /// Does the command change server state? Authorized mutating commands are audited.
+    pub fn is_mutating(&self) -> bool {
+        match self {
+            Self::WhoAmI
+            | Self::Roles { .. }
+            | Self::ShowRole { .. }
+            | Self::Nodes { .. }
+            | Self::NodeRoles { .. }
+            | Self::RoleNodes { .. }
+            | Self::NodeKeyHistory { .. }
+            | Self::RoleParents { .. }
+            | Self::Groups { .. }
+            | Self::GroupMembers { .. }
+            | Self::GroupRoles { .. }
+            | Self::NodeLabels { .. }
+            | Self::SelectorGrants
+            | Self::Tenants
+            | Self::Check { .. }
+            | Self::Invites
+            | Self::ListRequests { .. }
+            | Self::PendingOps
+            | Self::AuditLog(_)
+            | Self::Export => false,
+            Self::Bootstrap { .. }
+            | Self::CreateInvite { .. }
+            | Self::DeleteInvite { .. }
+            | Self::Redeem { .. }
+            | Self::RequestRole { .. }
+            | Self::Approve { .. }
+            | Self::Deny { .. }
+            | Self::Confirm { .. }
+            | Self::CancelOp { .. }
+            | Self::CreateRole { .. }
+            | Self::DescribeRole { .. }
+            | Self::RenameRole { .. }
+            | Self::DeleteRole { .. }
+            | Self::CreateNode { .. }
+            | Self::UpdateNode { .. }
+            | Self::SetAdminTier { .. }
+            | Self::SetNodeTenant { .. }
+            | Self::RotateNodeKey { .. }
+            | Self::DeleteNode { .. }
+            | Self::GrantRole { .. }
+            | Self::RevokeRole { .. }
+            | Self::DenyRole { .. }
+            | Self::RemoveRoleDeny { .. }
+            | Self::AddRoleParent { .. }
+            | Self::RemoveRoleParent { .. }
+            | Self::CreateGroup { .. }
+            | Self::DeleteGroup { .. }
+            | Self::AddGroupMember { .. }
+            | Self::RemoveGroupMember { .. }
+            | Self::GrantGroupRole { .. }
+            | Self::RevokeGroupRole { .. }
+            | Self::SetNodeLabel { .. }
+            | Self::RemoveNodeLabel { .. }
+            | Self::GrantSelectorRole { .. }
+            | Self::RevokeSelectorRole { .. }
+            | Self::CreateTenant { .. }
+            | Self::DeleteTenant { .. }
+            | Self::Import { .. } => true,
+        }
+    }
+
+    /// Lowest administrative tier allowed to run the command, or `None` if it's
+    /// reserved for superadmins.
+    pub fn required_tier(&self) -> Option<AdminTier> {
+        match self {
+            Self::Check { .. } => Some(AdminTier::Checker),
+            _ if !self.is_mutating() => Some(AdminTier::Auditor),
+            Self::CreateNode {
+                superadmin: true, ..
+            }
+            | Self::UpdateNode {
+                superadmin: Some(true),
+                ..
+            }
+            | Self::Bootstrap { .. }
+            | Self::Redeem { .. }
+            | Self::SetAdminTier { .. }
+            | Self::SetNodeTenant { .. }
+            | Self::CreateTenant { .. }
+            | Self::DeleteTenant { .. }
+            | Self::Confirm { .. }
+            | Self::Import { .. } => None,
+            _ => Some(AdminTier::Operator),
+        }
+    }
+
+    /// Arguments as recorded in the audit log. Snapshots are summarised rather
+    /// than recorded in full, and bootstrap tokens and invite tickets are left out.
+    pub fn audit_args(&self) -> String {
+        match self {
+            Self::Bootstrap { name, node, .. } => {
+                format!("Bootstrap {{ name: {name:?}, node: {node:?} }}")
+            }
+            Self::Redeem { name, .. } => format!("Redeem {{ name: {name:?} }}"),
+            Self::Import { snapshot, mode } => format!(
+                "Import {{ mode: {:?}, version: {}, nodes: {}, roles: {}, role_parents: {}, grants: {} }}",
+                mode,
+                snapshot.version,
+                snapshot.nodes.len(),
+                snapshot.roles.len(),
+                snapshot.role_parents.len(),
+                snapshot.grants.len()
+            ),
+            cmd => format!("{cmd:?}"),
+        }
+    }
+
+    /// The node and role the command acts on, if any.
+    pub fn subject(&self) -> (Option<&str>, Option<&str>) {
+        match self {
+            Self::NodeRoles { node, .. }
+            | Self::Bootstrap { node, .. }
+            | Self::CreateNode { node, .. }
+            | Self::UpdateNode { node, .. }
+            | Self::SetAdminTier { node, .. }
+            | Self::SetNodeTenant { node, .. }
+            | Self::RotateNodeKey { node, .. }
+            | Self::NodeKeyHistory { node }
+            | Self::DeleteNode { node }
+            | Self::AddGroupMember { node, .. }
+            | Self::RemoveGroupMember { node, .. }
+            | Self::NodeLabels { node }
+            | Self::SetNodeLabel { node, .. }
+            | Self::RemoveNodeLabel { node, .. } => (Some(node), None),
+            Self::GrantRole { node, role, .. }
+            | Self::RevokeRole { node, role, .. }
+            | Self::DenyRole { node, role }
+            | Self::RemoveRoleDeny { node, role } => (Some(node), Some(role)),
+            Self::ShowRole { role }
+            | Self::CreateRole { role, .. }
+            | Self::DescribeRole { role, .. }
+            | Self::RenameRole { role, .. }
+            | Self::DeleteRole { role, .. }
+            | Self::RoleNodes { role, .. }
+            | Self::RoleParents { role }
+            | Self::AddRoleParent { role, .. }
+            | Self::RemoveRoleParent { role, .. }
+            | Self::RequestRole { role, .. }
+            | Self::GrantGroupRole { role, .. }
+            | Self::RevokeGroupRole { role, .. }
+            | Self::GrantSelectorRole { role, .. }
+            | Self::RevokeSelectorRole { role, .. } => (None, Some(role)),
+            Self::WhoAmI
+            | Self::Roles { .. }
+            | Self::Nodes { .. }
+            | Self::Check { .. }
+            | Self::Groups { .. }
+            | Self::CreateGroup { .. }
+            | Self::DeleteGroup { .. }
+            | Self::GroupMembers { .. }
+            | Self::GroupRoles { .. }
+            | Self::SelectorGrants
+            | Self::Tenants
+            | Self::CreateTenant { .. }
+            | Self::DeleteTenant { .. }
+            | Self::CreateInvite { .. }
+            | Self::Invites
+            | Self::DeleteInvite { .. }
+            | Self::Redeem { .. }
+            | Self::ListRequests { .. }
+            | Self::Approve { .. }
+            | Self::Deny { .. }
+            | Self::PendingOps
+            | Self::Confirm { .. }
+            | Self::CancelOp { .. }
+            | Self::AuditLog(_)
+            | Self::Export
+            | Self::Import { .. } => (None, None),
+        }
+    }
+
+    /// Is the command covered by a two-person rule entry? Entries are command
+    /// names, plus `create-superadmin` for creating or promoting a superadmin,
+    /// including by importing a snapshot with superadmins. An import in replace
+    /// mode can stand in for any other command, so it matches every entry.
+    /// Rotating a superadmin's key needs the database, see
+    /// [`crate::Arbiter::requires_confirmation`].
+    pub fn matches_rule(&self, rule: &str) -> bool {
+        if let Self::Import {
+            mode: ImportMode::Replace,
+            ..
+        } = self
+        {
+            return true;
+        }
+
+        match rule {
+            "create-superadmin" => match self {
+                Self::CreateNode { superadmin, .. } => *superadmin,
+                Self::UpdateNode { superadmin, .. } => *superadmin == Some(true),
+                Self::Import { snapshot, .. } => snapshot.nodes.iter().any(|n| n.superadmin),
+                _ => false,
+            },
+            rule => rule == self.name(),
+        }
+    }
+
+    /// Is `rule` a valid two-person rule entry: a command name (see
+    /// [`Cmd::name`]) or `create-superadmin`?
+    pub fn is_rule(rule: &str) -> bool {
+        // Export and import aren't remote subcommands in the CLI
+        matches!(rule, "create-superadmin" | "export" | "import")
+            || Self::augment_subcommands(clap::Command::new("gk"))
+                .find_subcommand(rule)
+                .is_some()
+    }
+}
+
+#[derive(Args, Clone, Debug, Decode, Encode)]
+pub struct AuditFilter {
+    /// Only show entries acting on this node
+    #[arg(long)]
+    pub node: Option<String>,
+    /// Only show entries acting on this role
+    #[arg(long)]
+    pub role: Option<String>,
+    /// Only show entries made by this caller
+    #[arg(long)]
+    pub caller: Option<String>,
+    /// Only show entries at or after this time (RFC 3339 or YYYY-MM-DD)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub since: Option<i64>,
+    /// Only show entries before this time (RFC 3339 or YYYY-MM-DD)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub until: Option<i64>,
+    /// Maximum number of entries to return
+    #[arg(long, default_value_t = 50)]
+    pub limit: u32,
+    /// Number of entries to skip
+    #[arg(long, default_value_t = 0)]
+    pub offset: u32,
+}
+
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub caller: String,
+    pub cmd: String,
+    pub args: String,
+    pub node: Option<String>,
+    pub role: Option<String>,
+    pub outcome: String,
+    /// Creation time as a unix timestamp
+    pub created: i64,
+}
+
+impl From<db::AuditLog> for AuditEntry {
+    fn from(value: db::AuditLog) -> Self {
+        Self {
+            id: value.id,
+            caller: value.caller,
+            cmd: value.cmd,
+            args: value.args,
+            node: value.node,
+            role: value.role,
+            outcome: value.outcome,
+            created: value.created.and_utc().timestamp(),
+        }
+    }
+}
+
+/// A node's move from one key to another.
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct KeyRotation {
+    pub old_node: String,
+    pub new_node: String,
+    /// Node that requested the rotation
+    pub rotated_by: String,
+    /// Rotation time as a unix timestamp
+    pub created: i64,
+}
+
+impl From<db::KeyRotation> for KeyRotation {
+    fn from(value: db::KeyRotation) -> Self {
+        Self {
+            old_node: value.old_node,
+            new_node: value.new_node,
+            rotated_by: value.rotated_by,
+            created: value.created.and_utc().timestamp(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct RoleCheck {
+    pub node: String,
+    pub role: String,
+    /// Resource the role must be held on, or `None` to require the role on
+    /// every resource
+    pub resource: Option<String>,
+}
+
+impl FromStr for RoleCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid check {s:?}, expected <node>:<role>[@<resource>]");
+        let (node, role) = s.split_once(':').ok_or_else(err)?;
+        let (role, resource) = match role.split_once('@') {
+            None => (role, None),
+            Some((_, "")) => return Err(err()),
+            Some((role, resource)) => (role, Some(resource.to_string())),
+        };
+
+        if node.is_empty() || role.is_empty() {
+            return Err(err());
+        }
+
+        Ok(Self {
+            node: node.to_string(),
+            role: role.to_string(),
+            resource,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+pub struct Grant {
+    pub role: String,
+    /// Expiry as a unix timestamp, or `None` for a permanent grant
+    pub expires_at: Option<i64>,
+    /// Is the role explicitly denied to the node, overriding any grant? Denies
+    /// only appear in direct listings, and are never effective.
+    pub deny: bool,
+    /// Resource pattern the grant is scoped to, or `None` for every resource
+    pub resource: Option<String>,
+}
+
+impl From<db::Grant> for Grant {
+    fn from(value: db::Grant) -> Self {
+        Self {
+            role: value.role,
+            expires_at: value.expires_at.map(|t| t.and_utc().timestamp()),
+            deny: value.deny,
+            resource: Some(value.resource).filter(|r| !r.is_empty()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+pub struct Role {
+    pub role: String,
+    pub description: Option<String>,
+    /// Creation time as a unix timestamp
+    pub created: i64,
+}
+
+impl From<db::Role> for Role {
+    fn from(value: db::Role) -> Self {
+        Self {
+            role: value.role,
+            description: value.description,
+            created: value.created.and_utc().timestamp(),
+        }
+    }
+}
+
+/// Administrative access short of superadmin, ordered from least to most privileged.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Decode,
+    Deserialize,
+    Encode,
+    Eq,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+    ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AdminTier {
+    /// Only checking whether nodes hold roles, e.g. for services gated remotely
+    Checker,
+    /// Read-only access to listings, checks, the audit log and exports
+    Auditor,
+    /// Auditor access, plus managing roles, grants and nodes without administrative access
+    Operator,
+}
+
+impl AdminTier {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Checker => "checker",
+            Self::Auditor => "auditor",
+            Self::Operator => "operator",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        <Self as ValueEnum>::from_str(s, false).ok()
+    }
 }
 
 #[derive(Clone, Debug, Decode, Encode)]
@@ -60,6 +887,9 @@ pub struct Node {
     pub name: String,
     pub node: String,
     pub superadmin: bool,
+    pub admin_tier: Option<AdminTier>,
+    pub description: Option<String>,
+    pub tenant: Option<String>,
 }
 
 impl From<db::Node> for Node {
@@ -68,6 +898,407 @@ impl From<db::Node> for Node {
             name: value.name,
             node: value.node,
             superadmin: value.superadmin,
+            admin_tier: value.admin_tier.as_deref().and_then(AdminTier::parse),
+            description: value.description,
+            tenant: value.tenant,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct Invite {
+    pub id: i64,
+    /// Only returned when the invite is created, see [`Invite::ticket`]
+    pub secret: Option<String>,
+    /// Roles granted to nodes redeeming the invite
+    pub roles: Vec<String>,
+    /// Expiry as a unix timestamp
+    pub expires_at: i64,
+    pub uses_left: u32,
+    pub created_by: String,
+    /// Creation time as a unix timestamp
+    pub created: i64,
+}
+
+impl Invite {
+    pub(crate) fn from_db(value: db::Invite, roles: Vec<String>) -> Self {
+        Self {
+            id: value.id,
+            secret: None,
+            roles,
+            expires_at: value.expires_at.and_utc().timestamp(),
+            uses_left: value.uses_left.try_into().unwrap_or(0),
+            created_by: value.created_by,
+            created: value.created.and_utc().timestamp(),
+        }
+    }
+
+    /// Ticket for redeeming the invite with the given server, if the secret is known.
+    pub fn ticket(&self, server: NodeId) -> Option<InviteTicket> {
+        let secret = self.secret.clone()?;
+        Some(InviteTicket { server, secret })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq)]
+pub enum RequestStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+impl RequestStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Denied => "denied",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(Self::Pending),
+            "approved" => Some(Self::Approved),
+            "denied" => Some(Self::Denied),
+            _ => None,
+        }
+    }
+}
+
+/// A node's request for a role, and its review.
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct AccessRequest {
+    pub id: i64,
+    /// Requesting node public key
+    pub node: String,
+    pub role: String,
+    pub justification: String,
+    /// Requested grant lifetime in seconds, or `None` for a permanent grant
+    pub ttl: Option<u64>,
+    pub status: RequestStatus,
+    pub reviewed_by: Option<String>,
+    pub note: Option<String>,
+    /// Creation time as a unix timestamp
+    pub created: i64,
+    /// Review time as a unix timestamp
+    pub reviewed: Option<i64>,
+}
+
+impl From<db::AccessRequest> for AccessRequest {
+    fn from(value: db::AccessRequest) -> Self {
+        Self {
+            id: value.id,
+            node: value.node,
+            role: value.role,
+            justification: value.justification,
+            ttl: value.ttl.and_then(|t| t.try_into().ok()),
+            status: RequestStatus::parse(&value.status).unwrap_or(RequestStatus::Pending),
+            reviewed_by: value.reviewed_by,
+            note: value.note,
+            created: value.created.and_utc().timestamp(),
+            reviewed: value.reviewed.map(|t| t.and_utc().timestamp()),
+        }
+    }
+}
+
+/// A command held by the two-person rule until a second superadmin confirms it.
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct PendingOp {
+    pub id: i64,
+    pub cmd: String,
+    /// Arguments as recorded in the audit log
+    pub args: String,
+    pub requested_by: String,
+    /// Expiry as a unix timestamp
+    pub expires_at: i64,
+    /// Creation time as a unix timestamp
+    pub created: i64,
+}
+
+impl From<db::PendingOp> for PendingOp {
+    fn from(value: db::PendingOp) -> Self {
+        Self {
+            id: value.id,
+            cmd: value.cmd_name,
+            args: value.args,
+            requested_by: value.requested_by,
+            expires_at: value.expires_at.and_utc().timestamp(),
+            created: value.created.and_utc().timestamp(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+pub struct Group {
+    pub name: String,
+    pub description: Option<String>,
+    /// Creation time as a unix timestamp
+    pub created: i64,
+}
+
+impl From<db::Group> for Group {
+    fn from(value: db::Group) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            created: value.created.and_utc().timestamp(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+pub struct Tenant {
+    pub name: String,
+    pub description: Option<String>,
+    /// Creation time as a unix timestamp
+    pub created: i64,
+}
+
+impl From<db::Tenant> for Tenant {
+    fn from(value: db::Tenant) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            created: value.created.and_utc().timestamp(),
+        }
+    }
+}
+
+/// A key/value label attached to a node, written `<key>=<value>`.
+#[derive(Clone, Debug, Decode, Encode, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Label {
+    pub key: String,
+    pub value: String,
+}
+
+impl Label {
+    /// Neither the key nor the value may be empty, or contain the `=` and `,`
+    /// separators used by selectors.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let invalid = |s: &str| s.is_empty() || s.contains(['=', ',']);
+        if invalid(&self.key) || invalid(&self.value) {
+            return Err(format!("invalid label {self:?}, expected <key>=<value>"));
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Label {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid label {s:?}, expected <key>=<value>"))?;
+
+        let label = Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        label.validate()?;
+        Ok(label)
+    }
+}
+
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+impl From<db::NodeLabel> for Label {
+    fn from(value: db::NodeLabel) -> Self {
+        Self {
+            key: value.key,
+            value: value.value,
+        }
+    }
+}
+
+/// Labels a node must all carry to match, written `<key>=<value>[,<key>=<value>...]`.
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+pub struct LabelSelector {
+    pub labels: Vec<Label>,
+}
+
+impl LabelSelector {
+    /// The selector in canonical form, its labels sorted by key. Selectors must
+    /// have at least one label, and name each key at most once.
+    pub(crate) fn normalize(&self) -> Result<Self, String> {
+        let mut labels = self.labels.clone();
+        labels.sort();
+
+        if labels.is_empty() {
+            return Err("empty label selector".to_string());
+        }
+
+        for label in labels.iter() {
+            label.validate()?;
+        }
+
+        if let Some(pair) = labels.windows(2).find(|p| p[0].key == p[1].key) {
+            return Err(format!("label selector names {:?} twice", pair[0].key));
+        }
+
+        Ok(Self { labels })
+    }
+}
+
+impl FromStr for LabelSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let labels = s
+            .split(',')
+            .map(Label::from_str)
+            .collect::<Result<_, _>>()?;
+
+        Self { labels }.normalize()
+    }
+}
+
+impl std::fmt::Display for LabelSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, label) in self.labels.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{label}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A role granted to every node matching a label selector.
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+pub struct SelectorGrant {
+    /// Selector in canonical form, see [`LabelSelector`]
+    pub selector: String,
+    pub role: String,
+    /// Creation time as a unix timestamp
+    pub created: i64,
+}
+
+/// A node's view of its own access, as returned by [`Cmd::WhoAmI`].
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct WhoAmI {
+    pub node: Node,
+    /// Roles granted to the node directly
+    pub roles: Vec<Grant>,
+    /// Roles held directly, through groups, through selector grants or through
+    /// inheritance
+    pub effective_roles: Vec<Grant>,
+    /// Groups the node is a member of
+    pub groups: Vec<String>,
+    /// Labels attached to the node
+    pub labels: Vec<Label>,
+}
+
+/// Longest TTL, in seconds, accepted for grants, invites, requests and confirmations.
+pub const MAX_TTL: u64 = 100 * 365 * 24 * 60 * 60;
+
+/// Parse a duration such as `90s`, `30m`, `8h`, `7d` or `1h30m` into seconds.
+pub fn parse_ttl(s: &str) -> Result<u64, String> {
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return Err(format!("invalid duration unit {c:?} in {s:?}")),
+        };
+
+        let n: u64 = digits
+            .parse()
+            .map_err(|_| format!("invalid duration {s:?}"))?;
+
+        total = n
+            .checked_mul(unit)
+            .and_then(|n| total.checked_add(n))
+            .ok_or_else(|| format!("duration {s:?} is too long"))?;
+
+        digits.clear();
+    }
+
+    if !digits.is_empty() || total == 0 {
+        return Err(format!(
+            "invalid duration {s:?}, expected e.g. 30m, 8h or 7d"
+        ));
+    }
+
+    if total > MAX_TTL {
+        return Err(format!("duration {s:?} is too long"));
+    }
+
+    Ok(total)
+}
+
+/// Parse an RFC 3339 timestamp, or a `YYYY-MM-DD` date (midnight UTC), into a unix timestamp.
+pub fn parse_timestamp(s: &str) -> Result<i64, String> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.timestamp());
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|t| t.and_utc().timestamp())
+        .ok_or_else(|| format!("invalid timestamp {s:?}, expected RFC 3339 or YYYY-MM-DD"))
+}
+
+/// Does a role or group belong to the tenant? Tenants own the roles and groups
+/// named after them, e.g. `infra/deploy` and `infra/builders` for `infra`. The
+/// name is the only record of ownership, as roles and groups have no tenant.
+pub fn tenant_owns(tenant: &str, name: &str) -> bool {
+    name.strip_prefix(tenant)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .is_some_and(|rest| !rest.is_empty())
+}
+
+/// Does a grant scoped to `pattern` cover `resource`? Both are `/`-separated
+/// paths. A pattern covers everything beneath it, so `bucket/a` covers
+/// `bucket/a/logs`. Within a segment `*` matches any run of characters, and a
+/// `**` segment matches any number of segments.
+pub(crate) fn resource_matches(pattern: &str, resource: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let resource: Vec<&str> = resource.split('/').collect();
+    segments_match(&pattern, &resource)
+}
+
+fn segments_match(pattern: &[&str], resource: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => true,
+        Some((&"**", rest)) => (0..=resource.len()).any(|i| segments_match(rest, &resource[i..])),
+        Some((segment, rest)) => match resource.split_first() {
+            None => false,
+            Some((first, tail)) => glob_match(segment, first) && segments_match(rest, tail),
+        },
+    }
+}
+
+fn glob_match(pattern: &str, s: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == s,
+        Some((prefix, rest)) => {
+            let Some(s) = s.strip_prefix(prefix) else {
+                return false;
+            };
+
+            s.char_indices()
+                .map(|(i, _)| i)
+                .chain([s.len()])
+                .any(|i| glob_match(rest, &s[i..]))
         }
     }
 }
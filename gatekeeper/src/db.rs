@@ -1,7 +1,36 @@
+mod access_request;
+mod audit_log;
+mod group;
+mod invite;
+mod key_rotation;
 mod node;
+mod node_label;
 mod node_role;
+mod node_role_deny;
+mod pending_op;
 mod role;
+mod role_parent;
+mod selector_role;
+mod tenant;
 
-pub use node::Node;
+use sqlx::prelude::FromRow;
+
+pub use access_request::AccessRequest;
+pub use audit_log::{AuditLog, AuditLogFilter, NewAuditLog};
+pub use group::Group;
+pub use invite::Invite;
+pub use key_rotation::KeyRotation;
+pub use node::{Grant, Node};
+pub use node_label::NodeLabel;
 pub use node_role::NodeRole;
+pub use node_role_deny::NodeRoleDeny;
+pub use pending_op::PendingOp;
 pub use role::Role;
+pub use role_parent::RoleParent;
+pub use selector_role::SelectorRole;
+pub use tenant::Tenant;
+
+#[derive(FromRow)]
+struct Count {
+    count: i64,
+}
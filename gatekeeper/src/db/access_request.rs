@@ -0,0 +1,111 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
+
+/// A request for a role, with the requesting node's key resolved.
+#[allow(dead_code)]
+#[derive(Debug, FromRow)]
+pub struct AccessRequest {
+    pub id: i64,
+    pub node_id: i64,
+    pub node: String,
+    pub role: String,
+    pub justification: String,
+    pub ttl: Option<i64>,
+    pub status: String,
+    pub reviewed_by: Option<String>,
+    pub note: Option<String>,
+    pub created: NaiveDateTime,
+    pub reviewed: Option<NaiveDateTime>,
+}
+
+const SELECT: &str = r#"
+    SELECT pr.id, pr.node_id, n.node, pr.role, pr.justification, pr.ttl, pr.status,
+        pr.reviewed_by, pr.note, pr.created, pr.reviewed
+    FROM pending_requests pr
+    JOIN nodes n ON pr.node_id = n.id
+"#;
+
+impl AccessRequest {
+    /// Requests, oldest first, optionally only those with the given status.
+    pub async fn search<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        status: Option<&str>,
+    ) -> Result<Vec<AccessRequest>, sqlx::Error> {
+        query_as::<_, AccessRequest>(&format!(
+            "{SELECT} WHERE ($1 IS NULL OR pr.status = $1) ORDER BY pr.id"
+        ))
+        .bind(status)
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn find<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<Option<AccessRequest>, sqlx::Error> {
+        query_as::<_, AccessRequest>(&format!("{SELECT} WHERE pr.id = $1"))
+            .bind(id)
+            .fetch_optional(conn)
+            .await
+    }
+
+    pub async fn find_pending<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+        role: &str,
+    ) -> Result<Option<AccessRequest>, sqlx::Error> {
+        query_as::<_, AccessRequest>(&format!(
+            "{SELECT} WHERE pr.node_id = $1 AND pr.role = $2 AND pr.status = 'pending'"
+        ))
+        .bind(node_id)
+        .bind(role)
+        .fetch_optional(conn)
+        .await
+    }
+
+    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+        role: &str,
+        justification: &str,
+        ttl: Option<i64>,
+    ) -> Result<i64, sqlx::Error> {
+        query(
+            r#"
+                INSERT INTO pending_requests (node_id, role, justification, ttl, status, created)
+                VALUES ($1, $2, $3, $4, 'pending', datetime('now'))
+            "#,
+        )
+        .bind(node_id)
+        .bind(role)
+        .bind(justification)
+        .bind(ttl)
+        .execute(conn)
+        .await
+        .map(|r| r.last_insert_rowid())
+    }
+
+    /// Close a pending request, returning false if it was no longer pending.
+    pub async fn review<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        status: &str,
+        reviewed_by: &str,
+        note: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+                UPDATE pending_requests
+                SET status = $2, reviewed_by = $3, note = $4, reviewed = datetime('now')
+                WHERE id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(reviewed_by)
+        .bind(note)
+        .execute(conn)
+        .await
+        .map(|r| r.rows_affected() == 1)
+    }
+}
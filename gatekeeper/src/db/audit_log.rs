@@ -0,0 +1,85 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query_as};
+
+#[allow(dead_code)]
+#[derive(Debug, FromRow)]
+pub struct AuditLog {
+    pub id: i64,
+    pub caller: String,
+    pub cmd: String,
+    pub args: String,
+    pub node: Option<String>,
+    pub role: Option<String>,
+    pub outcome: String,
+    pub created: NaiveDateTime,
+}
+
+pub struct NewAuditLog<'a> {
+    pub caller: &'a str,
+    pub cmd: &'a str,
+    pub args: &'a str,
+    pub node: Option<&'a str>,
+    pub role: Option<&'a str>,
+    pub outcome: &'a str,
+}
+
+pub struct AuditLogFilter<'a> {
+    pub node: Option<&'a str>,
+    pub role: Option<&'a str>,
+    pub caller: Option<&'a str>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl AuditLog {
+    /// Matching entries, most recent first.
+    pub async fn search<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        filter: &AuditLogFilter<'_>,
+    ) -> Result<Vec<AuditLog>, sqlx::Error> {
+        query_as::<_, AuditLog>(
+            r#"
+                SELECT * FROM audit_log
+                WHERE ($1 IS NULL OR node = $1)
+                AND ($2 IS NULL OR role = $2)
+                AND ($3 IS NULL OR caller = $3)
+                AND ($4 IS NULL OR created >= $4)
+                AND ($5 IS NULL OR created < $5)
+                ORDER BY id DESC
+                LIMIT $6 OFFSET $7
+            "#,
+        )
+        .bind(filter.node)
+        .bind(filter.role)
+        .bind(filter.caller)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.limit)
+        .bind(filter.offset)
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        entry: &NewAuditLog<'_>,
+    ) -> Result<AuditLog, sqlx::Error> {
+        query_as::<_, AuditLog>(
+            r#"
+                INSERT INTO audit_log (caller, cmd, args, node, role, outcome, created)
+                VALUES ($1, $2, $3, $4, $5, $6, datetime('now'))
+                RETURNING *
+            "#,
+        )
+        .bind(entry.caller)
+        .bind(entry.cmd)
+        .bind(entry.args)
+        .bind(entry.node)
+        .bind(entry.role)
+        .bind(entry.outcome)
+        .fetch_one(conn)
+        .await
+    }
+}
@@ -0,0 +1,238 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
+
+use super::{Node, Role};
+
+#[derive(Debug, FromRow)]
+pub struct Group {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub created: NaiveDateTime,
+}
+
+/// A group membership with its group and node resolved.
+#[derive(Debug, FromRow)]
+pub struct NamedGroupMember {
+    pub group_name: String,
+    pub node: String,
+}
+
+/// A group grant with its group and role resolved.
+#[derive(Debug, FromRow)]
+pub struct NamedGroupRole {
+    pub group_name: String,
+    pub role: String,
+}
+
+impl Group {
+    pub async fn all<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<Group>, sqlx::Error> {
+        query_as::<_, Group>("SELECT * FROM groups ORDER BY name")
+            .fetch_all(conn)
+            .await
+    }
+
+    pub async fn find<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        name: &str,
+    ) -> Result<Option<Group>, sqlx::Error> {
+        query_as::<_, Group>("SELECT * FROM groups WHERE name = $1")
+            .bind(name)
+            .fetch_optional(conn)
+            .await
+    }
+
+    /// Groups the node is a member of.
+    pub async fn for_node<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+    ) -> Result<Vec<Group>, sqlx::Error> {
+        query_as::<_, Group>(
+            r#"
+                SELECT g.* FROM groups g
+                JOIN group_members gm ON gm.group_id = g.id
+                WHERE gm.node_id = $1
+                ORDER BY g.name
+            "#,
+        )
+        .bind(node_id)
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<Group, sqlx::Error> {
+        query_as::<_, Group>(
+            "INSERT INTO groups (name, description, created) VALUES ($1, $2, datetime('now')) RETURNING *",
+        )
+        .bind(name)
+        .bind(description)
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Insert a group with a known creation time, or update the description of
+    /// an existing group with the same name.
+    pub async fn restore<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        name: &str,
+        description: Option<&str>,
+        created: NaiveDateTime,
+    ) -> Result<Group, sqlx::Error> {
+        query_as::<_, Group>(
+            r#"
+                INSERT INTO groups (name, description, created) VALUES ($1, $2, $3)
+                ON CONFLICT (name) DO UPDATE SET description = excluded.description
+                RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(description)
+        .bind(created)
+        .fetch_one(conn)
+        .await
+    }
+
+    pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM groups WHERE id = $1")
+            .bind(id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    pub async fn delete_all<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM groups")
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    pub async fn members<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<Vec<Node>, sqlx::Error> {
+        query_as::<_, Node>(
+            r#"
+                SELECT n.* FROM nodes n
+                JOIN group_members gm ON gm.node_id = n.id
+                WHERE gm.group_id = $1
+                ORDER BY n.node
+            "#,
+        )
+        .bind(id)
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn add_member<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        node_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("INSERT OR IGNORE INTO group_members (group_id, node_id) VALUES ($1, $2)")
+            .bind(id)
+            .bind(node_id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    pub async fn remove_member<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        node_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM group_members WHERE group_id = $1 AND node_id = $2")
+            .bind(id)
+            .bind(node_id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    pub async fn roles<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<Vec<Role>, sqlx::Error> {
+        query_as::<_, Role>(
+            r#"
+                SELECT r.* FROM roles r
+                JOIN group_roles gr ON gr.role_id = r.id
+                WHERE gr.group_id = $1
+                ORDER BY r.role
+            "#,
+        )
+        .bind(id)
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn add_role<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        role_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("INSERT OR IGNORE INTO group_roles (group_id, role_id) VALUES ($1, $2)")
+            .bind(id)
+            .bind(role_id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    pub async fn remove_role<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        role_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM group_roles WHERE group_id = $1 AND role_id = $2")
+            .bind(id)
+            .bind(role_id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    pub async fn all_members_named<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<NamedGroupMember>, sqlx::Error> {
+        query_as::<_, NamedGroupMember>(
+            r#"
+                SELECT g.name AS group_name, n.node AS node
+                FROM group_members gm
+                JOIN groups g ON gm.group_id = g.id
+                JOIN nodes n ON gm.node_id = n.id
+                ORDER BY g.name, n.node
+            "#,
+        )
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn all_roles_named<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<NamedGroupRole>, sqlx::Error> {
+        query_as::<_, NamedGroupRole>(
+            r#"
+                SELECT g.name AS group_name, r.role AS role
+                FROM group_roles gr
+                JOIN groups g ON gr.group_id = g.id
+                JOIN roles r ON gr.role_id = r.id
+                ORDER BY g.name, r.role
+            "#,
+        )
+        .fetch_all(conn)
+        .await
+    }
+}
@@ -0,0 +1,120 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
+
+#[allow(dead_code)]
+#[derive(Debug, FromRow)]
+pub struct Invite {
+    pub id: i64,
+    pub secret: String,
+    pub expires_at: NaiveDateTime,
+    pub uses_left: i64,
+    pub created_by: String,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Debug, FromRow)]
+pub struct InviteRole {
+    pub role_id: i64,
+    pub role: String,
+}
+
+impl Invite {
+    pub async fn all<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<Invite>, sqlx::Error> {
+        query_as::<_, Invite>("SELECT * FROM invites ORDER BY id")
+            .fetch_all(conn)
+            .await
+    }
+
+    /// An invite that hasn't expired or been used up.
+    pub async fn find_valid<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        secret: &str,
+    ) -> Result<Option<Invite>, sqlx::Error> {
+        query_as::<_, Invite>(
+            r#"
+                SELECT * FROM invites
+                WHERE secret = $1 AND uses_left > 0 AND expires_at > datetime('now')
+            "#,
+        )
+        .bind(secret)
+        .fetch_optional(conn)
+        .await
+    }
+
+    pub async fn roles<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<Vec<InviteRole>, sqlx::Error> {
+        query_as::<_, InviteRole>(
+            r#"
+                SELECT r.id AS role_id, r.role AS role FROM invite_roles ir
+                JOIN roles r ON ir.role_id = r.id
+                WHERE ir.invite_id = $1
+                ORDER BY r.role
+            "#,
+        )
+        .bind(id)
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        secret: &str,
+        expires_at: NaiveDateTime,
+        uses: i64,
+        created_by: &str,
+    ) -> Result<Invite, sqlx::Error> {
+        query_as::<_, Invite>(
+            r#"
+                INSERT INTO invites (secret, expires_at, uses_left, created_by, created)
+                VALUES ($1, $2, $3, $4, datetime('now'))
+                RETURNING *
+            "#,
+        )
+        .bind(secret)
+        .bind(expires_at)
+        .bind(uses)
+        .bind(created_by)
+        .fetch_one(conn)
+        .await
+    }
+
+    pub async fn add_role<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        role_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("INSERT OR IGNORE INTO invite_roles (invite_id, role_id) VALUES ($1, $2)")
+            .bind(id)
+            .bind(role_id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    /// Use up one redemption, returning false if none were left.
+    pub async fn redeem<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        query("UPDATE invites SET uses_left = uses_left - 1 WHERE id = $1 AND uses_left > 0")
+            .bind(id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected() == 1)
+    }
+
+    pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM invites WHERE id = $1")
+            .bind(id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+}
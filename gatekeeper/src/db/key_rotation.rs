@@ -0,0 +1,60 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query_as};
+
+/// A past key of a node and the key that replaced it.
+#[allow(dead_code)]
+#[derive(Debug, FromRow)]
+pub struct KeyRotation {
+    pub id: i64,
+    pub node_id: i64,
+    pub old_node: String,
+    pub new_node: String,
+    pub rotated_by: String,
+    pub created: NaiveDateTime,
+}
+
+impl KeyRotation {
+    pub async fn for_node<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+    ) -> Result<Vec<KeyRotation>, sqlx::Error> {
+        query_as::<_, KeyRotation>("SELECT * FROM key_rotations WHERE node_id = $1 ORDER BY id")
+            .bind(node_id)
+            .fetch_all(conn)
+            .await
+    }
+
+    /// The node that was known by `old_node` before it was rotated away.
+    pub async fn find_node_id<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        old_node: &str,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        query_as::<_, KeyRotation>("SELECT * FROM key_rotations WHERE old_node = $1 LIMIT 1")
+            .bind(old_node)
+            .fetch_optional(conn)
+            .await
+            .map(|r| r.map(|r| r.node_id))
+    }
+
+    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+        old_node: &str,
+        new_node: &str,
+        rotated_by: &str,
+    ) -> Result<KeyRotation, sqlx::Error> {
+        query_as::<_, KeyRotation>(
+            r#"
+                INSERT INTO key_rotations (node_id, old_node, new_node, rotated_by, created)
+                VALUES ($1, $2, $3, $4, datetime('now'))
+                RETURNING *
+            "#,
+        )
+        .bind(node_id)
+        .bind(old_node)
+        .bind(new_node)
+        .bind(rotated_by)
+        .fetch_one(conn)
+        .await
+    }
+}
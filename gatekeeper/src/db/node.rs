@@ -1,6 +1,8 @@
 use chrono::NaiveDateTime;
 use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
 
+use super::Count;
+
 #[allow(dead_code)]
 #[derive(Debug, FromRow)]
 pub struct Node {
@@ -9,16 +11,18 @@ pub struct Node {
     pub node: String,
     pub superadmin: bool,
     pub created: NaiveDateTime,
+    pub description: Option<String>,
+    pub admin_tier: Option<String>,
+    pub tenant: Option<String>,
 }
 
-#[derive(FromRow)]
-pub struct Count {
-    count: i64,
-}
-
-#[derive(FromRow)]
-pub struct Role {
-    role: String,
+#[derive(Debug, FromRow)]
+pub struct Grant {
+    pub role: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub deny: bool,
+    /// Resource the grant is scoped to, empty for every resource
+    pub resource: String,
 }
 
 impl Node {
@@ -30,6 +34,16 @@ impl Node {
             .await
     }
 
+    pub async fn in_tenant<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        tenant: &str,
+    ) -> Result<Vec<Node>, sqlx::Error> {
+        query_as::<_, Node>("SELECT * FROM nodes WHERE tenant = $1 ORDER BY node")
+            .bind(tenant)
+            .fetch_all(conn)
+            .await
+    }
+
     pub async fn any<'a, E: Executor<'a, Database = Sqlite>>(conn: E) -> Result<bool, sqlx::Error> {
         query_as::<_, Count>("SELECT COUNT(*) AS count FROM nodes")
             .fetch_one(conn)
@@ -47,27 +61,183 @@ impl Node {
             .await
     }
 
+    pub async fn find_by_name<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        name: &str,
+    ) -> Result<Option<Node>, sqlx::Error> {
+        query_as::<_, Node>("SELECT * FROM nodes WHERE name = $1")
+            .bind(name)
+            .fetch_optional(conn)
+            .await
+    }
+
+    /// Roles granted or denied to the node directly.
     pub async fn roles<'a, E: Executor<'a, Database = Sqlite>>(
         conn: E,
         node: &str,
-    ) -> Result<Vec<String>, sqlx::Error> {
-        let roles = query_as::<_, Role>(
+    ) -> Result<Vec<Grant>, sqlx::Error> {
+        query_as::<_, Grant>(
             r#"
-                SELECT r.role AS role FROM nodes n
+                SELECT
+                    r.role AS role, nr.expires_at AS expires_at, FALSE AS deny,
+                    nr.resource AS resource
+                FROM nodes n
                 JOIN node_roles nr ON nr.node_id = n.id
                 JOIN roles r ON nr.role_id = r.id
                 WHERE n.node = $1
-                ORDER BY r.role
+                AND (nr.expires_at IS NULL OR nr.expires_at > datetime('now'))
+                UNION ALL
+                SELECT r.role AS role, NULL AS expires_at, TRUE AS deny, '' AS resource FROM nodes n
+                JOIN node_role_denies d ON d.node_id = n.id
+                JOIN roles r ON d.role_id = r.id
+                WHERE n.node = $1
+                ORDER BY role, deny, resource
+            "#,
+        )
+        .bind(node)
+        .fetch_all(conn)
+        .await
+    }
+
+    /// Roles held directly, through groups, selector grants or parent roles,
+    /// less denied roles and, in a tenant, selector roles it doesn't own.
+    pub async fn effective_roles<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node: &str,
+    ) -> Result<Vec<Grant>, sqlx::Error> {
+        query_as::<_, Grant>(
+            r#"
+                WITH RECURSIVE effective(role_id, resource, expires_at) AS (
+                    SELECT nr.role_id, nr.resource, nr.expires_at FROM nodes n
+                    JOIN node_roles nr ON nr.node_id = n.id
+                    WHERE n.node = $1
+                    AND (nr.expires_at IS NULL OR nr.expires_at > datetime('now'))
+                    UNION
+                    SELECT gr.role_id, '', NULL FROM nodes n
+                    JOIN group_members gm ON gm.node_id = n.id
+                    JOIN group_roles gr ON gr.group_id = gm.group_id
+                    WHERE n.node = $1
+                    UNION
+                    SELECT sr.role_id, '', NULL FROM nodes n, selector_roles sr
+                    JOIN roles r ON r.id = sr.role_id
+                    WHERE n.node = $1
+                    AND (
+                        n.tenant IS NULL
+                        OR (substr(r.role, 1, length(n.tenant) + 1) = n.tenant || '/'
+                            AND length(r.role) > length(n.tenant) + 1)
+                    )
+                    AND NOT EXISTS (
+                        SELECT 1 FROM selector_role_terms t
+                        WHERE t.selector_role_id = sr.id
+                        AND NOT EXISTS (
+                            SELECT 1 FROM node_labels l
+                            WHERE l.node_id = n.id AND l.key = t.key AND l.value = t.value
+                        )
+                    )
+                    UNION
+                    SELECT rp.role_id, e.resource, e.expires_at FROM role_parents rp
+                    JOIN effective e ON rp.parent_id = e.role_id
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM node_role_denies d
+                        JOIN nodes n ON d.node_id = n.id
+                        WHERE n.node = $1 AND d.role_id = e.role_id
+                    )
+                )
+                SELECT
+                    r.role AS role,
+                    CASE WHEN COUNT(*) > COUNT(e.expires_at) THEN NULL ELSE MAX(e.expires_at) END AS expires_at,
+                    FALSE AS deny,
+                    e.resource AS resource
+                FROM effective e
+                JOIN roles r ON r.id = e.role_id
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM node_role_denies d
+                    JOIN nodes n ON d.node_id = n.id
+                    WHERE n.node = $1 AND d.role_id = e.role_id
+                )
+                GROUP BY r.id, e.resource
+                ORDER BY r.role, e.resource
             "#,
         )
         .bind(node)
         .fetch_all(conn)
-        .await?
-        .into_iter()
-        .map(|r| r.role)
-        .collect();
+        .await
+    }
 
-        Ok(roles)
+    /// Nodes holding a role, optionally including those that only hold it through
+    /// parent roles, groups or selector grants, and optionally only those in a
+    /// tenant. Only grants covering every resource count, and nodes denied the
+    /// role, or every role they would inherit it through, are left out.
+    pub async fn with_role<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        role: &str,
+        indirect: bool,
+        tenant: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Node>, sqlx::Error> {
+        query_as::<_, Node>(
+            r#"
+                WITH RECURSIVE granting(node_id, role_id) AS (
+                    SELECT n.id, r.id FROM nodes n
+                    JOIN roles r ON r.role = $1
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM node_role_denies d
+                        WHERE d.node_id = n.id AND d.role_id = r.id
+                    )
+                    UNION
+                    SELECT g.node_id, rp.parent_id FROM role_parents rp
+                    JOIN granting g ON rp.role_id = g.role_id
+                    WHERE $2 AND NOT EXISTS (
+                        SELECT 1 FROM node_role_denies d
+                        WHERE d.node_id = g.node_id AND d.role_id = rp.parent_id
+                    )
+                )
+                SELECT n.* FROM nodes n
+                WHERE (
+                    EXISTS (
+                        SELECT 1 FROM node_roles nr
+                        JOIN granting g ON nr.role_id = g.role_id AND g.node_id = n.id
+                        WHERE nr.node_id = n.id AND nr.resource = ''
+                        AND (nr.expires_at IS NULL OR nr.expires_at > datetime('now'))
+                    )
+                    OR ($2 AND EXISTS (
+                        SELECT 1 FROM group_members gm
+                        JOIN group_roles gr ON gr.group_id = gm.group_id
+                        JOIN granting g ON gr.role_id = g.role_id AND g.node_id = n.id
+                        WHERE gm.node_id = n.id
+                    ))
+                    OR ($2 AND EXISTS (
+                        SELECT 1 FROM selector_roles sr
+                        JOIN granting g ON sr.role_id = g.role_id AND g.node_id = n.id
+                        JOIN roles r ON r.id = sr.role_id
+                        WHERE (
+                            n.tenant IS NULL
+                            OR (substr(r.role, 1, length(n.tenant) + 1) = n.tenant || '/'
+                                AND length(r.role) > length(n.tenant) + 1)
+                        )
+                        AND NOT EXISTS (
+                            SELECT 1 FROM selector_role_terms t
+                            WHERE t.selector_role_id = sr.id
+                            AND NOT EXISTS (
+                                SELECT 1 FROM node_labels l
+                                WHERE l.node_id = n.id AND l.key = t.key AND l.value = t.value
+                            )
+                        )
+                    ))
+                )
+                AND ($5 IS NULL OR n.tenant = $5)
+                ORDER BY n.node
+                LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(role)
+        .bind(indirect)
+        .bind(limit)
+        .bind(offset)
+        .bind(tenant)
+        .fetch_all(conn)
+        .await
     }
 
     pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
@@ -75,17 +245,125 @@ impl Node {
         name: &str,
         node: &str,
         superadmin: bool,
+        tenant: Option<&str>,
+    ) -> Result<Node, sqlx::Error> {
+        query_as::<_, Node>(
+            "INSERT INTO nodes (name, node, superadmin, tenant, created) VALUES ($1, $2, $3, $4, datetime('now')) RETURNING *",
+        )
+        .bind(name)
+        .bind(node)
+        .bind(superadmin)
+        .bind(tenant)
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Update the name, superadmin flag and description of a node, leaving
+    /// fields that are `None` unchanged.
+    pub async fn update<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        name: Option<&str>,
+        superadmin: Option<bool>,
+        description: Option<Option<&str>>,
+    ) -> Result<Node, sqlx::Error> {
+        query_as::<_, Node>(
+            r#"
+                UPDATE nodes SET
+                    name = COALESCE($2, name),
+                    superadmin = COALESCE($3, superadmin),
+                    description = CASE WHEN $4 THEN $5 ELSE description END
+                WHERE id = $1
+                RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(name)
+        .bind(superadmin)
+        .bind(description.is_some())
+        .bind(description.flatten())
+        .fetch_one(conn)
+        .await
+    }
+
+    pub async fn set_admin_tier<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        admin_tier: Option<&str>,
+    ) -> Result<Node, sqlx::Error> {
+        query_as::<_, Node>("UPDATE nodes SET admin_tier = $2 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(admin_tier)
+            .fetch_one(conn)
+            .await
+    }
+
+    pub async fn set_tenant<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        tenant: Option<&str>,
+    ) -> Result<Node, sqlx::Error> {
+        query_as::<_, Node>("UPDATE nodes SET tenant = $2 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(tenant)
+            .fetch_one(conn)
+            .await
+    }
+
+    pub async fn set_key<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        node: &str,
+    ) -> Result<Node, sqlx::Error> {
+        query_as::<_, Node>("UPDATE nodes SET node = $2 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(node)
+            .fetch_one(conn)
+            .await
+    }
+
+    /// Insert a node with a known creation time, or update the name, superadmin
+    /// flag, admin tier and description of an existing node with the same key.
+    pub async fn restore<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        name: &str,
+        node: &str,
+        superadmin: bool,
+        admin_tier: Option<&str>,
+        description: Option<&str>,
+        created: NaiveDateTime,
     ) -> Result<Node, sqlx::Error> {
         query_as::<_, Node>(
-            "INSERT INTO nodes (name, node, superadmin, created) VALUES ($1, $2, $3, datetime('now')) RETURNING *",
+            r#"
+                INSERT INTO nodes (name, node, superadmin, admin_tier, description, created)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (node) DO UPDATE SET
+                    name = excluded.name,
+                    superadmin = excluded.superadmin,
+                    admin_tier = excluded.admin_tier,
+                    description = excluded.description
+                RETURNING *
+            "#,
         )
         .bind(name)
         .bind(node)
         .bind(superadmin)
+        .bind(admin_tier)
+        .bind(description)
+        .bind(created)
         .fetch_one(conn)
         .await
     }
 
+    pub async fn delete_all<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM nodes")
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
     pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
         conn: E,
         id: i64,
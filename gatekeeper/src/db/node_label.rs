@@ -0,0 +1,108 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
+
+#[allow(dead_code)]
+#[derive(Debug, FromRow)]
+pub struct NodeLabel {
+    pub id: i64,
+    pub node_id: i64,
+    pub key: String,
+    pub value: String,
+    pub created: NaiveDateTime,
+}
+
+/// A label with its node resolved.
+#[derive(Debug, FromRow)]
+pub struct NamedNodeLabel {
+    pub node: String,
+    pub key: String,
+    pub value: String,
+    pub created: NaiveDateTime,
+}
+
+impl NodeLabel {
+    pub async fn for_node<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+    ) -> Result<Vec<NodeLabel>, sqlx::Error> {
+        query_as::<_, NodeLabel>("SELECT * FROM node_labels WHERE node_id = $1 ORDER BY key")
+            .bind(node_id)
+            .fetch_all(conn)
+            .await
+    }
+
+    /// Set a label, replacing the node's existing value for the key.
+    pub async fn set<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+        key: &str,
+        value: &str,
+    ) -> Result<NodeLabel, sqlx::Error> {
+        query_as::<_, NodeLabel>(
+            r#"
+                INSERT INTO node_labels (node_id, key, value, created)
+                VALUES ($1, $2, $3, datetime('now'))
+                ON CONFLICT (node_id, key) DO UPDATE
+                SET value = excluded.value, created = excluded.created
+                RETURNING *
+            "#,
+        )
+        .bind(node_id)
+        .bind(key)
+        .bind(value)
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Insert a label with a known creation time, or update an existing one.
+    pub async fn restore<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+        key: &str,
+        value: &str,
+        created: NaiveDateTime,
+    ) -> Result<NodeLabel, sqlx::Error> {
+        query_as::<_, NodeLabel>(
+            r#"
+                INSERT INTO node_labels (node_id, key, value, created) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (node_id, key) DO UPDATE
+                SET value = excluded.value, created = excluded.created
+                RETURNING *
+            "#,
+        )
+        .bind(node_id)
+        .bind(key)
+        .bind(value)
+        .bind(created)
+        .fetch_one(conn)
+        .await
+    }
+
+    pub async fn all_named<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<NamedNodeLabel>, sqlx::Error> {
+        query_as::<_, NamedNodeLabel>(
+            r#"
+                SELECT n.node AS node, l.key AS key, l.value AS value, l.created AS created
+                FROM node_labels l
+                JOIN nodes n ON l.node_id = n.id
+                ORDER BY n.node, l.key
+            "#,
+        )
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+        key: &str,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM node_labels WHERE node_id = $1 AND key = $2")
+            .bind(node_id)
+            .bind(key)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+}
@@ -1,4 +1,5 @@
-use sqlx::{Executor, Sqlite, SqlitePool, prelude::FromRow, query, query_as};
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
 
 #[allow(dead_code)]
 #[derive(Debug, FromRow)]
@@ -6,43 +7,104 @@ pub struct NodeRole {
     pub id: i64,
     pub node_id: i64,
     pub role_id: i64,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created: NaiveDateTime,
+    /// Resource the grant is scoped to, empty for every resource
+    pub resource: String,
+}
+
+/// A grant with its node and role resolved.
+#[derive(Debug, FromRow)]
+pub struct NamedNodeRole {
+    pub node: String,
+    pub role: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created: NaiveDateTime,
+    pub resource: String,
 }
 
 impl NodeRole {
-    pub async fn ensure(
-        pool: &SqlitePool,
+    /// Insert a grant, or replace the expiry of an existing one.
+    pub async fn upsert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
         node_id: i64,
         role_id: i64,
+        resource: &str,
+        expires_at: Option<NaiveDateTime>,
     ) -> Result<NodeRole, sqlx::Error> {
-        match Self::find(pool, node_id, role_id).await? {
-            Some(existing) => Ok(existing),
-            None => Self::insert(pool, node_id, role_id).await,
-        }
+        query_as::<_, NodeRole>(
+            r#"
+                INSERT INTO node_roles (node_id, role_id, resource, expires_at, created)
+                VALUES ($1, $2, $3, $4, datetime('now'))
+                ON CONFLICT (node_id, role_id, resource) DO UPDATE SET expires_at = excluded.expires_at
+                RETURNING *
+            "#,
+        )
+        .bind(node_id)
+        .bind(role_id)
+        .bind(resource)
+        .bind(expires_at)
+        .fetch_one(conn)
+        .await
     }
 
-    pub async fn find<'a, E: Executor<'a, Database = Sqlite>>(
+    /// Insert a grant with a known creation time, or replace an existing one.
+    /// Grants without a creation time are dated now.
+    pub async fn restore<'a, E: Executor<'a, Database = Sqlite>>(
         conn: E,
         node_id: i64,
         role_id: i64,
-    ) -> Result<Option<NodeRole>, sqlx::Error> {
-        query_as::<_, NodeRole>("SELECT * FROM node_roles WHERE node_id = $1 AND role_id = $2")
-            .bind(node_id)
-            .bind(role_id)
-            .fetch_optional(conn)
-            .await
+        resource: &str,
+        expires_at: Option<NaiveDateTime>,
+        created: Option<NaiveDateTime>,
+    ) -> Result<NodeRole, sqlx::Error> {
+        query_as::<_, NodeRole>(
+            r#"
+                INSERT INTO node_roles (node_id, role_id, resource, expires_at, created) VALUES ($1, $2, $3, $4, COALESCE($5, datetime('now')))
+                ON CONFLICT (node_id, role_id, resource) DO UPDATE SET expires_at = excluded.expires_at, created = excluded.created
+                RETURNING *
+            "#,
+        )
+        .bind(node_id)
+        .bind(role_id)
+        .bind(resource)
+        .bind(expires_at)
+        .bind(created)
+        .fetch_one(conn)
+        .await
     }
 
-    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+    pub async fn all_named<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<NamedNodeRole>, sqlx::Error> {
+        query_as::<_, NamedNodeRole>(
+            r#"
+                SELECT
+                    n.node AS node, r.role AS role, nr.expires_at AS expires_at,
+                    nr.created AS created, nr.resource AS resource
+                FROM node_roles nr
+                JOIN nodes n ON nr.node_id = n.id
+                JOIN roles r ON nr.role_id = r.id
+                ORDER BY n.node, r.role, nr.resource
+            "#,
+        )
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn find<'a, E: Executor<'a, Database = Sqlite>>(
         conn: E,
         node_id: i64,
         role_id: i64,
-    ) -> Result<NodeRole, sqlx::Error> {
+        resource: &str,
+    ) -> Result<Option<NodeRole>, sqlx::Error> {
         query_as::<_, NodeRole>(
-            "INSERT INTO node_roles (node_id, role_id) VALUES ($1, $2) RETURNING *",
+            "SELECT * FROM node_roles WHERE node_id = $1 AND role_id = $2 AND resource = $3",
         )
         .bind(node_id)
         .bind(role_id)
-        .fetch_one(conn)
+        .bind(resource)
+        .fetch_optional(conn)
         .await
     }
 
@@ -56,4 +118,15 @@ impl NodeRole {
             .await
             .map(|r| r.rows_affected())
     }
+
+    pub async fn delete_expired<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<u64, sqlx::Error> {
+        query(
+            "DELETE FROM node_roles WHERE expires_at IS NOT NULL AND expires_at <= datetime('now')",
+        )
+        .execute(conn)
+        .await
+        .map(|r| r.rows_affected())
+    }
 }
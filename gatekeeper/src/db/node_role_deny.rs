@@ -0,0 +1,91 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
+
+/// A negative grant: the node never holds the role, however it's granted.
+#[allow(dead_code)]
+#[derive(Debug, FromRow)]
+pub struct NodeRoleDeny {
+    pub id: i64,
+    pub node_id: i64,
+    pub role_id: i64,
+    pub created: NaiveDateTime,
+}
+
+/// A deny with its node and role resolved.
+#[derive(Debug, FromRow)]
+pub struct NamedNodeRoleDeny {
+    pub node: String,
+    pub role: String,
+    pub created: NaiveDateTime,
+}
+
+impl NodeRoleDeny {
+    pub async fn ensure<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+        role_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query(
+            r#"
+                INSERT INTO node_role_denies (node_id, role_id, created)
+                VALUES ($1, $2, datetime('now'))
+                ON CONFLICT (node_id, role_id) DO NOTHING
+            "#,
+        )
+        .bind(node_id)
+        .bind(role_id)
+        .execute(conn)
+        .await
+        .map(|r| r.rows_affected())
+    }
+
+    /// Insert a deny with a known creation time, or update an existing one.
+    pub async fn restore<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+        role_id: i64,
+        created: NaiveDateTime,
+    ) -> Result<NodeRoleDeny, sqlx::Error> {
+        query_as::<_, NodeRoleDeny>(
+            r#"
+                INSERT INTO node_role_denies (node_id, role_id, created) VALUES ($1, $2, $3)
+                ON CONFLICT (node_id, role_id) DO UPDATE SET created = excluded.created
+                RETURNING *
+            "#,
+        )
+        .bind(node_id)
+        .bind(role_id)
+        .bind(created)
+        .fetch_one(conn)
+        .await
+    }
+
+    pub async fn all_named<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<NamedNodeRoleDeny>, sqlx::Error> {
+        query_as::<_, NamedNodeRoleDeny>(
+            r#"
+                SELECT n.node AS node, r.role AS role, d.created AS created
+                FROM node_role_denies d
+                JOIN nodes n ON d.node_id = n.id
+                JOIN roles r ON d.role_id = r.id
+                ORDER BY n.node, r.role
+            "#,
+        )
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        node_id: i64,
+        role_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM node_role_denies WHERE node_id = $1 AND role_id = $2")
+            .bind(node_id)
+            .bind(role_id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+}
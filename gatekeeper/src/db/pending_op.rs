@@ -0,0 +1,84 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
+
+/// A command held until a second superadmin confirms it, stored in its wire encoding.
+#[derive(Debug, FromRow)]
+pub struct PendingOp {
+    pub id: i64,
+    pub cmd_name: String,
+    pub cmd: Vec<u8>,
+    pub args: String,
+    pub requested_by: String,
+    pub expires_at: NaiveDateTime,
+    pub created: NaiveDateTime,
+}
+
+impl PendingOp {
+    /// Operations that can still be confirmed, oldest first.
+    pub async fn all_open<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<PendingOp>, sqlx::Error> {
+        query_as::<_, PendingOp>(
+            "SELECT * FROM pending_ops WHERE expires_at > datetime('now') ORDER BY id",
+        )
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn find_open<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<Option<PendingOp>, sqlx::Error> {
+        query_as::<_, PendingOp>(
+            "SELECT * FROM pending_ops WHERE id = $1 AND expires_at > datetime('now')",
+        )
+        .bind(id)
+        .fetch_optional(conn)
+        .await
+    }
+
+    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        cmd_name: &str,
+        cmd: &[u8],
+        args: &str,
+        requested_by: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<PendingOp, sqlx::Error> {
+        query_as::<_, PendingOp>(
+            r#"
+                INSERT INTO pending_ops (cmd_name, cmd, args, requested_by, expires_at, created)
+                VALUES ($1, $2, $3, $4, $5, datetime('now'))
+                RETURNING *
+            "#,
+        )
+        .bind(cmd_name)
+        .bind(cmd)
+        .bind(args)
+        .bind(requested_by)
+        .bind(expires_at)
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Remove an operation, returning false if it was already gone.
+    pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        query("DELETE FROM pending_ops WHERE id = $1")
+            .bind(id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected() == 1)
+    }
+
+    pub async fn delete_expired<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM pending_ops WHERE expires_at <= datetime('now')")
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+}
@@ -1,5 +1,7 @@
 use chrono::NaiveDateTime;
-use sqlx::{Executor, Sqlite, SqlitePool, prelude::FromRow, query_as};
+use sqlx::{Executor, Sqlite, SqlitePool, prelude::FromRow, query, query_as};
+
+use super::Count;
 
 #[allow(dead_code)]
 #[derive(Debug, FromRow)]
@@ -7,6 +9,7 @@ pub struct Role {
     pub id: i64,
     pub role: String,
     pub created: NaiveDateTime,
+    pub description: Option<String>,
 }
 
 impl Role {
@@ -21,10 +24,27 @@ impl Role {
     pub async fn ensure(pool: &SqlitePool, role: &str) -> Result<Role, sqlx::Error> {
         match Self::find(pool, role).await? {
             Some(existing) => Ok(existing),
-            None => Self::insert(pool, role).await,
+            None => Self::insert(pool, role, None).await,
         }
     }
 
+    pub async fn parents<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        role_id: i64,
+    ) -> Result<Vec<Role>, sqlx::Error> {
+        query_as::<_, Role>(
+            r#"
+                SELECT r.* FROM role_parents rp
+                JOIN roles r ON rp.parent_id = r.id
+                WHERE rp.role_id = $1
+                ORDER BY r.role
+            "#,
+        )
+        .bind(role_id)
+        .fetch_all(conn)
+        .await
+    }
+
     pub async fn find<'a, E: Executor<'a, Database = Sqlite>>(
         conn: E,
         role: &str,
@@ -35,15 +55,141 @@ impl Role {
             .await
     }
 
+    /// Number of grants of the role to nodes, groups and selectors, including expired ones
+    /// not yet purged.
+    pub async fn grant_count<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        role_id: i64,
+    ) -> Result<i64, sqlx::Error> {
+        query_as::<_, Count>(
+            r#"
+                SELECT
+                    (SELECT COUNT(*) FROM node_roles WHERE role_id = $1)
+                    + (SELECT COUNT(*) FROM group_roles WHERE role_id = $1)
+                    + (SELECT COUNT(*) FROM selector_roles WHERE role_id = $1) AS count
+            "#,
+        )
+        .bind(role_id)
+        .fetch_one(conn)
+        .await
+        .map(|c| c.count)
+    }
+
+    /// Number of grants, denies and parent links of the role reaching outside the
+    /// tenant: nodes in another tenant or none, groups and roles the tenant
+    /// doesn't own (see [`crate::tenant_owns`]), and selector grants, which
+    /// belong to no tenant.
+    pub async fn links_outside_tenant<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        role_id: i64,
+        tenant: &str,
+    ) -> Result<i64, sqlx::Error> {
+        query_as::<_, Count>(
+            r#"
+                SELECT
+                    (SELECT COUNT(*) FROM node_roles nr
+                        JOIN nodes n ON nr.node_id = n.id
+                        WHERE nr.role_id = $1 AND n.tenant IS NOT $2)
+                    + (SELECT COUNT(*) FROM node_role_denies d
+                        JOIN nodes n ON d.node_id = n.id
+                        WHERE d.role_id = $1 AND n.tenant IS NOT $2)
+                    + (SELECT COUNT(*) FROM group_roles gr
+                        JOIN groups g ON gr.group_id = g.id
+                        WHERE gr.role_id = $1
+                        AND NOT (substr(g.name, 1, length($2) + 1) = $2 || '/'
+                            AND length(g.name) > length($2) + 1))
+                    + (SELECT COUNT(*) FROM selector_roles WHERE role_id = $1)
+                    + (SELECT COUNT(*) FROM role_parents rp
+                        JOIN roles r ON r.id IN (rp.role_id, rp.parent_id) AND r.id != $1
+                        WHERE $1 IN (rp.role_id, rp.parent_id)
+                        AND NOT (substr(r.role, 1, length($2) + 1) = $2 || '/'
+                            AND length(r.role) > length($2) + 1)) AS count
+            "#,
+        )
+        .bind(role_id)
+        .bind(tenant)
+        .fetch_one(conn)
+        .await
+        .map(|c| c.count)
+    }
+
+    /// Insert a role with a known creation time, or update the description of
+    /// an existing role with the same name.
+    pub async fn restore<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        role: &str,
+        description: Option<&str>,
+        created: NaiveDateTime,
+    ) -> Result<Role, sqlx::Error> {
+        query_as::<_, Role>(
+            r#"
+                INSERT INTO roles (role, description, created) VALUES ($1, $2, $3)
+                ON CONFLICT (role) DO UPDATE SET description = excluded.description
+                RETURNING *
+            "#,
+        )
+        .bind(role)
+        .bind(description)
+        .bind(created)
+        .fetch_one(conn)
+        .await
+    }
+
+    pub async fn delete_all<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM roles")
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
     pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
         conn: E,
         role: &str,
+        description: Option<&str>,
     ) -> Result<Role, sqlx::Error> {
         query_as::<_, Role>(
-            "INSERT INTO roles (role, created) VALUES ($1, datetime('now')) RETURNING *",
+            "INSERT INTO roles (role, description, created) VALUES ($1, $2, datetime('now')) RETURNING *",
         )
         .bind(role)
+        .bind(description)
         .fetch_one(conn)
         .await
     }
+
+    pub async fn rename<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        role: &str,
+    ) -> Result<Role, sqlx::Error> {
+        query_as::<_, Role>("UPDATE roles SET role = $2 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(role)
+            .fetch_one(conn)
+            .await
+    }
+
+    pub async fn set_description<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        description: Option<&str>,
+    ) -> Result<Role, sqlx::Error> {
+        query_as::<_, Role>("UPDATE roles SET description = $2 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(description)
+            .fetch_one(conn)
+            .await
+    }
+
+    pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM roles WHERE id = $1")
+            .bind(id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
 }
@@ -0,0 +1,124 @@
+use sqlx::{Executor, Sqlite, SqlitePool, prelude::FromRow, query, query_as};
+
+use super::Count;
+
+#[allow(dead_code)]
+#[derive(Debug, FromRow)]
+pub struct RoleParent {
+    pub id: i64,
+    pub role_id: i64,
+    pub parent_id: i64,
+}
+
+/// An inheritance edge with its roles resolved.
+#[derive(Debug, FromRow)]
+pub struct NamedRoleParent {
+    pub role: String,
+    pub parent: String,
+}
+
+impl RoleParent {
+    pub async fn ensure(
+        pool: &SqlitePool,
+        role_id: i64,
+        parent_id: i64,
+    ) -> Result<RoleParent, sqlx::Error> {
+        match Self::find(pool, role_id, parent_id).await? {
+            Some(existing) => Ok(existing),
+            None => Self::insert(pool, role_id, parent_id).await,
+        }
+    }
+
+    pub async fn all_named<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<NamedRoleParent>, sqlx::Error> {
+        query_as::<_, NamedRoleParent>(
+            r#"
+                SELECT r.role AS role, p.role AS parent FROM role_parents rp
+                JOIN roles r ON rp.role_id = r.id
+                JOIN roles p ON rp.parent_id = p.id
+                ORDER BY r.role, p.role
+            "#,
+        )
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn find<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        role_id: i64,
+        parent_id: i64,
+    ) -> Result<Option<RoleParent>, sqlx::Error> {
+        query_as::<_, RoleParent>(
+            "SELECT * FROM role_parents WHERE role_id = $1 AND parent_id = $2",
+        )
+        .bind(role_id)
+        .bind(parent_id)
+        .fetch_optional(conn)
+        .await
+    }
+
+    /// Does holding the role `from_id` (transitively) confer the role `to_id`?
+    pub async fn implies<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        from_id: i64,
+        to_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        query_as::<_, Count>(
+            r#"
+                WITH RECURSIVE implied(role_id) AS (
+                    SELECT $1
+                    UNION
+                    SELECT rp.role_id FROM role_parents rp
+                    JOIN implied i ON rp.parent_id = i.role_id
+                )
+                SELECT COUNT(*) AS count FROM implied WHERE role_id = $2
+            "#,
+        )
+        .bind(from_id)
+        .bind(to_id)
+        .fetch_one(conn)
+        .await
+        .map(|c| c.count > 0)
+    }
+
+    pub async fn insert_if_missing<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        role_id: i64,
+        parent_id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query(
+            "INSERT INTO role_parents (role_id, parent_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(role_id)
+        .bind(parent_id)
+        .execute(conn)
+        .await
+        .map(|r| r.rows_affected())
+    }
+
+    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        role_id: i64,
+        parent_id: i64,
+    ) -> Result<RoleParent, sqlx::Error> {
+        query_as::<_, RoleParent>(
+            "INSERT INTO role_parents (role_id, parent_id) VALUES ($1, $2) RETURNING *",
+        )
+        .bind(role_id)
+        .bind(parent_id)
+        .fetch_one(conn)
+        .await
+    }
+
+    pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM role_parents WHERE id = $1")
+            .bind(id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
+
+/// A role granted to every node carrying all of a selector's labels.
+#[allow(dead_code)]
+#[derive(Debug, FromRow)]
+pub struct SelectorRole {
+    pub id: i64,
+    pub selector: String,
+    pub role_id: i64,
+    pub created: NaiveDateTime,
+}
+
+/// A selector grant with its role resolved.
+#[derive(Debug, FromRow)]
+pub struct NamedSelectorRole {
+    pub selector: String,
+    pub role: String,
+    pub created: NaiveDateTime,
+}
+
+impl SelectorRole {
+    pub async fn all_named<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<NamedSelectorRole>, sqlx::Error> {
+        query_as::<_, NamedSelectorRole>(
+            r#"
+                SELECT sr.selector AS selector, r.role AS role, sr.created AS created
+                FROM selector_roles sr
+                JOIN roles r ON sr.role_id = r.id
+                ORDER BY sr.selector, r.role
+            "#,
+        )
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn find<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        selector: &str,
+        role_id: i64,
+    ) -> Result<Option<SelectorRole>, sqlx::Error> {
+        query_as::<_, SelectorRole>(
+            "SELECT * FROM selector_roles WHERE selector = $1 AND role_id = $2",
+        )
+        .bind(selector)
+        .bind(role_id)
+        .fetch_optional(conn)
+        .await
+    }
+
+    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        selector: &str,
+        role_id: i64,
+    ) -> Result<SelectorRole, sqlx::Error> {
+        query_as::<_, SelectorRole>(
+            r#"
+                INSERT INTO selector_roles (selector, role_id, created)
+                VALUES ($1, $2, datetime('now'))
+                RETURNING *
+            "#,
+        )
+        .bind(selector)
+        .bind(role_id)
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Insert a selector grant with a known creation time, or update an
+    /// existing one.
+    pub async fn restore<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        selector: &str,
+        role_id: i64,
+        created: NaiveDateTime,
+    ) -> Result<SelectorRole, sqlx::Error> {
+        query_as::<_, SelectorRole>(
+            r#"
+                INSERT INTO selector_roles (selector, role_id, created) VALUES ($1, $2, $3)
+                ON CONFLICT (selector, role_id) DO UPDATE SET created = excluded.created
+                RETURNING *
+            "#,
+        )
+        .bind(selector)
+        .bind(role_id)
+        .bind(created)
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Add a label the selector requires, unless it's already there.
+    pub async fn add_term<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+        key: &str,
+        value: &str,
+    ) -> Result<u64, sqlx::Error> {
+        query(
+            r#"
+                INSERT OR IGNORE INTO selector_role_terms (selector_role_id, key, value)
+                VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(id)
+        .bind(key)
+        .bind(value)
+        .execute(conn)
+        .await
+        .map(|r| r.rows_affected())
+    }
+
+    pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM selector_roles WHERE id = $1")
+            .bind(id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+}
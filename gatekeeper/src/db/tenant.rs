@@ -0,0 +1,100 @@
+use chrono::NaiveDateTime;
+use sqlx::{Executor, Sqlite, prelude::FromRow, query, query_as};
+
+use super::Count;
+
+#[derive(Debug, FromRow)]
+pub struct Tenant {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub created: NaiveDateTime,
+}
+
+impl Tenant {
+    pub async fn all<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<Vec<Tenant>, sqlx::Error> {
+        query_as::<_, Tenant>("SELECT * FROM tenants ORDER BY name")
+            .fetch_all(conn)
+            .await
+    }
+
+    pub async fn find<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        name: &str,
+    ) -> Result<Option<Tenant>, sqlx::Error> {
+        query_as::<_, Tenant>("SELECT * FROM tenants WHERE name = $1")
+            .bind(name)
+            .fetch_optional(conn)
+            .await
+    }
+
+    pub async fn insert<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<Tenant, sqlx::Error> {
+        query_as::<_, Tenant>(
+            "INSERT INTO tenants (name, description, created) VALUES ($1, $2, datetime('now')) RETURNING *",
+        )
+        .bind(name)
+        .bind(description)
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Insert a tenant with a known creation time, or update the description of
+    /// an existing tenant with the same name.
+    pub async fn restore<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        name: &str,
+        description: Option<&str>,
+        created: NaiveDateTime,
+    ) -> Result<Tenant, sqlx::Error> {
+        query_as::<_, Tenant>(
+            r#"
+                INSERT INTO tenants (name, description, created) VALUES ($1, $2, $3)
+                ON CONFLICT (name) DO UPDATE SET description = excluded.description
+                RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(description)
+        .bind(created)
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Number of nodes in the tenant.
+    pub async fn node_count<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        name: &str,
+    ) -> Result<i64, sqlx::Error> {
+        query_as::<_, Count>("SELECT COUNT(*) AS count FROM nodes WHERE tenant = $1")
+            .bind(name)
+            .fetch_one(conn)
+            .await
+            .map(|c| c.count)
+    }
+
+    pub async fn delete<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+        id: i64,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM tenants WHERE id = $1")
+            .bind(id)
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    pub async fn delete_all<'a, E: Executor<'a, Database = Sqlite>>(
+        conn: E,
+    ) -> Result<u64, sqlx::Error> {
+        query("DELETE FROM tenants")
+            .execute(conn)
+            .await
+            .map(|r| r.rows_affected())
+    }
+}
@@ -1,3 +1,5 @@
+use bincode::{Decode, Encode};
+
 #[derive(Debug)]
 pub enum Error {
     ConnectionError(iroh::endpoint::ConnectionError),
@@ -8,8 +10,54 @@ pub enum Error {
     DecodeError(bincode::error::DecodeError),
     EncodeError(bincode::error::EncodeError),
     DbError(sqlx::Error),
+    ServerError(String),
     UnauthorizedError,
     NoSuchNodeError,
+    RoleCycleError,
+    InvalidSnapshotError(String),
+    NoSuchRoleError,
+    RoleExistsError,
+    RoleInUseError,
+    NodeExistsError,
+    InvalidTicketError,
+    NoSuchRequestError,
+    ConfirmationRequiredError(i64),
+    NoSuchOpError,
+    NoSuchGroupError,
+    GroupExistsError,
+    InvalidLabelError(String),
+    NoSuchTenantError,
+    TenantExistsError,
+    TenantInUseError,
+    InvalidTenantError(String),
+    InvalidTtlError(String),
+}
+
+/// Wire representation of an [`Error`], sent by the server in place of a
+/// successful response.
+#[derive(Clone, Debug, Decode, Encode)]
+pub enum ErrorCode {
+    Unauthorized,
+    NoSuchNode,
+    RoleCycle,
+    InvalidSnapshot(String),
+    NoSuchRole,
+    RoleExists,
+    RoleInUse,
+    NodeExists,
+    InvalidTicket,
+    NoSuchRequest,
+    ConfirmationRequired(i64),
+    NoSuchOp,
+    NoSuchGroup,
+    GroupExists,
+    InvalidLabel(String),
+    NoSuchTenant,
+    TenantExists,
+    TenantInUse,
+    InvalidTenant(String),
+    InvalidTtl(String),
+    Internal(String),
 }
 
 impl std::fmt::Display for Error {
@@ -23,14 +71,89 @@ impl std::fmt::Display for Error {
             Self::DecodeError(e) => write!(f, "DecodeError: {:?}", e),
             Self::EncodeError(e) => write!(f, "EncodeError: {:?}", e),
             Self::DbError(e) => write!(f, "DbError: {:?}", e),
+            Self::ServerError(e) => write!(f, "ServerError: {}", e),
             Self::UnauthorizedError => write!(f, "UnauthorizedError"),
             Self::NoSuchNodeError => write!(f, "NoSuchNodeError"),
+            Self::RoleCycleError => write!(f, "RoleCycleError"),
+            Self::InvalidSnapshotError(e) => write!(f, "InvalidSnapshotError: {}", e),
+            Self::NoSuchRoleError => write!(f, "NoSuchRoleError"),
+            Self::RoleExistsError => write!(f, "RoleExistsError"),
+            Self::RoleInUseError => write!(f, "RoleInUseError"),
+            Self::NodeExistsError => write!(f, "NodeExistsError"),
+            Self::InvalidTicketError => write!(f, "InvalidTicketError"),
+            Self::NoSuchRequestError => write!(f, "NoSuchRequestError"),
+            Self::ConfirmationRequiredError(id) => write!(f, "ConfirmationRequiredError: {}", id),
+            Self::NoSuchOpError => write!(f, "NoSuchOpError"),
+            Self::NoSuchGroupError => write!(f, "NoSuchGroupError"),
+            Self::GroupExistsError => write!(f, "GroupExistsError"),
+            Self::InvalidLabelError(e) => write!(f, "InvalidLabelError: {}", e),
+            Self::NoSuchTenantError => write!(f, "NoSuchTenantError"),
+            Self::TenantExistsError => write!(f, "TenantExistsError"),
+            Self::TenantInUseError => write!(f, "TenantInUseError"),
+            Self::InvalidTenantError(e) => write!(f, "InvalidTenantError: {}", e),
+            Self::InvalidTtlError(e) => write!(f, "InvalidTtlError: {}", e),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+impl From<&Error> for ErrorCode {
+    fn from(value: &Error) -> Self {
+        match value {
+            Error::UnauthorizedError => Self::Unauthorized,
+            Error::NoSuchNodeError => Self::NoSuchNode,
+            Error::RoleCycleError => Self::RoleCycle,
+            Error::InvalidSnapshotError(e) => Self::InvalidSnapshot(e.clone()),
+            Error::NoSuchRoleError => Self::NoSuchRole,
+            Error::RoleExistsError => Self::RoleExists,
+            Error::RoleInUseError => Self::RoleInUse,
+            Error::NodeExistsError => Self::NodeExists,
+            Error::InvalidTicketError => Self::InvalidTicket,
+            Error::NoSuchRequestError => Self::NoSuchRequest,
+            Error::ConfirmationRequiredError(id) => Self::ConfirmationRequired(*id),
+            Error::NoSuchOpError => Self::NoSuchOp,
+            Error::NoSuchGroupError => Self::NoSuchGroup,
+            Error::GroupExistsError => Self::GroupExists,
+            Error::InvalidLabelError(e) => Self::InvalidLabel(e.clone()),
+            Error::NoSuchTenantError => Self::NoSuchTenant,
+            Error::TenantExistsError => Self::TenantExists,
+            Error::TenantInUseError => Self::TenantInUse,
+            Error::InvalidTenantError(e) => Self::InvalidTenant(e.clone()),
+            Error::InvalidTtlError(e) => Self::InvalidTtl(e.clone()),
+            e => Self::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<ErrorCode> for Error {
+    fn from(value: ErrorCode) -> Self {
+        match value {
+            ErrorCode::Unauthorized => Self::UnauthorizedError,
+            ErrorCode::NoSuchNode => Self::NoSuchNodeError,
+            ErrorCode::RoleCycle => Self::RoleCycleError,
+            ErrorCode::InvalidSnapshot(e) => Self::InvalidSnapshotError(e),
+            ErrorCode::NoSuchRole => Self::NoSuchRoleError,
+            ErrorCode::RoleExists => Self::RoleExistsError,
+            ErrorCode::RoleInUse => Self::RoleInUseError,
+            ErrorCode::NodeExists => Self::NodeExistsError,
+            ErrorCode::InvalidTicket => Self::InvalidTicketError,
+            ErrorCode::NoSuchRequest => Self::NoSuchRequestError,
+            ErrorCode::ConfirmationRequired(id) => Self::ConfirmationRequiredError(id),
+            ErrorCode::NoSuchOp => Self::NoSuchOpError,
+            ErrorCode::NoSuchGroup => Self::NoSuchGroupError,
+            ErrorCode::GroupExists => Self::GroupExistsError,
+            ErrorCode::InvalidLabel(e) => Self::InvalidLabelError(e),
+            ErrorCode::NoSuchTenant => Self::NoSuchTenantError,
+            ErrorCode::TenantExists => Self::TenantExistsError,
+            ErrorCode::TenantInUse => Self::TenantInUseError,
+            ErrorCode::InvalidTenant(e) => Self::InvalidTenantError(e),
+            ErrorCode::InvalidTtl(e) => Self::InvalidTtlError(e),
+            ErrorCode::Internal(e) => Self::ServerError(e),
+        }
+    }
+}
+
 impl From<iroh::endpoint::ConnectionError> for Error {
     fn from(value: iroh::endpoint::ConnectionError) -> Self {
         Self::ConnectionError(value)
@@ -0,0 +1,116 @@
+use iroh::{
+    NodeId,
+    endpoint::{Connecting, Connection},
+    protocol::{AcceptError, ProtocolHandler},
+};
+
+use crate::{Arbiter, Client, Error};
+
+/// Where [`Gated`] handlers look up the roles held by a connecting node.
+#[derive(Clone, Debug)]
+pub enum Gate {
+    /// Check roles against a local arbiter
+    Local(Arbiter),
+    /// Check roles against a remote gatekeeper server. The client only needs the
+    /// `checker` tier.
+    Remote(Client),
+}
+
+/// Roles a node must hold to be let through a [`Gated`] handler.
+#[derive(Clone, Debug)]
+pub enum Requirement {
+    /// At least one of the roles
+    Any(Vec<String>),
+    /// Every one of the roles
+    All(Vec<String>),
+}
+
+impl Gate {
+    /// Wrap a protocol handler so that only nodes meeting the requirement reach it.
+    pub fn gated<H: ProtocolHandler>(&self, inner: H, requirement: Requirement) -> Gated<H> {
+        Gated::new(inner, self.clone(), requirement)
+    }
+
+    pub async fn allows(&self, node: NodeId, requirement: &Requirement) -> Result<bool, Error> {
+        match self {
+            Self::Local(arbiter) => {
+                let node = format!("{node}");
+                match requirement {
+                    Requirement::Any(roles) => arbiter.has_any_role(&node, roles).await,
+                    Requirement::All(roles) => arbiter.has_all_roles(&node, roles).await,
+                }
+            }
+            Self::Remote(client) => {
+                let (roles, any) = match requirement {
+                    Requirement::Any(roles) => (roles, true),
+                    Requirement::All(roles) => (roles, false),
+                };
+
+                if roles.is_empty() {
+                    return Ok(!any);
+                }
+
+                let checks = roles.iter().map(|r| (node, r.clone(), None)).collect();
+                let res = client.check(checks).await?;
+
+                if any {
+                    Ok(res.into_iter().any(|held| held))
+                } else {
+                    Ok(res.into_iter().all(|held| held))
+                }
+            }
+        }
+    }
+}
+
+/// A protocol handler that only accepts connections from nodes meeting a role
+/// requirement. Register one per ALPN to give each protocol its own requirement.
+///
+/// Refused connections are closed with an error code of `0` and reason `not allowed`.
+#[derive(Clone, Debug)]
+pub struct Gated<H> {
+    inner: H,
+    gate: Gate,
+    requirement: Requirement,
+}
+
+impl<H> Gated<H> {
+    pub fn new(inner: H, gate: Gate, requirement: Requirement) -> Self {
+        Self {
+            inner,
+            gate,
+            requirement,
+        }
+    }
+}
+
+impl<H: ProtocolHandler> ProtocolHandler for Gated<H> {
+    fn on_connecting(
+        &self,
+        connecting: Connecting,
+    ) -> impl Future<Output = Result<Connection, AcceptError>> + Send {
+        self.inner.on_connecting(connecting)
+    }
+
+    async fn accept(&self, connection: Connection) -> Result<(), AcceptError> {
+        let node_id = connection.remote_node_id()?;
+
+        match self.gate.allows(node_id, &self.requirement).await {
+            Ok(true) => self.inner.accept(connection).await,
+            Ok(false) => {
+                tracing::info!(node_id = ?node_id, requirement = ?self.requirement, "gate_denied");
+                connection.close(0u32.into(), b"not allowed");
+                Err(AcceptError::from_err(Error::UnauthorizedError))
+            }
+            Err(e) => {
+                tracing::warn!(node_id = ?node_id, err = ?e, "gate_failed");
+                connection.close(0u32.into(), b"not allowed");
+                Err(AcceptError::from_err(e))
+            }
+        }
+    }
+
+    fn shutdown(&self) -> impl Future<Output = ()> + Send {
+        self.inner.shutdown()
+    }
+}
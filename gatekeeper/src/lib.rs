@@ -3,10 +3,24 @@ mod client;
 mod common;
 mod db;
 mod error;
+mod gate;
 mod server;
+mod snapshot;
+mod ticket;
 
 pub use arbiter::Arbiter;
 pub use client::Client;
-pub use common::{ALPN, Cmd, Either, Node};
-pub use error::Error;
+pub use common::{
+    ALPN, AccessRequest, AdminTier, AuditEntry, AuditFilter, Cmd, Either, Grant, Group, Invite,
+    KeyRotation, Label, LabelSelector, MAX_TTL, Node, PendingOp, RequestStatus, Role, RoleCheck,
+    SelectorGrant, Tenant, WhoAmI, parse_timestamp, parse_ttl, tenant_owns,
+};
+pub use error::{Error, ErrorCode};
+pub use gate::{Gate, Gated, Requirement};
 pub use server::Server;
+pub use snapshot::{
+    ImportMode, SNAPSHOT_VERSION, Snapshot, SnapshotDeny, SnapshotGrant, SnapshotGroup,
+    SnapshotLabel, SnapshotNode, SnapshotRole, SnapshotRoleParent, SnapshotSelectorGrant,
+    SnapshotTenant,
+};
+pub use ticket::InviteTicket;
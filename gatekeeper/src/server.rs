@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, time::Duration};
 
 use bincode::Encode;
 use iroh::{
@@ -7,7 +7,7 @@ use iroh::{
     protocol::{AcceptError, ProtocolHandler},
 };
 
-use crate::{Arbiter, Cmd, Error};
+use crate::{Arbiter, Cmd, Error, ErrorCode};
 
 #[derive(Clone)]
 pub struct Server {
@@ -31,27 +31,261 @@ impl Server {
     }
 
     async fn handle(&self, caller: NodeId, cmd: Cmd) -> Result<Vec<u8>, Error> {
-        if !self.arbiter.allow(caller).await? {
+        // Rejected callers are only logged, so unknown nodes can't fill the audit log
+        if !self.arbiter.authorize(caller, &cmd).await? {
+            tracing::warn!(caller = ?caller, cmd = cmd.name(), "unauthorized");
             return Err(Error::UnauthorizedError);
         }
 
+        if !cmd.is_mutating() {
+            return self.dispatch(caller, cmd).await;
+        }
+
+        let audited = cmd.clone();
+        let rsp = self.dispatch(caller, cmd).await;
+        self.audit(caller, &audited, &rsp).await;
+
+        rsp
+    }
+
+    async fn audit(&self, caller: NodeId, cmd: &Cmd, rsp: &Result<Vec<u8>, Error>) {
+        if let Err(e) = self
+            .arbiter
+            .record_audit(caller, cmd, rsp.as_ref().err())
+            .await
+        {
+            tracing::error!(cmd = ?cmd, err = ?e, "audit_failed");
+        }
+    }
+
+    /// Run an authorized command, or hold it if it needs confirmation.
+    async fn dispatch(&self, caller: NodeId, cmd: Cmd) -> Result<Vec<u8>, Error> {
+        if self.arbiter.requires_confirmation(&cmd).await? {
+            let op = self.arbiter.hold_op(caller, &cmd).await?;
+            return Err(Error::ConfirmationRequiredError(op.id));
+        }
+
+        self.execute(caller, cmd).await
+    }
+
+    /// Run an already authorized command.
+    async fn execute(&self, caller: NodeId, cmd: Cmd) -> Result<Vec<u8>, Error> {
         match cmd {
-            Cmd::Roles => self.exec(self.arbiter.roles()).await,
-            Cmd::Nodes => self.exec(self.arbiter.nodes()).await,
-            Cmd::NodeRoles { node } => self.exec(self.arbiter.node_roles(&node)).await,
+            Cmd::Bootstrap { token, name, node } => {
+                self.exec(self.arbiter.bootstrap(&token, &name, &node))
+                    .await
+            }
+            Cmd::WhoAmI => self.exec(self.arbiter.whoami(caller)).await,
+            Cmd::Roles { tenant } => self.exec(self.arbiter.roles(tenant.as_deref())).await,
+            Cmd::ShowRole { role } => self.exec(self.arbiter.show_role(&role)).await,
+            Cmd::CreateRole { role, description } => {
+                self.exec(self.arbiter.create_role(&role, description.as_deref()))
+                    .await
+            }
+            Cmd::DescribeRole { role, description } => {
+                self.exec(self.arbiter.describe_role(&role, description.as_deref()))
+                    .await
+            }
+            Cmd::RenameRole { role, new_role } => {
+                self.exec(self.arbiter.rename_role(&role, &new_role)).await
+            }
+            Cmd::DeleteRole { role, cascade } => {
+                self.exec(self.arbiter.delete_role(&role, cascade)).await
+            }
+            Cmd::Nodes { tenant } => self.exec(self.arbiter.nodes(tenant.as_deref())).await,
+            Cmd::NodeRoles { node, effective } => {
+                self.exec(self.arbiter.node_roles(&node, effective)).await
+            }
+            Cmd::RoleNodes {
+                role,
+                indirect,
+                tenant,
+                limit,
+                offset,
+            } => {
+                self.exec(self.arbiter.role_nodes(
+                    &role,
+                    indirect,
+                    tenant.as_deref(),
+                    limit,
+                    offset,
+                ))
+                .await
+            }
             Cmd::CreateNode {
                 name,
                 node,
                 superadmin,
+                tenant,
             } => {
-                self.exec(self.arbiter.create_node(&name, &node, superadmin))
+                self.exec(
+                    self.arbiter
+                        .create_node(&name, &node, superadmin, tenant.as_deref()),
+                )
+                .await
+            }
+            Cmd::UpdateNode {
+                node,
+                name,
+                superadmin,
+                description,
+            } => {
+                self.exec(self.arbiter.update_node(
+                    &node,
+                    name.as_deref(),
+                    superadmin,
+                    description.as_deref(),
+                ))
+                .await
+            }
+            Cmd::SetAdminTier { node, tier } => {
+                self.exec(self.arbiter.set_admin_tier(&node, tier)).await
+            }
+            Cmd::SetNodeTenant { node, tenant } => {
+                self.exec(self.arbiter.set_node_tenant(&node, tenant.as_deref()))
                     .await
             }
+            Cmd::RotateNodeKey { node, new_node } => {
+                self.exec(self.arbiter.rotate_node_key(caller, &node, &new_node))
+                    .await
+            }
+            Cmd::NodeKeyHistory { node } => self.exec(self.arbiter.node_key_history(&node)).await,
             Cmd::DeleteNode { node } => self.exec(self.arbiter.delete_node(&node)).await,
-            Cmd::GrantRole { node, role } => self.exec(self.arbiter.grant_role(&node, &role)).await,
-            Cmd::RevokeRole { node, role } => {
-                self.exec(self.arbiter.revoke_role(&node, &role)).await
+            Cmd::GrantRole {
+                node,
+                role,
+                resource,
+                ttl,
+            } => {
+                let ttl = ttl.map(Duration::from_secs);
+                self.exec(
+                    self.arbiter
+                        .grant_role(&node, &role, resource.as_deref(), ttl),
+                )
+                .await
+            }
+            Cmd::RevokeRole {
+                node,
+                role,
+                resource,
+            } => {
+                self.exec(self.arbiter.revoke_role(&node, &role, resource.as_deref()))
+                    .await
+            }
+            Cmd::DenyRole { node, role } => self.exec(self.arbiter.deny_role(&node, &role)).await,
+            Cmd::RemoveRoleDeny { node, role } => {
+                self.exec(self.arbiter.remove_role_deny(&node, &role)).await
+            }
+            Cmd::RoleParents { role } => self.exec(self.arbiter.role_parents(&role)).await,
+            Cmd::AddRoleParent { role, parent } => {
+                self.exec(self.arbiter.add_role_parent(&role, &parent))
+                    .await
+            }
+            Cmd::RemoveRoleParent { role, parent } => {
+                self.exec(self.arbiter.remove_role_parent(&role, &parent))
+                    .await
+            }
+            Cmd::Groups { tenant } => self.exec(self.arbiter.groups(tenant.as_deref())).await,
+            Cmd::CreateGroup { group, description } => {
+                self.exec(self.arbiter.create_group(&group, description.as_deref()))
+                    .await
+            }
+            Cmd::DeleteGroup { group } => self.exec(self.arbiter.delete_group(&group)).await,
+            Cmd::GroupMembers { group } => self.exec(self.arbiter.group_members(&group)).await,
+            Cmd::AddGroupMember { group, node } => {
+                self.exec(self.arbiter.add_group_member(&group, &node))
+                    .await
+            }
+            Cmd::RemoveGroupMember { group, node } => {
+                self.exec(self.arbiter.remove_group_member(&group, &node))
+                    .await
+            }
+            Cmd::GroupRoles { group } => self.exec(self.arbiter.group_roles(&group)).await,
+            Cmd::GrantGroupRole { group, role } => {
+                self.exec(self.arbiter.grant_group_role(&group, &role))
+                    .await
+            }
+            Cmd::RevokeGroupRole { group, role } => {
+                self.exec(self.arbiter.revoke_group_role(&group, &role))
+                    .await
+            }
+            Cmd::NodeLabels { node } => self.exec(self.arbiter.node_labels(&node)).await,
+            Cmd::SetNodeLabel { node, label } => {
+                self.exec(self.arbiter.set_node_label(&node, &label)).await
+            }
+            Cmd::RemoveNodeLabel { node, key } => {
+                self.exec(self.arbiter.remove_node_label(&node, &key)).await
+            }
+            Cmd::SelectorGrants => self.exec(self.arbiter.selector_grants()).await,
+            Cmd::GrantSelectorRole { selector, role } => {
+                self.exec(self.arbiter.grant_selector_role(&selector, &role))
+                    .await
+            }
+            Cmd::RevokeSelectorRole { selector, role } => {
+                self.exec(self.arbiter.revoke_selector_role(&selector, &role))
+                    .await
+            }
+            Cmd::Tenants => self.exec(self.arbiter.tenants()).await,
+            Cmd::CreateTenant {
+                tenant,
+                description,
+            } => {
+                self.exec(self.arbiter.create_tenant(&tenant, description.as_deref()))
+                    .await
+            }
+            Cmd::DeleteTenant { tenant } => self.exec(self.arbiter.delete_tenant(&tenant)).await,
+            Cmd::Check { checks } => self.exec(self.arbiter.check(&checks)).await,
+            Cmd::CreateInvite { roles, ttl, uses } => {
+                let ttl = Duration::from_secs(ttl);
+                self.exec(self.arbiter.create_invite(caller, &roles, ttl, uses))
+                    .await
+            }
+            Cmd::Invites => self.exec(self.arbiter.invites()).await,
+            Cmd::DeleteInvite { id } => self.exec(self.arbiter.delete_invite(id)).await,
+            Cmd::Redeem { ticket, name } => {
+                self.exec(self.arbiter.redeem(caller, &ticket, &name)).await
+            }
+            Cmd::RequestRole {
+                role,
+                justification,
+                ttl,
+            } => {
+                let ttl = ttl.map(Duration::from_secs);
+                self.exec(
+                    self.arbiter
+                        .request_role(caller, &role, &justification, ttl),
+                )
+                .await
             }
+            Cmd::ListRequests { all } => self.exec(self.arbiter.list_requests(all)).await,
+            Cmd::Approve { id, note } => {
+                self.exec(self.arbiter.approve(caller, id, note.as_deref()))
+                    .await
+            }
+            Cmd::Deny { id, note } => {
+                self.exec(self.arbiter.deny(caller, id, note.as_deref()))
+                    .await
+            }
+            Cmd::PendingOps => self.exec(self.arbiter.pending_ops()).await,
+            Cmd::Confirm { id } => {
+                let (requester, cmd) = self.arbiter.confirm_op(caller, id).await?;
+                // The requester may have lost access since the command was held.
+                let rsp = match self.arbiter.authorize(requester, &cmd).await? {
+                    true => Box::pin(self.execute(requester, cmd.clone())).await,
+                    false => Err(Error::UnauthorizedError),
+                };
+
+                // Audited as itself, on behalf of the requester
+                if cmd.is_mutating() {
+                    self.audit(requester, &cmd, &rsp).await;
+                }
+
+                rsp
+            }
+            Cmd::CancelOp { id } => self.exec(self.arbiter.cancel_op(caller, id)).await,
+            Cmd::AuditLog(filter) => self.exec(self.arbiter.audit_log(&filter)).await,
+            Cmd::Export => self.exec(self.arbiter.export()).await,
+            Cmd::Import { snapshot, mode } => self.exec(self.arbiter.import(&snapshot, mode)).await,
         }
     }
 
@@ -59,7 +293,15 @@ impl Server {
         &self,
         f: F,
     ) -> Result<Vec<u8>, Error> {
-        let rsp = f.await?;
+        let rsp: Result<R, ErrorCode> = Ok(f.await?);
+        let res = bincode::encode_to_vec(&rsp, self.bincode_config)?;
+        Ok(res)
+    }
+
+    fn encode_error(&self, e: &Error) -> Result<Vec<u8>, Error> {
+        // The error variant of the envelope doesn't depend on the success type,
+        // so a `Result<(), ErrorCode>` decodes as a `Result<R, ErrorCode>` for any `R`.
+        let rsp: Result<(), ErrorCode> = Err(e.into());
         let res = bincode::encode_to_vec(&rsp, self.bincode_config)?;
         Ok(res)
     }
@@ -82,14 +324,19 @@ impl ProtocolHandler for Server {
             data.append(&mut bytes);
         }
 
-        let cmd: Cmd = bincode::decode_from_slice(&data, self.bincode_config)
-            .map_err(AcceptError::from_err)?
-            .0;
-
-        let rsp = self.handle(node_id, cmd.clone()).await;
-        if rsp.is_err() {
-            tracing::warn!(cmd = ?cmd, rsp = ?rsp, "handle_failed");
-        }
+        let rsp = match bincode::decode_from_slice::<Cmd, _>(&data, self.bincode_config) {
+            Ok((cmd, _)) => match self.handle(node_id, cmd.clone()).await {
+                Ok(rsp) => Ok(rsp),
+                Err(e) => {
+                    tracing::warn!(cmd = ?cmd, err = ?e, "handle_failed");
+                    self.encode_error(&e)
+                }
+            },
+            Err(e) => {
+                tracing::warn!(err = ?e, "decode_failed");
+                self.encode_error(&e.into())
+            }
+        };
 
         let rsp = rsp.map_err(AcceptError::from_err)?;
 
@@ -0,0 +1,132 @@
+use bincode::{Decode, Encode};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::AdminTier;
+
+/// Version of the snapshot format produced by this build. Snapshots from newer
+/// versions are rejected on import.
+///
+/// - 1: nodes, roles, role parents and grants
+/// - 2: role descriptions
+/// - 3: node descriptions
+/// - 4: node admin tiers
+/// - 5: node groups
+/// - 6: role denies
+/// - 7: grant resources
+/// - 8: node labels and selector grants
+/// - 9: tenants
+pub const SNAPSHOT_VERSION: u32 = 9;
+
+/// The full server state: every node, role, role parent, grant, group, deny,
+/// label, selector grant and tenant.
+/// Timestamps are unix timestamps.
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub nodes: Vec<SnapshotNode>,
+    pub roles: Vec<SnapshotRole>,
+    pub role_parents: Vec<SnapshotRoleParent>,
+    pub grants: Vec<SnapshotGrant>,
+    #[serde(default)]
+    pub groups: Vec<SnapshotGroup>,
+    #[serde(default)]
+    pub denies: Vec<SnapshotDeny>,
+    #[serde(default)]
+    pub labels: Vec<SnapshotLabel>,
+    #[serde(default)]
+    pub selector_grants: Vec<SnapshotSelectorGrant>,
+    #[serde(default)]
+    pub tenants: Vec<SnapshotTenant>,
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct SnapshotNode {
+    pub name: String,
+    pub node: String,
+    pub superadmin: bool,
+    #[serde(default)]
+    pub admin_tier: Option<AdminTier>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tenant: Option<String>,
+    pub created: i64,
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct SnapshotRole {
+    pub role: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub created: i64,
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct SnapshotRoleParent {
+    pub role: String,
+    pub parent: String,
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct SnapshotGrant {
+    pub node: String,
+    pub role: String,
+    pub expires_at: Option<i64>,
+    /// Missing in older snapshots, in which case the grant is dated to the import
+    pub created: Option<i64>,
+    /// Resource pattern the grant is scoped to, or `None` for every resource
+    #[serde(default)]
+    pub resource: Option<String>,
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct SnapshotGroup {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Member node public keys
+    pub members: Vec<String>,
+    /// Roles granted to the group
+    pub roles: Vec<String>,
+    pub created: i64,
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct SnapshotDeny {
+    pub node: String,
+    pub role: String,
+    pub created: i64,
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct SnapshotLabel {
+    pub node: String,
+    pub key: String,
+    pub value: String,
+    pub created: i64,
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct SnapshotSelectorGrant {
+    /// Label selector, in the form `<key>=<value>[,<key>=<value>...]`
+    pub selector: String,
+    pub role: String,
+    pub created: i64,
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, PartialEq, Serialize)]
+pub struct SnapshotTenant {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub created: i64,
+}
+
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, ValueEnum)]
+pub enum ImportMode {
+    /// Add the snapshot to the existing state, overwriting nodes and grants it contains
+    Merge,
+    /// Discard the existing state and replace it with the snapshot
+    Replace,
+}
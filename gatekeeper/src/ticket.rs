@@ -0,0 +1,49 @@
+use std::{fmt::Display, str::FromStr};
+
+use data_encoding::BASE32_NOPAD;
+use iroh::NodeId;
+
+use crate::Error;
+
+const PREFIX: &str = "invite";
+
+/// Copy-pasteable invitation to enroll with a server, in the style of iroh
+/// tickets: `invite` followed by the lowercase base32 encoded server node ID
+/// and invite secret.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InviteTicket {
+    pub server: NodeId,
+    pub secret: String,
+}
+
+impl Display for InviteTicket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut bytes = self.server.as_bytes().to_vec();
+        bytes.extend_from_slice(self.secret.as_bytes());
+
+        let encoded = BASE32_NOPAD.encode(&bytes).to_ascii_lowercase();
+        write!(f, "{PREFIX}{encoded}")
+    }
+}
+
+impl FromStr for InviteTicket {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let encoded = s.strip_prefix(PREFIX).ok_or(Error::InvalidTicketError)?;
+        let bytes = BASE32_NOPAD
+            .decode(encoded.to_ascii_uppercase().as_bytes())
+            .map_err(|_| Error::InvalidTicketError)?;
+
+        if bytes.len() <= 32 {
+            return Err(Error::InvalidTicketError);
+        }
+
+        let (server, secret) = bytes.split_at(32);
+        let server = NodeId::from_bytes(server.try_into().map_err(|_| Error::InvalidTicketError)?)
+            .map_err(|_| Error::InvalidTicketError)?;
+        let secret = String::from_utf8(secret.to_vec()).map_err(|_| Error::InvalidTicketError)?;
+
+        Ok(Self { server, secret })
+    }
+}
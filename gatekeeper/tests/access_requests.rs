@@ -0,0 +1,127 @@
+mod util;
+
+use std::time::Duration;
+
+use gatekeeper::{Arbiter, Cmd, Error, RequestStatus};
+use iroh::SecretKey;
+use util::TestInfra;
+
+#[tokio::test]
+async fn access_requests() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    let mut rng = rand::thread_rng();
+    let admin = SecretKey::generate(&mut rng).public();
+    let engineer = SecretKey::generate(&mut rng).public();
+    let engineer_key = format!("{engineer}");
+
+    arbiter
+        .create_node("admin", &format!("{admin}"), true, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("engineer", &engineer_key, false, None)
+        .await
+        .unwrap();
+
+    let cmd = Cmd::RequestRole {
+        role: "deploy".to_string(),
+        justification: "release".to_string(),
+        ttl: None,
+    };
+    assert!(arbiter.authorize(engineer, &cmd).await.unwrap());
+    assert!(
+        !arbiter
+            .authorize(engineer, &Cmd::ListRequests { all: false })
+            .await
+            .unwrap()
+    );
+
+    let ttl = Some(Duration::from_secs(3600));
+    let deploy = arbiter
+        .request_role(engineer, "deploy", "release", ttl)
+        .await
+        .unwrap();
+    assert_eq!(deploy.status, RequestStatus::Pending);
+    assert_eq!(deploy.ttl, Some(3600));
+
+    let again = arbiter
+        .request_role(engineer, "deploy", "release again", ttl)
+        .await
+        .unwrap();
+    assert_eq!(again.id, deploy.id);
+
+    let admin_role = arbiter
+        .request_role(engineer, "admin", "curious", None)
+        .await
+        .unwrap();
+
+    let pending = arbiter.list_requests(false).await.unwrap();
+    assert_eq!(pending.len(), 2);
+
+    let approved = arbiter
+        .approve(admin, deploy.id, Some("go ahead"))
+        .await
+        .unwrap();
+    assert_eq!(approved.status, RequestStatus::Approved);
+    assert_eq!(approved.reviewed_by, Some(format!("{admin}")));
+    assert!(approved.reviewed.is_some());
+
+    let grants = arbiter.node_roles(&engineer_key, false).await.unwrap();
+    assert_eq!(grants.len(), 1);
+    assert_eq!(grants[0].role, "deploy");
+    assert!(grants[0].expires_at.is_some());
+
+    let denied = arbiter.deny(admin, admin_role.id, None).await.unwrap();
+    assert_eq!(denied.status, RequestStatus::Denied);
+    assert!(!arbiter.has_role(&engineer_key, "admin").await.unwrap());
+
+    let res = arbiter.approve(admin, admin_role.id, None).await;
+    assert!(matches!(res, Err(Error::NoSuchRequestError)));
+
+    assert!(arbiter.list_requests(false).await.unwrap().is_empty());
+    assert_eq!(arbiter.list_requests(true).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn request_ttl() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    let mut rng = rand::thread_rng();
+    let admin = SecretKey::generate(&mut rng).public();
+    let engineer = SecretKey::generate(&mut rng).public();
+    let engineer_key = format!("{engineer}");
+
+    arbiter
+        .create_node("admin", &format!("{admin}"), true, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("engineer", &engineer_key, false, None)
+        .await
+        .unwrap();
+
+    let res = arbiter
+        .request_role(
+            engineer,
+            "deploy",
+            "forever and then some",
+            Some(Duration::MAX),
+        )
+        .await;
+    assert!(matches!(res, Err(Error::InvalidTtlError(_))));
+    assert!(arbiter.list_requests(true).await.unwrap().is_empty());
+
+    let request = arbiter
+        .request_role(engineer, "deploy", "release", None)
+        .await
+        .unwrap();
+    assert_eq!(request.ttl, None);
+
+    arbiter.approve(admin, request.id, None).await.unwrap();
+    let grants = arbiter.node_roles(&engineer_key, false).await.unwrap();
+    assert_eq!(grants.len(), 1);
+    assert!(grants[0].expires_at.is_none());
+}
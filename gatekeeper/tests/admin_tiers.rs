@@ -0,0 +1,135 @@
+mod util;
+
+use gatekeeper::{
+    AdminTier, Arbiter, AuditFilter, Cmd, ImportMode, RoleCheck, SNAPSHOT_VERSION, Snapshot,
+};
+use iroh::SecretKey;
+use util::TestInfra;
+
+fn grant(node: &str, role: &str) -> Cmd {
+    Cmd::GrantRole {
+        node: node.to_string(),
+        role: role.to_string(),
+        resource: None,
+        ttl: None,
+    }
+}
+
+fn create(node: &str, superadmin: bool) -> Cmd {
+    Cmd::CreateNode {
+        name: node.to_string(),
+        node: node.to_string(),
+        superadmin,
+        tenant: None,
+    }
+}
+
+fn delete(node: &str) -> Cmd {
+    Cmd::DeleteNode {
+        node: node.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn admin_tiers() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    let mut rng = rand::thread_rng();
+    let [admin, operator, auditor, checker, plain] =
+        [(); 5].map(|_| SecretKey::generate(&mut rng).public());
+
+    for (name, node, superadmin) in [
+        ("admin", admin, true),
+        ("operator", operator, false),
+        ("auditor", auditor, false),
+        ("checker", checker, false),
+        ("plain", plain, false),
+    ] {
+        arbiter
+            .create_node(name, &format!("{node}"), superadmin, None)
+            .await
+            .unwrap();
+    }
+
+    let node = arbiter
+        .set_admin_tier(&format!("{operator}"), Some(AdminTier::Operator))
+        .await
+        .unwrap();
+    assert_eq!(node.admin_tier, Some(AdminTier::Operator));
+    arbiter
+        .set_admin_tier(&format!("{auditor}"), Some(AdminTier::Auditor))
+        .await
+        .unwrap();
+    arbiter
+        .set_admin_tier(&format!("{checker}"), Some(AdminTier::Checker))
+        .await
+        .unwrap();
+
+    let import = Cmd::Import {
+        snapshot: Snapshot {
+            version: SNAPSHOT_VERSION,
+            nodes: vec![],
+            roles: vec![],
+            role_parents: vec![],
+            grants: vec![],
+            groups: vec![],
+            denies: vec![],
+            labels: vec![],
+            selector_grants: vec![],
+            tenants: vec![],
+        },
+        mode: ImportMode::Merge,
+    };
+
+    let plain_key = format!("{plain}");
+    let auditor_key = format!("{auditor}");
+    let check = Cmd::Check {
+        checks: vec![RoleCheck {
+            node: plain_key.clone(),
+            role: "deploy".to_string(),
+            resource: None,
+        }],
+    };
+    let audit_log = Cmd::AuditLog(AuditFilter {
+        node: None,
+        role: None,
+        caller: None,
+        since: None,
+        until: None,
+        limit: 50,
+        offset: 0,
+    });
+    let cmds = [
+        (check, [true, true, true, true, false]),
+        (
+            Cmd::Nodes { tenant: None },
+            [true, true, true, false, false],
+        ),
+        (audit_log, [true, true, true, false, false]),
+        (Cmd::Export, [true, true, true, false, false]),
+        (
+            grant(&plain_key, "deploy"),
+            [true, true, false, false, false],
+        ),
+        (create("new", false), [true, true, false, false, false]),
+        (create("new", true), [true, false, false, false, false]),
+        (delete(&plain_key), [true, true, false, false, false]),
+        (delete(&auditor_key), [true, false, false, false, false]),
+        (import, [true, false, false, false, false]),
+    ];
+
+    for (cmd, expected) in cmds.iter() {
+        for (caller, allowed) in [admin, operator, auditor, checker, plain]
+            .iter()
+            .zip(expected)
+        {
+            assert_eq!(
+                arbiter.authorize(*caller, cmd).await.unwrap(),
+                *allowed,
+                "{} by {caller}",
+                cmd.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,142 @@
+mod util;
+
+use gatekeeper::{AuditFilter, Client, Error};
+use iroh::{Endpoint, SecretKey, Watcher};
+use util::{ClientServer, TestInfra};
+
+fn filter() -> AuditFilter {
+    AuditFilter {
+        node: None,
+        role: None,
+        caller: None,
+        since: None,
+        until: None,
+        limit: 50,
+        offset: 0,
+    }
+}
+
+#[tokio::test]
+async fn audit_log() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client
+        .bootstrap(token.clone(), "self".to_string(), client_pk)
+        .await;
+    assert!(res.is_ok());
+
+    let mut rng = rand::thread_rng();
+    let other_id = SecretKey::generate(&mut rng).public();
+
+    client
+        .grant_role(client_pk, "foo".to_string(), None, None)
+        .await
+        .unwrap();
+    client
+        .revoke_role(client_pk, "foo".to_string(), None)
+        .await
+        .unwrap();
+    let res = client
+        .grant_role(other_id, "foo".to_string(), None, None)
+        .await;
+    assert!(res.is_err());
+
+    client.roles(None).await.unwrap();
+    client.nodes(None).await.unwrap();
+
+    let entries = client.audit_log(filter()).await.unwrap();
+    let cmds: Vec<&str> = entries.iter().map(|e| e.cmd.as_str()).collect();
+    assert_eq!(
+        cmds,
+        vec!["grant-role", "revoke-role", "grant-role", "bootstrap"]
+    );
+    assert!(entries.iter().all(|e| e.caller == format!("{client_pk}")));
+    assert_eq!(entries[0].outcome, "NoSuchNodeError");
+    assert_eq!(entries[1].outcome, "ok");
+    assert!(!entries[3].args.contains(&token));
+
+    let entries = client
+        .audit_log(AuditFilter {
+            node: Some(format!("{client_pk}")),
+            role: Some("foo".to_string()),
+            ..filter()
+        })
+        .await
+        .unwrap();
+    let cmds: Vec<&str> = entries.iter().map(|e| e.cmd.as_str()).collect();
+    assert_eq!(cmds, vec!["revoke-role", "grant-role"]);
+
+    let entries = client
+        .audit_log(AuditFilter {
+            limit: 2,
+            offset: 2,
+            ..filter()
+        })
+        .await
+        .unwrap();
+    let cmds: Vec<&str> = entries.iter().map(|e| e.cmd.as_str()).collect();
+    assert_eq!(cmds, vec!["grant-role", "bootstrap"]);
+
+    let entries = client
+        .audit_log(AuditFilter {
+            until: Some(0),
+            ..filter()
+        })
+        .await
+        .unwrap();
+    assert!(entries.is_empty());
+
+    let entries = client
+        .audit_log(AuditFilter {
+            since: Some(0),
+            caller: Some(format!("{other_id}")),
+            ..filter()
+        })
+        .await
+        .unwrap();
+    assert!(entries.is_empty());
+}
+
+#[tokio::test]
+async fn unauthorized_calls_not_audited() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    client
+        .bootstrap(token, "self".to_string(), client_pk)
+        .await
+        .unwrap();
+
+    let mut rng = rand::thread_rng();
+    let stranger_sk = SecretKey::generate(&mut rng);
+    let stranger_pk = stranger_sk.public();
+    let endpoint = Endpoint::builder()
+        .discovery_n0()
+        .secret_key(stranger_sk)
+        .bind()
+        .await
+        .unwrap();
+    let addr = client_server
+        .server
+        .endpoint()
+        .node_addr()
+        .initialized()
+        .await;
+    let stranger = Client::with_addr(endpoint, addr);
+
+    let res = stranger
+        .grant_role(stranger_pk, "foo".to_string(), None, None)
+        .await;
+    assert!(matches!(res, Err(Error::UnauthorizedError)));
+
+    let entries = client.audit_log(filter()).await.unwrap();
+    let cmds: Vec<&str> = entries.iter().map(|e| e.cmd.as_str()).collect();
+    assert_eq!(cmds, vec!["bootstrap"]);
+}
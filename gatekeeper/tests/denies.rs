@@ -0,0 +1,111 @@
+mod util;
+
+use gatekeeper::{Arbiter, RoleCheck};
+use util::TestInfra;
+
+#[tokio::test]
+async fn denies_override_grants() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter
+        .create_node("a", "node-a", false, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("b", "node-b", false, None)
+        .await
+        .unwrap();
+    arbiter.add_role_parent("read", "write").await.unwrap();
+    arbiter
+        .grant_role("node-a", "write", None, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-b", "write", None, None)
+        .await
+        .unwrap();
+    arbiter.create_group("prod", None).await.unwrap();
+    arbiter.add_group_member("prod", "node-a").await.unwrap();
+    arbiter
+        .grant_group_role("prod", "prod.write")
+        .await
+        .unwrap();
+
+    arbiter.deny_role("node-a", "prod.write").await.unwrap();
+    arbiter.deny_role("node-a", "read").await.unwrap();
+    arbiter.deny_role("node-a", "read").await.unwrap();
+
+    let effective = arbiter.node_roles("node-a", true).await.unwrap();
+    let roles: Vec<_> = effective.iter().map(|g| g.role.as_str()).collect();
+    assert_eq!(roles, vec!["write"]);
+    assert!(effective.iter().all(|g| !g.deny));
+
+    let direct = arbiter.node_roles("node-a", false).await.unwrap();
+    let direct: Vec<_> = direct.iter().map(|g| (g.role.as_str(), g.deny)).collect();
+    assert_eq!(
+        direct,
+        vec![("prod.write", true), ("read", true), ("write", false)]
+    );
+
+    let checks: Vec<RoleCheck> = ["node-a:read", "node-b:read", "node-a:prod.write"]
+        .iter()
+        .map(|c| c.parse().unwrap())
+        .collect();
+    assert_eq!(
+        arbiter.check(&checks).await.unwrap(),
+        vec![false, true, false]
+    );
+
+    let nodes = arbiter.role_nodes("read", true, None, 50, 0).await.unwrap();
+    let nodes: Vec<_> = nodes.iter().map(|n| n.node.as_str()).collect();
+    assert_eq!(nodes, vec!["node-b"]);
+
+    arbiter.remove_role_deny("node-a", "read").await.unwrap();
+    assert!(arbiter.has_role("node-a", "read").await.unwrap());
+    assert!(!arbiter.has_role("node-a", "prod.write").await.unwrap());
+}
+
+#[tokio::test]
+async fn denies_stop_inheritance() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    for n in ["a", "b"] {
+        arbiter
+            .create_node(n, &format!("node-{n}"), false, None)
+            .await
+            .unwrap();
+        arbiter
+            .grant_role(&format!("node-{n}"), "admin", None, None)
+            .await
+            .unwrap();
+    }
+    arbiter.add_role_parent("write", "admin").await.unwrap();
+    arbiter.add_role_parent("read", "write").await.unwrap();
+    arbiter.add_role_parent("read", "audit").await.unwrap();
+
+    // Denying write also withholds read, which node-a only inherits through it
+    arbiter.deny_role("node-a", "write").await.unwrap();
+
+    let effective = arbiter.node_roles("node-a", true).await.unwrap();
+    let roles: Vec<_> = effective.iter().map(|g| g.role.as_str()).collect();
+    assert_eq!(roles, vec!["admin"]);
+    assert!(!arbiter.has_role("node-a", "read").await.unwrap());
+    assert!(arbiter.has_role("node-b", "read").await.unwrap());
+
+    let nodes = arbiter.role_nodes("read", true, None, 50, 0).await.unwrap();
+    let nodes: Vec<_> = nodes.iter().map(|n| n.node.as_str()).collect();
+    assert_eq!(nodes, vec!["node-b"]);
+
+    // Another path to read that avoids the denied role still grants it
+    arbiter
+        .grant_role("node-a", "audit", None, None)
+        .await
+        .unwrap();
+    assert!(arbiter.has_role("node-a", "read").await.unwrap());
+
+    let nodes = arbiter.role_nodes("read", true, None, 50, 0).await.unwrap();
+    let nodes: Vec<_> = nodes.iter().map(|n| n.node.as_str()).collect();
+    assert_eq!(nodes, vec!["node-a", "node-b"]);
+}
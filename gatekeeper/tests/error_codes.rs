@@ -0,0 +1,55 @@
+mod util;
+
+use gatekeeper::Error;
+use iroh::SecretKey;
+use util::{ClientServer, TestInfra};
+
+#[tokio::test]
+async fn unauthorized() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, false).await;
+    let client = client_server.client;
+
+    let res = client.roles(None).await;
+    assert!(matches!(res, Err(Error::UnauthorizedError)));
+}
+
+#[tokio::test]
+async fn no_such_node() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
+    assert!(res.is_ok());
+
+    let mut rng = rand::thread_rng();
+    let other_id = SecretKey::generate(&mut rng).public();
+
+    let res = client.delete_node(other_id).await;
+    assert!(matches!(res, Err(Error::NoSuchNodeError)));
+
+    let res = client
+        .grant_role(other_id, "foo".to_string(), None, None)
+        .await;
+    assert!(matches!(res, Err(Error::NoSuchNodeError)));
+}
+
+#[tokio::test]
+async fn server_error() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
+    assert!(res.is_ok());
+
+    let res = client
+        .create_node("self".to_string(), client_pk, true, None)
+        .await;
+    assert!(matches!(res, Err(Error::ServerError(_))));
+}
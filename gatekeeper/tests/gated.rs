@@ -0,0 +1,173 @@
+mod util;
+
+use gatekeeper::{AdminTier, Arbiter, Client, Error, Gate, Requirement};
+use iroh::{
+    Endpoint, NodeAddr, SecretKey, Watcher,
+    endpoint::Connection,
+    protocol::{AcceptError, ProtocolHandler, Router},
+};
+use util::{ClientServer, TestInfra};
+
+const ECHO_ALPN: &[u8] = b"gatekeeper/test/echo";
+
+#[derive(Clone, Debug)]
+struct Echo;
+
+impl ProtocolHandler for Echo {
+    async fn accept(&self, connection: Connection) -> Result<(), AcceptError> {
+        let (mut tx, mut rx) = connection.accept_bi().await?;
+        tokio::io::copy(&mut rx, &mut tx).await?;
+        tx.finish()?;
+        connection.closed().await;
+        Ok(())
+    }
+}
+
+async fn echo_router(gate: Gate, requirement: Requirement) -> (Router, NodeAddr) {
+    let mut rng = rand::thread_rng();
+    let endpoint = Endpoint::builder()
+        .discovery_n0()
+        .secret_key(SecretKey::generate(&mut rng))
+        .bind()
+        .await
+        .unwrap();
+
+    let router = Router::builder(endpoint)
+        .accept(ECHO_ALPN, gate.gated(Echo, requirement))
+        .spawn();
+
+    let addr = router.endpoint().node_addr().initialized().await;
+    (router, addr)
+}
+
+async fn echo(sk: SecretKey, addr: NodeAddr) -> bool {
+    let endpoint = Endpoint::builder()
+        .discovery_n0()
+        .secret_key(sk)
+        .bind()
+        .await
+        .unwrap();
+
+    let Ok(conn) = endpoint.connect(addr, ECHO_ALPN).await else {
+        return false;
+    };
+
+    let Ok((mut tx, mut rx)) = conn.open_bi().await else {
+        return false;
+    };
+
+    if tx.write_all(b"hello").await.is_err() || tx.finish().is_err() {
+        return false;
+    }
+
+    let res = matches!(rx.read_to_end(1_000).await, Ok(data) if data == b"hello");
+    conn.close(0u32.into(), b"bye");
+    res
+}
+
+#[tokio::test]
+async fn local_gate() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    let mut rng = rand::thread_rng();
+    let allowed_sk = SecretKey::generate(&mut rng);
+    let denied_sk = SecretKey::generate(&mut rng);
+    let allowed = format!("{}", allowed_sk.public());
+    let denied = format!("{}", denied_sk.public());
+
+    arbiter
+        .create_node("allowed", &allowed, false, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("denied", &denied, false, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role(&allowed, "echo", None, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role(&denied, "other", None, None)
+        .await
+        .unwrap();
+
+    let gate = Gate::Local(arbiter);
+    let (_router, addr) = echo_router(gate, Requirement::Any(vec!["echo".to_string()])).await;
+
+    assert!(echo(allowed_sk, addr.clone()).await);
+    assert!(!echo(denied_sk, addr.clone()).await);
+    assert!(!echo(SecretKey::generate(&mut rng), addr).await);
+}
+
+#[tokio::test]
+async fn remote_gate() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
+    assert!(res.is_ok());
+
+    let mut rng = rand::thread_rng();
+    let allowed_sk = SecretKey::generate(&mut rng);
+    let partial_sk = SecretKey::generate(&mut rng);
+
+    client
+        .create_node("allowed".to_string(), allowed_sk.public(), false, None)
+        .await
+        .unwrap();
+    client
+        .create_node("partial".to_string(), partial_sk.public(), false, None)
+        .await
+        .unwrap();
+
+    for role in ["echo.read", "echo.write"] {
+        client
+            .grant_role(allowed_sk.public(), role.to_string(), None, None)
+            .await
+            .unwrap();
+    }
+    client
+        .grant_role(partial_sk.public(), "echo.read".to_string(), None, None)
+        .await
+        .unwrap();
+
+    // The gating service only needs to check roles
+    let gate_sk = SecretKey::generate(&mut rng);
+    client
+        .create_node("gate".to_string(), gate_sk.public(), false, None)
+        .await
+        .unwrap();
+    client
+        .set_admin_tier(gate_sk.public(), Some(AdminTier::Checker))
+        .await
+        .unwrap();
+
+    let endpoint = Endpoint::builder()
+        .discovery_n0()
+        .secret_key(gate_sk)
+        .bind()
+        .await
+        .unwrap();
+    let server_addr = client_server
+        .server
+        .endpoint()
+        .node_addr()
+        .initialized()
+        .await;
+    let gate_client = Client::with_addr(endpoint, server_addr);
+
+    let res = gate_client.export().await;
+    assert!(matches!(res, Err(Error::UnauthorizedError)));
+
+    let gate = Gate::Remote(gate_client);
+    let requirement = Requirement::All(vec!["echo.read".to_string(), "echo.write".to_string()]);
+    let (_router, addr) = echo_router(gate, requirement).await;
+
+    assert!(echo(allowed_sk, addr.clone()).await);
+    assert!(!echo(partial_sk, addr).await);
+}
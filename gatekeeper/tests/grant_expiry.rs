@@ -0,0 +1,164 @@
+mod util;
+
+use std::time::Duration;
+
+use gatekeeper::{Arbiter, Error, MAX_TTL, parse_ttl};
+use util::{ClientServer, TestInfra};
+
+#[tokio::test]
+async fn grant_expiry() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
+    assert!(res.is_ok());
+
+    client
+        .grant_role(client_pk, "permanent".to_string(), None, None)
+        .await
+        .unwrap();
+    client
+        .grant_role(
+            client_pk,
+            "long".to_string(),
+            None,
+            Some(Duration::from_secs(3600)),
+        )
+        .await
+        .unwrap();
+    client
+        .grant_role(
+            client_pk,
+            "short".to_string(),
+            None,
+            Some(Duration::from_secs(1)),
+        )
+        .await
+        .unwrap();
+
+    let grants = client.node_roles(client_pk, false).await.unwrap();
+    let roles: Vec<&str> = grants.iter().map(|g| g.role.as_str()).collect();
+    assert_eq!(roles, vec!["long", "permanent", "short"]);
+    assert!(grants[0].expires_at.is_some());
+    assert!(grants[1].expires_at.is_none());
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let grants = client.node_roles(client_pk, false).await.unwrap();
+    let roles: Vec<&str> = grants.iter().map(|g| g.role.as_str()).collect();
+    assert_eq!(roles, vec!["long", "permanent"]);
+
+    let res = client
+        .check(vec![(client_pk, "short".to_string(), None)])
+        .await
+        .unwrap();
+    assert_eq!(res, vec![false]);
+}
+
+#[tokio::test]
+async fn inherited_grant_expiry() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter.create_node("n", "node", false, None).await.unwrap();
+    arbiter.add_role_parent("child", "parent").await.unwrap();
+    arbiter
+        .grant_role("node", "parent", None, Some(Duration::from_secs(60)))
+        .await
+        .unwrap();
+
+    let grants = arbiter.node_roles("node", true).await.unwrap();
+    assert_eq!(grants.len(), 2);
+    assert!(grants[0].expires_at.is_some());
+    assert_eq!(grants[0].expires_at, grants[1].expires_at);
+
+    arbiter
+        .grant_role("node", "child", None, None)
+        .await
+        .unwrap();
+
+    let grants = arbiter.node_roles("node", true).await.unwrap();
+    assert_eq!(grants[0].role, "child");
+    assert!(grants[0].expires_at.is_none());
+}
+
+#[tokio::test]
+async fn expired_grant_purge() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter.create_node("n", "node", false, None).await.unwrap();
+    arbiter
+        .grant_role("node", "short", None, Some(Duration::from_secs(1)))
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node", "permanent", None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(arbiter.purge_expired_grants().await.unwrap(), 0);
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    assert_eq!(arbiter.purge_expired_grants().await.unwrap(), 1);
+    assert_eq!(arbiter.purge_expired_grants().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn grant_ttl_too_long() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    client
+        .bootstrap(token, "self".to_string(), client_pk)
+        .await
+        .unwrap();
+
+    for ttl in [MAX_TTL + 1, u64::MAX] {
+        let res = client
+            .grant_role(
+                client_pk,
+                "role".to_string(),
+                None,
+                Some(Duration::from_secs(ttl)),
+            )
+            .await;
+        assert!(matches!(res, Err(Error::InvalidTtlError(_))));
+    }
+
+    client
+        .grant_role(
+            client_pk,
+            "role".to_string(),
+            None,
+            Some(Duration::from_secs(MAX_TTL)),
+        )
+        .await
+        .unwrap();
+    let grants = client.node_roles(client_pk, false).await.unwrap();
+    assert_eq!(grants.len(), 1);
+    assert!(grants[0].expires_at.is_some());
+}
+
+#[test]
+fn ttl_parsing() {
+    assert_eq!(parse_ttl("90s"), Ok(90));
+    assert_eq!(parse_ttl("30m"), Ok(1800));
+    assert_eq!(parse_ttl("8h"), Ok(28800));
+    assert_eq!(parse_ttl("1h30m"), Ok(5400));
+    assert_eq!(parse_ttl("7d"), Ok(604800));
+    assert!(parse_ttl("").is_err());
+    assert!(parse_ttl("8").is_err());
+    assert!(parse_ttl("8x").is_err());
+    assert!(parse_ttl("h").is_err());
+    assert_eq!(parse_ttl("36500d"), Ok(MAX_TTL));
+    assert!(parse_ttl("36501d").is_err());
+    assert!(parse_ttl("100000000d").is_err());
+}
@@ -0,0 +1,105 @@
+mod util;
+
+use std::time::Duration;
+
+use gatekeeper::{Arbiter, Error};
+use util::TestInfra;
+
+#[tokio::test]
+async fn groups() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter
+        .create_node("a", "node-a", false, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("b", "node-b", false, None)
+        .await
+        .unwrap();
+    arbiter.add_role_parent("read", "build").await.unwrap();
+    arbiter
+        .grant_role("node-a", "deploy", None, Some(Duration::from_secs(3600)))
+        .await
+        .unwrap();
+
+    let group = arbiter
+        .create_group("builders", Some("CI agents"))
+        .await
+        .unwrap();
+    assert_eq!(group.name, "builders");
+    assert_eq!(group.description.as_deref(), Some("CI agents"));
+
+    let res = arbiter.create_group("builders", None).await;
+    assert!(matches!(res, Err(Error::GroupExistsError)));
+
+    arbiter
+        .add_group_member("builders", "node-a")
+        .await
+        .unwrap();
+    arbiter
+        .add_group_member("builders", "node-a")
+        .await
+        .unwrap();
+    arbiter.grant_group_role("builders", "build").await.unwrap();
+    arbiter
+        .grant_group_role("builders", "deploy")
+        .await
+        .unwrap();
+
+    let members = arbiter.group_members("builders").await.unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].node, "node-a");
+    assert_eq!(
+        arbiter.group_roles("builders").await.unwrap(),
+        vec!["build", "deploy"]
+    );
+
+    // Direct and group grants of the same role merge, with the group's
+    // permanent grant winning over the direct one's expiry.
+    let effective = arbiter.node_roles("node-a", true).await.unwrap();
+    let roles: Vec<_> = effective.iter().map(|g| g.role.as_str()).collect();
+    assert_eq!(roles, vec!["build", "deploy", "read"]);
+    assert!(effective.iter().all(|g| g.expires_at.is_none()));
+    assert_eq!(arbiter.node_roles("node-a", false).await.unwrap().len(), 1);
+
+    assert!(arbiter.has_role("node-a", "read").await.unwrap());
+    assert!(!arbiter.has_role("node-b", "build").await.unwrap());
+
+    let direct = arbiter
+        .role_nodes("build", false, None, 50, 0)
+        .await
+        .unwrap();
+    assert!(direct.is_empty());
+    let indirect = arbiter.role_nodes("read", true, None, 50, 0).await.unwrap();
+    assert_eq!(indirect.len(), 1);
+
+    let res = arbiter.delete_role("build", false).await;
+    assert!(matches!(res, Err(Error::RoleInUseError)));
+
+    arbiter
+        .revoke_group_role("builders", "build")
+        .await
+        .unwrap();
+    assert!(!arbiter.has_role("node-a", "read").await.unwrap());
+
+    arbiter
+        .remove_group_member("builders", "node-a")
+        .await
+        .unwrap();
+    let effective = arbiter.node_roles("node-a", true).await.unwrap();
+    assert_eq!(effective.len(), 1);
+    assert!(effective[0].expires_at.is_some());
+
+    arbiter
+        .add_group_member("builders", "node-b")
+        .await
+        .unwrap();
+    arbiter.delete_group("builders").await.unwrap();
+    assert!(!arbiter.has_role("node-b", "deploy").await.unwrap());
+    assert!(arbiter.groups(None).await.unwrap().is_empty());
+
+    let res = arbiter.group_members("builders").await;
+    assert!(matches!(res, Err(Error::NoSuchGroupError)));
+}
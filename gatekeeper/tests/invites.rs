@@ -0,0 +1,119 @@
+mod util;
+
+use std::{str::FromStr, time::Duration};
+
+use gatekeeper::{AuditFilter, Client, Error, InviteTicket};
+use iroh::{Endpoint, NodeAddr, SecretKey, Watcher};
+use util::{ClientServer, TestInfra};
+
+async fn connect(sk: SecretKey, addr: NodeAddr) -> Client {
+    let endpoint = Endpoint::builder()
+        .discovery_n0()
+        .secret_key(sk)
+        .bind()
+        .await
+        .unwrap();
+
+    Client::with_addr(endpoint, addr)
+}
+
+#[tokio::test]
+async fn invites() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    client
+        .bootstrap(token, "self".to_string(), client_pk)
+        .await
+        .unwrap();
+
+    let invite = client
+        .create_invite(
+            vec!["deploy".to_string(), "read".to_string()],
+            Duration::from_secs(3600),
+            1,
+        )
+        .await
+        .unwrap();
+    assert_eq!(invite.roles, vec!["deploy", "read"]);
+    assert_eq!(invite.uses_left, 1);
+
+    let ticket = invite.ticket(client.server_id()).unwrap();
+    assert_eq!(
+        InviteTicket::from_str(&format!("{ticket}")).unwrap(),
+        ticket
+    );
+    assert!(InviteTicket::from_str("invitenotbase32!").is_err());
+
+    let addr = client_server
+        .server
+        .endpoint()
+        .node_addr()
+        .initialized()
+        .await;
+
+    let mut rng = rand::thread_rng();
+    let new_sk = SecretKey::generate(&mut rng);
+    let new_client = connect(new_sk.clone(), addr.clone()).await;
+
+    let node = new_client.redeem(&ticket, "new".to_string()).await.unwrap();
+    assert_eq!(node.node, format!("{}", new_sk.public()));
+    assert!(!node.superadmin);
+
+    let whoami = new_client.whoami().await.unwrap();
+    let roles: Vec<_> = whoami.roles.iter().map(|g| g.role.as_str()).collect();
+    assert_eq!(roles, vec!["deploy", "read"]);
+
+    let late_client = connect(SecretKey::generate(&mut rng), addr).await;
+    let res = late_client.redeem(&ticket, "late".to_string()).await;
+    assert!(matches!(res, Err(Error::InvalidTicketError)));
+
+    let invites = client.invites().await.unwrap();
+    assert_eq!(invites.len(), 1);
+    assert_eq!(invites[0].uses_left, 0);
+    assert!(invites[0].secret.is_none());
+
+    let entries = client
+        .audit_log(AuditFilter {
+            node: None,
+            role: None,
+            caller: Some(format!("{}", new_sk.public())),
+            since: None,
+            until: None,
+            limit: 50,
+            offset: 0,
+        })
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].cmd, "redeem");
+    assert!(!entries[0].args.contains(&ticket.secret));
+
+    client.delete_invite(invite.id).await.unwrap();
+    assert!(client.invites().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn invite_ttl_too_long() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    client
+        .bootstrap(token, "self".to_string(), client_pk)
+        .await
+        .unwrap();
+
+    let res = client
+        .create_invite(vec!["deploy".to_string()], Duration::from_secs(u64::MAX), 1)
+        .await;
+    assert!(matches!(res, Err(Error::InvalidTtlError(_))));
+
+    assert!(client.invites().await.unwrap().is_empty());
+    assert!(client.roles(None).await.unwrap().is_empty());
+}
@@ -0,0 +1,83 @@
+mod util;
+
+use gatekeeper::{Arbiter, Cmd, Error};
+use iroh::SecretKey;
+use util::TestInfra;
+
+#[tokio::test]
+async fn key_rotation() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    let mut rng = rand::thread_rng();
+    let admin = SecretKey::generate(&mut rng).public();
+    let (old, new) = (
+        format!("{}", SecretKey::generate(&mut rng).public()),
+        format!("{}", SecretKey::generate(&mut rng).public()),
+    );
+
+    arbiter
+        .create_node("admin", &format!("{admin}"), true, None)
+        .await
+        .unwrap();
+    arbiter.create_node("a", &old, false, None).await.unwrap();
+    arbiter
+        .create_node("b", "node-b", false, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role(&old, "deploy", None, None)
+        .await
+        .unwrap();
+
+    let res = arbiter.rotate_node_key(admin, &old, "node-b").await;
+    assert!(matches!(res, Err(Error::NodeExistsError)));
+
+    let node = arbiter.rotate_node_key(admin, &old, &new).await.unwrap();
+    assert_eq!(node.name, "a");
+    assert_eq!(node.node, new);
+
+    assert!(arbiter.has_role(&new, "deploy").await.unwrap());
+    assert!(!arbiter.has_role(&old, "deploy").await.unwrap());
+
+    for key in [&old, &new] {
+        let history = arbiter.node_key_history(key).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].old_node, old);
+        assert_eq!(history[0].new_node, new);
+        assert_eq!(history[0].rotated_by, format!("{admin}"));
+    }
+
+    let res = arbiter.node_key_history("node-c").await;
+    assert!(matches!(res, Err(Error::NoSuchNodeError)));
+}
+
+fn rotate(node: &str, new_node: &str) -> Cmd {
+    Cmd::RotateNodeKey {
+        node: node.to_string(),
+        new_node: new_node.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn self_service_rotation() {
+    let infra = TestInfra::new().await;
+
+    let mut rng = rand::thread_rng();
+    let node = SecretKey::generate(&mut rng).public();
+    let other = SecretKey::generate(&mut rng).public();
+    let (key, other_key) = (format!("{node}"), format!("{other}"));
+
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+    arbiter.create_node("a", &key, false, None).await.unwrap();
+
+    let own = rotate(&key, "new");
+    assert!(!arbiter.authorize(node, &own).await.unwrap());
+
+    let arbiter = arbiter.with_self_service_rotation(true);
+    assert!(arbiter.authorize(node, &own).await.unwrap());
+
+    let others = rotate(&other_key, "new");
+    assert!(!arbiter.authorize(node, &others).await.unwrap());
+    assert!(!arbiter.authorize(other, &others).await.unwrap());
+}
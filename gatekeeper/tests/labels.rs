@@ -0,0 +1,153 @@
+mod util;
+
+use gatekeeper::{Arbiter, Error, Label, LabelSelector};
+use util::TestInfra;
+
+fn label(s: &str) -> Label {
+    s.parse().unwrap()
+}
+
+fn selector(s: &str) -> LabelSelector {
+    s.parse().unwrap()
+}
+
+#[tokio::test]
+async fn selector_grants() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter
+        .create_node("a", "node-a", false, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("b", "node-b", false, None)
+        .await
+        .unwrap();
+    arbiter.add_role_parent("read", "deploy").await.unwrap();
+
+    arbiter
+        .set_node_label("node-a", &label("env=prod"))
+        .await
+        .unwrap();
+    arbiter
+        .set_node_label("node-a", &label("team=infra"))
+        .await
+        .unwrap();
+    arbiter
+        .set_node_label("node-b", &label("env=prod"))
+        .await
+        .unwrap();
+
+    // Selectors are stored in canonical form, whatever order they're written in
+    arbiter
+        .grant_selector_role(&selector("team=infra,env=prod"), "deploy")
+        .await
+        .unwrap();
+    arbiter
+        .grant_selector_role(&selector("env=prod,team=infra"), "deploy")
+        .await
+        .unwrap();
+    let grants = arbiter.selector_grants().await.unwrap();
+    assert_eq!(grants.len(), 1);
+    assert_eq!(grants[0].selector, "env=prod,team=infra");
+    assert_eq!(grants[0].role, "deploy");
+
+    assert!(arbiter.has_role("node-a", "deploy").await.unwrap());
+    assert!(arbiter.has_role("node-a", "read").await.unwrap());
+    assert!(!arbiter.has_role("node-b", "deploy").await.unwrap());
+    assert!(
+        arbiter
+            .node_roles("node-a", false)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // Labeling a node is enough for it to pick up the role, and relabeling it
+    // takes the role away again
+    arbiter
+        .set_node_label("node-b", &label("team=infra"))
+        .await
+        .unwrap();
+    assert!(arbiter.has_role("node-b", "deploy").await.unwrap());
+    arbiter
+        .set_node_label("node-b", &label("env=dev"))
+        .await
+        .unwrap();
+    assert!(!arbiter.has_role("node-b", "deploy").await.unwrap());
+    assert_eq!(
+        arbiter.node_labels("node-b").await.unwrap(),
+        vec![label("env=dev"), label("team=infra")]
+    );
+
+    let nodes = arbiter
+        .role_nodes("read", true, None, 100, 0)
+        .await
+        .unwrap();
+    let nodes: Vec<_> = nodes.iter().map(|n| n.node.as_str()).collect();
+    assert_eq!(nodes, vec!["node-a"]);
+    assert!(
+        arbiter
+            .role_nodes("deploy", false, None, 100, 0)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    assert!(matches!(
+        arbiter.delete_role("deploy", false).await,
+        Err(Error::RoleInUseError)
+    ));
+
+    arbiter.deny_role("node-a", "deploy").await.unwrap();
+    assert!(!arbiter.has_role("node-a", "deploy").await.unwrap());
+    arbiter.remove_role_deny("node-a", "deploy").await.unwrap();
+
+    arbiter.remove_node_label("node-a", "team").await.unwrap();
+    assert!(!arbiter.has_role("node-a", "deploy").await.unwrap());
+    arbiter
+        .set_node_label("node-a", &label("team=infra"))
+        .await
+        .unwrap();
+
+    arbiter
+        .revoke_selector_role(&selector("env=prod,team=infra"), "deploy")
+        .await
+        .unwrap();
+    assert!(arbiter.selector_grants().await.unwrap().is_empty());
+    assert!(!arbiter.has_role("node-a", "deploy").await.unwrap());
+}
+
+#[tokio::test]
+async fn invalid_labels() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+    arbiter
+        .create_node("a", "node-a", false, None)
+        .await
+        .unwrap();
+
+    for s in ["env", "=prod", "env=", "env=a=b"] {
+        assert!(s.parse::<Label>().is_err(), "{s}");
+    }
+    for s in ["", "env=prod,", "env=prod,env=dev"] {
+        assert!(s.parse::<LabelSelector>().is_err(), "{s}");
+    }
+
+    let res = arbiter
+        .set_node_label(
+            "node-a",
+            &Label {
+                key: "env,team".to_string(),
+                value: "prod".to_string(),
+            },
+        )
+        .await;
+    assert!(matches!(res, Err(Error::InvalidLabelError(_))));
+
+    let res = arbiter
+        .grant_selector_role(&LabelSelector { labels: vec![] }, "deploy")
+        .await;
+    assert!(matches!(res, Err(Error::InvalidLabelError(_))));
+}
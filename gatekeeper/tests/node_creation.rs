@@ -1,3 +1,5 @@
+#![allow(clippy::redundant_pattern_matching, clippy::bool_assert_comparison)]
+
 mod util;
 
 use gatekeeper::Client;
@@ -11,9 +13,8 @@ async fn non_privileged_node_creation() {
     let client = client_server.client;
 
     let client_pk = client_server.client_sk.public();
-    let res = client
-        .create_node("self".to_string(), client_pk, true)
-        .await;
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
     assert!(matches!(res, Ok(_)));
 
     let mut rng = rand::thread_rng();
@@ -21,7 +22,7 @@ async fn non_privileged_node_creation() {
     let other_id = other_sk.public();
 
     let other = client
-        .create_node("other".to_string(), other_id, false)
+        .create_node("other".to_string(), other_id, false, None)
         .await
         .unwrap();
 
@@ -45,7 +46,7 @@ async fn non_privileged_node_creation() {
 
     let other_client = Client::with_addr(other_client_endpoint, server_addr);
 
-    let res = other_client.roles().await;
+    let res = other_client.roles(None).await;
     assert!(matches!(res, Err(_)));
 }
 
@@ -56,9 +57,8 @@ async fn privileged_node_creation() {
     let client = client_server.client;
 
     let client_pk = client_server.client_sk.public();
-    let res = client
-        .create_node("self".to_string(), client_pk, true)
-        .await;
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
     assert!(matches!(res, Ok(_)));
 
     let mut rng = rand::thread_rng();
@@ -66,7 +66,7 @@ async fn privileged_node_creation() {
     let other_id = other_sk.public();
 
     let other = client
-        .create_node("other".to_string(), other_id, true)
+        .create_node("other".to_string(), other_id, true, None)
         .await
         .unwrap();
 
@@ -90,6 +90,6 @@ async fn privileged_node_creation() {
 
     let other_client = Client::with_addr(other_client_endpoint, server_addr);
 
-    let res = other_client.roles().await;
+    let res = other_client.roles(None).await;
     assert!(matches!(res, Ok(_)));
 }
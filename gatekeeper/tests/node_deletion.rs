@@ -1,3 +1,5 @@
+#![allow(clippy::redundant_pattern_matching)]
+
 mod util;
 
 use iroh::SecretKey;
@@ -10,9 +12,8 @@ async fn node_deletion() {
     let client = client_server.client;
 
     let client_pk = client_server.client_sk.public();
-    let res = client
-        .create_node("self".to_string(), client_pk, true)
-        .await;
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
     assert!(matches!(res, Ok(_)));
 
     let mut rng = rand::thread_rng();
@@ -20,11 +21,11 @@ async fn node_deletion() {
     let other_id = other_sk.public();
 
     client
-        .create_node("other".to_string(), other_id, false)
+        .create_node("other".to_string(), other_id, false, None)
         .await
         .unwrap();
 
-    let nodes = client.nodes().await.unwrap();
+    let nodes = client.nodes(None).await.unwrap();
     assert_eq!(nodes.len(), 2);
 
     let mut node_ids: Vec<String> = nodes.into_iter().map(|n| n.node).collect();
@@ -38,7 +39,7 @@ async fn node_deletion() {
 
     client.delete_node(other_id).await.unwrap();
 
-    let nodes = client.nodes().await.unwrap();
+    let nodes = client.nodes(None).await.unwrap();
     let node_ids: Vec<String> = nodes.into_iter().map(|n| n.node).collect();
     let expected_node_ids = vec![format!("{}", client_server.client_sk.public())];
     assert_eq!(node_ids, expected_node_ids);
@@ -0,0 +1,52 @@
+mod util;
+
+use gatekeeper::{Arbiter, Error};
+use util::TestInfra;
+
+#[tokio::test]
+async fn node_update() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter
+        .create_node("a", "node-a", false, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("b", "node-b", false, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-a", "deploy", None, None)
+        .await
+        .unwrap();
+
+    let node = arbiter
+        .update_node("node-a", Some("ci"), Some(true), Some("CI runner"))
+        .await
+        .unwrap();
+    assert_eq!(node.name, "ci");
+    assert!(node.superadmin);
+    assert_eq!(node.description.as_deref(), Some("CI runner"));
+    assert!(arbiter.has_role("node-a", "deploy").await.unwrap());
+
+    let node = arbiter
+        .update_node("node-a", None, Some(false), None)
+        .await
+        .unwrap();
+    assert_eq!(node.name, "ci");
+    assert!(!node.superadmin);
+    assert_eq!(node.description.as_deref(), Some("CI runner"));
+
+    let node = arbiter
+        .update_node("node-a", Some("ci"), None, Some(""))
+        .await
+        .unwrap();
+    assert_eq!(node.description, None);
+
+    let res = arbiter.update_node("node-a", Some("b"), None, None).await;
+    assert!(matches!(res, Err(Error::NodeExistsError)));
+
+    let res = arbiter.update_node("node-c", Some("c"), None, None).await;
+    assert!(matches!(res, Err(Error::NoSuchNodeError)));
+}
@@ -0,0 +1,109 @@
+mod util;
+
+use gatekeeper::{Arbiter, RoleCheck};
+use util::TestInfra;
+
+#[tokio::test]
+async fn resource_grants() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter
+        .create_node("a", "node-a", false, None)
+        .await
+        .unwrap();
+    arbiter.add_role_parent("read", "write").await.unwrap();
+    arbiter
+        .grant_role("node-a", "write", Some("bucket/a"), None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-a", "write", Some("bucket/*/logs"), None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-a", "admin", Some("org/**/billing"), None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-a", "list", None, None)
+        .await
+        .unwrap();
+
+    let cases = [
+        ("write", "bucket/a", true),
+        ("write", "bucket/a/objects/1", true),
+        ("write", "bucket/b", false),
+        ("write", "bucket/ab", false),
+        ("write", "bucket/b/logs", true),
+        ("write", "bucket/b/logs/today", true),
+        ("write", "bucket/b/metrics", false),
+        ("read", "bucket/a", true),
+        ("read", "bucket/b", false),
+        ("admin", "org/billing", true),
+        ("admin", "org/eu/west/billing", true),
+        ("admin", "org/eu/payroll", false),
+        ("list", "anything/at/all", true),
+    ];
+    for (role, resource, held) in cases {
+        assert_eq!(
+            arbiter.has_role_on("node-a", role, resource).await.unwrap(),
+            held,
+            "{role} on {resource}"
+        );
+    }
+
+    // Scoped grants don't amount to holding the role everywhere
+    assert!(!arbiter.has_role("node-a", "write").await.unwrap());
+    assert!(arbiter.has_role("node-a", "list").await.unwrap());
+
+    let checks: Vec<RoleCheck> = ["node-a:write@bucket/a", "node-a:write", "node-a:list@x"]
+        .iter()
+        .map(|c| c.parse().unwrap())
+        .collect();
+    assert_eq!(
+        arbiter.check(&checks).await.unwrap(),
+        vec![true, false, true]
+    );
+    assert!("node-a:write@".parse::<RoleCheck>().is_err());
+
+    let direct = arbiter.node_roles("node-a", false).await.unwrap();
+    let direct: Vec<_> = direct
+        .iter()
+        .map(|g| (g.role.as_str(), g.resource.as_deref()))
+        .collect();
+    assert_eq!(
+        direct,
+        vec![
+            ("admin", Some("org/**/billing")),
+            ("list", None),
+            ("write", Some("bucket/*/logs")),
+            ("write", Some("bucket/a")),
+        ]
+    );
+
+    arbiter
+        .revoke_role("node-a", "write", Some("bucket/a"))
+        .await
+        .unwrap();
+    assert!(
+        !arbiter
+            .has_role_on("node-a", "write", "bucket/a")
+            .await
+            .unwrap()
+    );
+    assert!(
+        arbiter
+            .has_role_on("node-a", "write", "bucket/a/logs")
+            .await
+            .unwrap()
+    );
+
+    arbiter.deny_role("node-a", "write").await.unwrap();
+    assert!(
+        !arbiter
+            .has_role_on("node-a", "write", "bucket/a/logs")
+            .await
+            .unwrap()
+    );
+}
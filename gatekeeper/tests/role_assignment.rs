@@ -1,3 +1,5 @@
+#![allow(clippy::redundant_pattern_matching)]
+
 mod util;
 
 use util::{ClientServer, TestInfra};
@@ -9,47 +11,58 @@ async fn role_assignment() {
     let client = client_server.client;
 
     let client_pk = client_server.client_sk.public();
-    let res = client
-        .create_node("self".to_string(), client_pk, true)
-        .await;
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
     assert!(matches!(res, Ok(_)));
 
     client
-        .grant_role(client_pk, "foo".to_string())
+        .grant_role(client_pk, "foo".to_string(), None, None)
         .await
         .unwrap();
     client
-        .grant_role(client_pk, "bar".to_string())
+        .grant_role(client_pk, "bar".to_string(), None, None)
         .await
         .unwrap();
     client
-        .grant_role(client_pk, "baz".to_string())
+        .grant_role(client_pk, "baz".to_string(), None, None)
         .await
         .unwrap();
 
-    let roles = client.roles().await.unwrap();
+    let roles = client.roles(None).await.unwrap();
     assert_eq!(
         roles,
         vec!["bar".to_string(), "baz".to_string(), "foo".to_string()]
     );
 
-    let node_roles = client.node_roles(client_pk).await.unwrap();
+    let node_roles: Vec<String> = client
+        .node_roles(client_pk, false)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|g| g.role)
+        .collect();
     assert_eq!(
         node_roles,
         vec!["bar".to_string(), "baz".to_string(), "foo".to_string()]
     );
 
     client
-        .revoke_role(client_pk, "baz".to_string())
+        .revoke_role(client_pk, "baz".to_string(), None)
         .await
         .unwrap();
 
-    let roles = client.roles().await.unwrap();
+    let roles = client.roles(None).await.unwrap();
     assert_eq!(
         roles,
         vec!["bar".to_string(), "baz".to_string(), "foo".to_string()]
     );
 
-    let node_roles = client.node_roles(client_pk).await.unwrap();
+    let node_roles: Vec<String> = client
+        .node_roles(client_pk, false)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|g| g.role)
+        .collect();
     assert_eq!(node_roles, vec!["bar".to_string(), "foo".to_string()]);
 }
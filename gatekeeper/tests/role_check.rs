@@ -0,0 +1,47 @@
+mod util;
+
+use iroh::SecretKey;
+use util::{ClientServer, TestInfra};
+
+#[tokio::test]
+async fn role_check() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
+    assert!(res.is_ok());
+
+    let mut rng = rand::thread_rng();
+    let other_id = SecretKey::generate(&mut rng).public();
+    let unknown_id = SecretKey::generate(&mut rng).public();
+
+    client
+        .create_node("other".to_string(), other_id, false, None)
+        .await
+        .unwrap();
+
+    client
+        .grant_role(client_pk, "foo".to_string(), None, None)
+        .await
+        .unwrap();
+    client
+        .grant_role(other_id, "bar".to_string(), None, None)
+        .await
+        .unwrap();
+
+    let res = client
+        .check(vec![
+            (client_pk, "foo".to_string(), None),
+            (client_pk, "bar".to_string(), None),
+            (other_id, "bar".to_string(), None),
+            (other_id, "foo".to_string(), None),
+            (unknown_id, "foo".to_string(), None),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(res, vec![true, false, true, false, false]);
+}
@@ -0,0 +1,115 @@
+mod util;
+
+use gatekeeper::Error;
+use util::{ClientServer, TestInfra};
+
+#[tokio::test]
+async fn role_hierarchy() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
+    assert!(res.is_ok());
+
+    client
+        .add_role_parent("storage.write".to_string(), "storage.admin".to_string())
+        .await
+        .unwrap();
+    client
+        .add_role_parent("storage.read".to_string(), "storage.write".to_string())
+        .await
+        .unwrap();
+
+    let parents = client
+        .role_parents("storage.read".to_string())
+        .await
+        .unwrap();
+    assert_eq!(parents, vec!["storage.write".to_string()]);
+
+    client
+        .grant_role(client_pk, "storage.admin".to_string(), None, None)
+        .await
+        .unwrap();
+
+    let direct: Vec<String> = client
+        .node_roles(client_pk, false)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|g| g.role)
+        .collect();
+    assert_eq!(direct, vec!["storage.admin".to_string()]);
+
+    let effective: Vec<String> = client
+        .node_roles(client_pk, true)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|g| g.role)
+        .collect();
+    assert_eq!(
+        effective,
+        vec![
+            "storage.admin".to_string(),
+            "storage.read".to_string(),
+            "storage.write".to_string()
+        ]
+    );
+
+    let res = client
+        .check(vec![(client_pk, "storage.read".to_string(), None)])
+        .await
+        .unwrap();
+    assert_eq!(res, vec![true]);
+
+    client
+        .remove_role_parent("storage.read".to_string(), "storage.write".to_string())
+        .await
+        .unwrap();
+
+    let effective: Vec<String> = client
+        .node_roles(client_pk, true)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|g| g.role)
+        .collect();
+    assert_eq!(
+        effective,
+        vec!["storage.admin".to_string(), "storage.write".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn role_hierarchy_cycle() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
+    assert!(res.is_ok());
+
+    client
+        .add_role_parent("b".to_string(), "a".to_string())
+        .await
+        .unwrap();
+    client
+        .add_role_parent("c".to_string(), "b".to_string())
+        .await
+        .unwrap();
+
+    let res = client
+        .add_role_parent("a".to_string(), "c".to_string())
+        .await;
+    assert!(matches!(res, Err(Error::RoleCycleError)));
+
+    let res = client
+        .add_role_parent("a".to_string(), "a".to_string())
+        .await;
+    assert!(matches!(res, Err(Error::RoleCycleError)));
+}
@@ -0,0 +1,96 @@
+mod util;
+
+use gatekeeper::{Arbiter, Error};
+use util::TestInfra;
+
+#[tokio::test]
+async fn role_lifecycle() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    let role = arbiter
+        .create_role("deploy", Some("Ship to production"))
+        .await
+        .unwrap();
+    assert_eq!(role.role, "deploy");
+    assert_eq!(role.description.as_deref(), Some("Ship to production"));
+
+    let res = arbiter.create_role("deploy", None).await;
+    assert!(matches!(res, Err(Error::RoleExistsError)));
+
+    let role = arbiter.describe_role("deploy", None).await.unwrap();
+    assert_eq!(role.description, None);
+    assert_eq!(arbiter.show_role("deploy").await.unwrap().description, None);
+
+    let res = arbiter.show_role("missing").await;
+    assert!(matches!(res, Err(Error::NoSuchRoleError)));
+
+    arbiter
+        .create_node("a", "node-a", false, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-a", "deploy", None, None)
+        .await
+        .unwrap();
+    arbiter.create_role("ops", None).await.unwrap();
+
+    let res = arbiter.rename_role("deploy", "ops").await;
+    assert!(matches!(res, Err(Error::RoleExistsError)));
+
+    arbiter.rename_role("deploy", "release").await.unwrap();
+    assert!(arbiter.has_role("node-a", "release").await.unwrap());
+    assert!(!arbiter.has_role("node-a", "deploy").await.unwrap());
+
+    let res = arbiter.delete_role("release", false).await;
+    assert!(matches!(res, Err(Error::RoleInUseError)));
+
+    arbiter.delete_role("release", true).await.unwrap();
+    assert!(!arbiter.has_role("node-a", "release").await.unwrap());
+    assert_eq!(arbiter.roles(None).await.unwrap(), vec!["ops"]);
+
+    arbiter.delete_role("ops", false).await.unwrap();
+    assert!(arbiter.roles(None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn revoke_does_not_create_roles() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter
+        .create_node("a", "node-a", false, None)
+        .await
+        .unwrap();
+    arbiter.revoke_role("node-a", "typo", None).await.unwrap();
+
+    assert!(arbiter.roles(None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn strict_roles() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false)
+        .await
+        .unwrap()
+        .with_strict_roles(true);
+
+    arbiter
+        .create_node("a", "node-a", false, None)
+        .await
+        .unwrap();
+
+    let res = arbiter.grant_role("node-a", "deploy", None, None).await;
+    assert!(matches!(res, Err(Error::NoSuchRoleError)));
+
+    let res = arbiter.add_role_parent("deploy", "admin").await;
+    assert!(matches!(res, Err(Error::NoSuchRoleError)));
+    assert!(arbiter.roles(None).await.unwrap().is_empty());
+
+    arbiter.create_role("deploy", None).await.unwrap();
+    arbiter
+        .grant_role("node-a", "deploy", None, None)
+        .await
+        .unwrap();
+    assert!(arbiter.has_role("node-a", "deploy").await.unwrap());
+}
@@ -0,0 +1,82 @@
+mod util;
+
+use std::time::Duration;
+
+use gatekeeper::Arbiter;
+use util::TestInfra;
+
+async fn names(
+    arbiter: &Arbiter,
+    role: &str,
+    indirect: bool,
+    limit: u32,
+    offset: u32,
+) -> Vec<String> {
+    arbiter
+        .role_nodes(role, indirect, None, limit, offset)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|n| n.name)
+        .collect()
+}
+
+#[tokio::test]
+async fn role_nodes() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    for n in ["a", "b", "c", "d", "e"] {
+        arbiter
+            .create_node(n, &format!("node-{n}"), false, None)
+            .await
+            .unwrap();
+    }
+
+    arbiter
+        .add_role_parent("deploy", "deploy.admin")
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-a", "deploy", None, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-b", "deploy", None, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-c", "deploy.admin", None, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-d", "other", None, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-e", "deploy", None, Some(Duration::from_secs(1)))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        names(&arbiter, "deploy", false, 50, 0).await,
+        vec!["a", "b", "e"]
+    );
+    assert_eq!(
+        names(&arbiter, "deploy", true, 50, 0).await,
+        vec!["a", "b", "c", "e"]
+    );
+    assert_eq!(names(&arbiter, "deploy", true, 2, 1).await, vec!["b", "c"]);
+    assert_eq!(
+        names(&arbiter, "deploy.admin", true, 50, 0).await,
+        vec!["c"]
+    );
+    assert!(names(&arbiter, "missing", true, 50, 0).await.is_empty());
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    assert_eq!(
+        names(&arbiter, "deploy", false, 50, 0).await,
+        vec!["a", "b"]
+    );
+}
@@ -1,5 +1,8 @@
+#![allow(clippy::redundant_pattern_matching)]
+
 mod util;
 
+use gatekeeper::Error;
 use util::{ClientServer, TestInfra};
 
 #[tokio::test]
@@ -11,12 +14,28 @@ async fn with_remote_setup() {
     let client_pk = client_server.client_sk.public();
 
     let res = client
-        .create_node("self".to_string(), client_pk, true)
+        .create_node("self".to_string(), client_pk, true, None)
         .await;
-    assert!(matches!(res, Ok(_)));
+    assert!(matches!(res, Err(_)));
 
-    let res = client.roles().await;
+    let res = client
+        .bootstrap("wrong".to_string(), "self".to_string(), client_pk)
+        .await;
+    assert!(matches!(res, Err(Error::UnauthorizedError)));
+
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client
+        .bootstrap(token.clone(), "self".to_string(), client_pk)
+        .await;
+    assert!(res.unwrap().superadmin);
+
+    let res = client.roles(None).await;
     assert!(matches!(res, Ok(_)));
+
+    let res = client
+        .bootstrap(token, "again".to_string(), client_pk)
+        .await;
+    assert!(matches!(res, Err(Error::UnauthorizedError)));
 }
 
 #[tokio::test]
@@ -25,13 +44,15 @@ async fn without_remote_setup() {
     let client_server = ClientServer::new(infra, false).await;
     let client = client_server.client;
 
+    assert!(client_server.bootstrap_token.is_none());
+
     let client_pk = client_server.client_sk.public();
 
     let res = client
-        .create_node("self".to_string(), client_pk, true)
+        .create_node("self".to_string(), client_pk, true, None)
         .await;
     assert!(matches!(res, Err(_)));
 
-    let res = client.roles().await;
+    let res = client.roles(None).await;
     assert!(matches!(res, Err(_)));
 }
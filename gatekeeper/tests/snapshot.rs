@@ -0,0 +1,200 @@
+mod util;
+
+use std::time::Duration;
+
+use gatekeeper::{Arbiter, Error, ImportMode, SnapshotGrant};
+use util::{ClientServer, TestInfra};
+
+async fn populated() -> (TestInfra, Arbiter) {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter
+        .create_node("a", "node-a", true, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("b", "node-b", false, None)
+        .await
+        .unwrap();
+    arbiter.add_role_parent("read", "write").await.unwrap();
+    arbiter
+        .grant_role("node-a", "write", None, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role("node-b", "read", None, Some(Duration::from_secs(3600)))
+        .await
+        .unwrap();
+
+    (infra, arbiter)
+}
+
+#[tokio::test]
+async fn export_import_replace() {
+    let (_infra, source) = populated().await;
+    source.create_group("builders", None).await.unwrap();
+    source.add_group_member("builders", "node-b").await.unwrap();
+    source.grant_group_role("builders", "write").await.unwrap();
+    source.deny_role("node-a", "read").await.unwrap();
+    source
+        .set_node_label("node-b", &"env=prod".parse().unwrap())
+        .await
+        .unwrap();
+    source
+        .grant_selector_role(&"env=prod".parse().unwrap(), "write")
+        .await
+        .unwrap();
+    let snapshot = source.export().await.unwrap();
+
+    assert_eq!(snapshot.nodes.len(), 2);
+    assert_eq!(snapshot.roles.len(), 2);
+    assert_eq!(snapshot.role_parents.len(), 1);
+    assert_eq!(snapshot.grants.len(), 2);
+    assert!(snapshot.grants.iter().all(|g| g.created.is_some()));
+    assert_eq!(snapshot.groups.len(), 1);
+    assert_eq!(snapshot.groups[0].members, vec!["node-b"]);
+    assert_eq!(snapshot.groups[0].roles, vec!["write"]);
+    assert_eq!(snapshot.denies.len(), 1);
+    assert_eq!(snapshot.labels.len(), 1);
+    assert_eq!(snapshot.selector_grants.len(), 1);
+
+    let infra = TestInfra::new().await;
+    let target = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+    target
+        .create_node("c", "node-c", false, None)
+        .await
+        .unwrap();
+    target
+        .grant_role("node-c", "other", None, None)
+        .await
+        .unwrap();
+
+    target.import(&snapshot, ImportMode::Replace).await.unwrap();
+
+    assert_eq!(target.export().await.unwrap(), snapshot);
+}
+
+#[tokio::test]
+async fn export_import_merge() {
+    let (_infra, source) = populated().await;
+    let snapshot = source.export().await.unwrap();
+
+    let infra = TestInfra::new().await;
+    let target = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+    target
+        .create_node("c", "node-c", false, None)
+        .await
+        .unwrap();
+    target
+        .create_node("old-b", "node-b", true, None)
+        .await
+        .unwrap();
+    target
+        .grant_role("node-c", "other", None, None)
+        .await
+        .unwrap();
+
+    target.import(&snapshot, ImportMode::Merge).await.unwrap();
+
+    let nodes = target.nodes(None).await.unwrap();
+    let nodes: Vec<(&str, bool)> = nodes
+        .iter()
+        .map(|n| (n.name.as_str(), n.superadmin))
+        .collect();
+    assert_eq!(nodes, vec![("a", true), ("b", false), ("c", false)]);
+
+    let roles = target.roles(None).await.unwrap();
+    assert_eq!(roles, vec!["other", "read", "write"]);
+
+    let roles: Vec<String> = target
+        .node_roles("node-a", true)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|g| g.role)
+        .collect();
+    assert_eq!(roles, vec!["read", "write"]);
+}
+
+#[tokio::test]
+async fn import_is_atomic() {
+    let (_infra, source) = populated().await;
+    let mut snapshot = source.export().await.unwrap();
+    snapshot.grants.push(SnapshotGrant {
+        node: "node-missing".to_string(),
+        role: "read".to_string(),
+        expires_at: None,
+        created: None,
+        resource: None,
+    });
+
+    let infra = TestInfra::new().await;
+    let target = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+    target
+        .create_node("c", "node-c", false, None)
+        .await
+        .unwrap();
+
+    let res = target.import(&snapshot, ImportMode::Replace).await;
+    assert!(matches!(res, Err(Error::InvalidSnapshotError(_))));
+
+    let nodes = target.nodes(None).await.unwrap();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].name, "c");
+
+    snapshot.grants.pop();
+    snapshot.version += 1;
+
+    let res = target.import(&snapshot, ImportMode::Replace).await;
+    assert!(matches!(res, Err(Error::InvalidSnapshotError(_))));
+}
+
+#[tokio::test]
+async fn remote_export_import() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    let res = client.bootstrap(token, "self".to_string(), client_pk).await;
+    assert!(res.is_ok());
+
+    client
+        .grant_role(client_pk, "foo".to_string(), None, None)
+        .await
+        .unwrap();
+
+    let snapshot = client.export().await.unwrap();
+    assert_eq!(snapshot.nodes.len(), 1);
+    assert_eq!(snapshot.grants.len(), 1);
+
+    client
+        .revoke_role(client_pk, "foo".to_string(), None)
+        .await
+        .unwrap();
+    client
+        .import(snapshot.clone(), ImportMode::Merge)
+        .await
+        .unwrap();
+
+    assert_eq!(client.export().await.unwrap(), snapshot);
+}
+
+#[tokio::test]
+async fn import_undated_grants() {
+    let (_infra, source) = populated().await;
+    let mut snapshot = source.export().await.unwrap();
+    for grant in snapshot.grants.iter_mut() {
+        grant.created = None;
+    }
+
+    let infra = TestInfra::new().await;
+    let target = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+    target.import(&snapshot, ImportMode::Replace).await.unwrap();
+
+    let snapshot = target.export().await.unwrap();
+    assert_eq!(snapshot.grants.len(), 2);
+    assert!(snapshot.grants.iter().all(|g| g.created.is_some()));
+}
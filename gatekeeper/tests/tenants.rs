@@ -0,0 +1,701 @@
+mod util;
+
+use gatekeeper::{
+    AdminTier, Arbiter, AuditFilter, Cmd, Error, ImportMode, Label, LabelSelector, tenant_owns,
+};
+use iroh::SecretKey;
+use util::TestInfra;
+
+fn grant(node: &str, role: &str) -> Cmd {
+    Cmd::GrantRole {
+        node: node.to_string(),
+        role: role.to_string(),
+        resource: None,
+        ttl: None,
+    }
+}
+
+fn nodes(tenant: Option<&str>) -> Cmd {
+    Cmd::Nodes {
+        tenant: tenant.map(str::to_string),
+    }
+}
+
+fn create(node: &str, tenant: Option<&str>) -> Cmd {
+    Cmd::CreateNode {
+        name: node.to_string(),
+        node: node.to_string(),
+        superadmin: false,
+        tenant: tenant.map(str::to_string),
+    }
+}
+
+#[tokio::test]
+async fn tenant_admins() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter.create_tenant("infra", None).await.unwrap();
+    arbiter.create_tenant("web", None).await.unwrap();
+
+    let mut rng = rand::thread_rng();
+    let [admin, infra_admin, web_admin, infra_node, web_node] =
+        [(); 5].map(|_| SecretKey::generate(&mut rng).public());
+
+    for (name, node, superadmin, tenant) in [
+        ("admin", admin, true, None),
+        ("infra-admin", infra_admin, false, Some("infra")),
+        ("web-admin", web_admin, false, Some("web")),
+        ("infra-node", infra_node, false, Some("infra")),
+        ("web-node", web_node, false, Some("web")),
+    ] {
+        arbiter
+            .create_node(name, &format!("{node}"), superadmin, tenant)
+            .await
+            .unwrap();
+    }
+
+    for node in [infra_admin, web_admin] {
+        arbiter
+            .set_admin_tier(&format!("{node}"), Some(AdminTier::Operator))
+            .await
+            .unwrap();
+    }
+
+    let infra_key = format!("{infra_node}");
+    let web_key = format!("{web_node}");
+    let cmds = [
+        (nodes(None), [true, false, false]),
+        (nodes(Some("infra")), [true, true, false]),
+        (grant(&infra_key, "infra/deploy"), [true, true, false]),
+        (grant(&infra_key, "web/deploy"), [true, false, false]),
+        (grant(&infra_key, "deploy"), [true, false, false]),
+        (grant(&web_key, "infra/deploy"), [true, false, false]),
+        (grant(&web_key, "web/deploy"), [true, false, true]),
+        (create("new", Some("infra")), [true, true, false]),
+        (create("new", None), [true, false, false]),
+        (
+            Cmd::CreateGroup {
+                group: "infra/builders".to_string(),
+                description: None,
+            },
+            [true, true, false],
+        ),
+        (
+            Cmd::AddRoleParent {
+                role: "infra/read".to_string(),
+                parent: "web/write".to_string(),
+            },
+            [true, false, false],
+        ),
+        (
+            Cmd::Check {
+                checks: vec![format!("{infra_node}:infra/deploy").parse().unwrap()],
+            },
+            [true, true, false],
+        ),
+        (Cmd::Tenants, [true, false, false]),
+        (Cmd::Export, [true, false, false]),
+        (
+            Cmd::SetNodeTenant {
+                node: infra_key.clone(),
+                tenant: None,
+            },
+            [true, false, false],
+        ),
+    ];
+
+    for (cmd, expected) in cmds.iter() {
+        for (caller, allowed) in [admin, infra_admin, web_admin].iter().zip(expected) {
+            assert_eq!(
+                arbiter.authorize(*caller, cmd).await.unwrap(),
+                *allowed,
+                "{} by {caller}",
+                cmd.name()
+            );
+        }
+    }
+
+    let listed = arbiter.nodes(Some("infra")).await.unwrap();
+    let listed: Vec<_> = listed.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(listed.len(), 2);
+    assert!(listed.contains(&"infra-admin") && listed.contains(&"infra-node"));
+
+    arbiter
+        .grant_role(&infra_key, "infra/deploy", None, None)
+        .await
+        .unwrap();
+    arbiter
+        .grant_role(&web_key, "infra/deploy", None, None)
+        .await
+        .unwrap();
+    arbiter.create_role("web/deploy", None).await.unwrap();
+    assert_eq!(
+        arbiter.roles(Some("infra")).await.unwrap(),
+        vec!["infra/deploy"]
+    );
+    let holders = arbiter
+        .role_nodes("infra/deploy", false, Some("infra"), 50, 0)
+        .await
+        .unwrap();
+    assert_eq!(holders.len(), 1);
+    assert_eq!(holders[0].node, infra_key);
+
+    // A tenant role granted outside the tenant can't be deleted or renamed by
+    // the tenant admin, since that would change grants it doesn't administer
+    let delete = Cmd::DeleteRole {
+        role: "infra/deploy".to_string(),
+        cascade: true,
+    };
+    let rename = Cmd::RenameRole {
+        role: "infra/deploy".to_string(),
+        new_role: "infra/ship".to_string(),
+    };
+    for cmd in [&delete, &rename] {
+        assert!(!arbiter.authorize(infra_admin, cmd).await.unwrap());
+        assert!(arbiter.authorize(admin, cmd).await.unwrap());
+    }
+
+    arbiter
+        .revoke_role(&web_key, "infra/deploy", None)
+        .await
+        .unwrap();
+    for cmd in [&delete, &rename] {
+        assert!(arbiter.authorize(infra_admin, cmd).await.unwrap());
+    }
+
+    arbiter
+        .add_role_parent("infra/deploy", "deploy")
+        .await
+        .unwrap();
+    assert!(!arbiter.authorize(infra_admin, &delete).await.unwrap());
+
+    // Likewise for groups with members outside the tenant
+    arbiter.create_group("infra/builders", None).await.unwrap();
+    arbiter
+        .add_group_member("infra/builders", &web_key)
+        .await
+        .unwrap();
+    let delete = Cmd::DeleteGroup {
+        group: "infra/builders".to_string(),
+    };
+    assert!(!arbiter.authorize(infra_admin, &delete).await.unwrap());
+    arbiter
+        .remove_group_member("infra/builders", &web_key)
+        .await
+        .unwrap();
+    assert!(arbiter.authorize(infra_admin, &delete).await.unwrap());
+}
+
+/// Every command a tenant operator can run, inside and outside their tenant.
+#[tokio::test]
+async fn tenant_rejections() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter.create_tenant("infra", None).await.unwrap();
+    arbiter.create_tenant("web", None).await.unwrap();
+
+    let mut rng = rand::thread_rng();
+    let [operator, infra_node, web_node] = [(); 3].map(|_| SecretKey::generate(&mut rng).public());
+
+    for (name, node, tenant) in [
+        ("operator", operator, "infra"),
+        ("infra-node", infra_node, "infra"),
+        ("web-node", web_node, "web"),
+    ] {
+        arbiter
+            .create_node(name, &format!("{node}"), false, Some(tenant))
+            .await
+            .unwrap();
+    }
+    arbiter
+        .set_admin_tier(&format!("{operator}"), Some(AdminTier::Operator))
+        .await
+        .unwrap();
+
+    let ours = format!("{infra_node}");
+    let theirs = format!("{web_node}");
+    let s = |s: &str| s.to_string();
+    let role = |role: &str| Cmd::ShowRole { role: s(role) };
+    let node = |node: &str| Cmd::NodeKeyHistory { node: s(node) };
+    let group = |group: &str| Cmd::GroupMembers { group: s(group) };
+
+    // Each command once within the tenant and once reaching outside it
+    let cmds = [
+        (
+            Cmd::Roles {
+                tenant: Some(s("infra")),
+            },
+            Cmd::Roles {
+                tenant: Some(s("web")),
+            },
+        ),
+        (
+            Cmd::Nodes {
+                tenant: Some(s("infra")),
+            },
+            Cmd::Nodes { tenant: None },
+        ),
+        (
+            Cmd::Groups {
+                tenant: Some(s("infra")),
+            },
+            Cmd::Groups { tenant: None },
+        ),
+        (create("new", Some("infra")), create("new", Some("web"))),
+        (
+            Cmd::RoleNodes {
+                role: s("infra/deploy"),
+                indirect: false,
+                tenant: Some(s("infra")),
+                limit: 50,
+                offset: 0,
+            },
+            Cmd::RoleNodes {
+                role: s("web/deploy"),
+                indirect: false,
+                tenant: Some(s("infra")),
+                limit: 50,
+                offset: 0,
+            },
+        ),
+        (role("infra/deploy"), role("web/deploy")),
+        (
+            Cmd::CreateRole {
+                role: s("infra/deploy"),
+                description: None,
+            },
+            Cmd::CreateRole {
+                role: s("deploy"),
+                description: None,
+            },
+        ),
+        (
+            Cmd::DescribeRole {
+                role: s("infra/deploy"),
+                description: None,
+            },
+            Cmd::DescribeRole {
+                role: s("web/deploy"),
+                description: None,
+            },
+        ),
+        (
+            Cmd::DeleteRole {
+                role: s("infra/deploy"),
+                cascade: false,
+            },
+            Cmd::DeleteRole {
+                role: s("web/deploy"),
+                cascade: false,
+            },
+        ),
+        (
+            Cmd::RoleParents {
+                role: s("infra/deploy"),
+            },
+            Cmd::RoleParents {
+                role: s("web/deploy"),
+            },
+        ),
+        (
+            Cmd::RenameRole {
+                role: s("infra/deploy"),
+                new_role: s("infra/ship"),
+            },
+            Cmd::RenameRole {
+                role: s("infra/deploy"),
+                new_role: s("web/ship"),
+            },
+        ),
+        (
+            Cmd::AddRoleParent {
+                role: s("infra/read"),
+                parent: s("infra/write"),
+            },
+            Cmd::AddRoleParent {
+                role: s("infra/read"),
+                parent: s("write"),
+            },
+        ),
+        (
+            Cmd::RemoveRoleParent {
+                role: s("infra/read"),
+                parent: s("infra/write"),
+            },
+            Cmd::RemoveRoleParent {
+                role: s("web/read"),
+                parent: s("infra/write"),
+            },
+        ),
+        (
+            Cmd::NodeRoles {
+                node: ours.clone(),
+                effective: true,
+            },
+            Cmd::NodeRoles {
+                node: theirs.clone(),
+                effective: true,
+            },
+        ),
+        (
+            Cmd::UpdateNode {
+                node: ours.clone(),
+                name: Some(s("n")),
+                superadmin: None,
+                description: None,
+            },
+            Cmd::UpdateNode {
+                node: theirs.clone(),
+                name: Some(s("n")),
+                superadmin: None,
+                description: None,
+            },
+        ),
+        (
+            Cmd::RotateNodeKey {
+                node: ours.clone(),
+                new_node: s("new-key"),
+            },
+            Cmd::RotateNodeKey {
+                node: theirs.clone(),
+                new_node: s("new-key"),
+            },
+        ),
+        (node(&ours), node(&theirs)),
+        (
+            Cmd::DeleteNode { node: ours.clone() },
+            Cmd::DeleteNode {
+                node: theirs.clone(),
+            },
+        ),
+        (
+            Cmd::NodeLabels { node: ours.clone() },
+            Cmd::NodeLabels {
+                node: theirs.clone(),
+            },
+        ),
+        (
+            Cmd::SetNodeLabel {
+                node: ours.clone(),
+                label: "env=prod".parse().unwrap(),
+            },
+            Cmd::SetNodeLabel {
+                node: theirs.clone(),
+                label: "env=prod".parse().unwrap(),
+            },
+        ),
+        (
+            Cmd::RemoveNodeLabel {
+                node: ours.clone(),
+                key: s("env"),
+            },
+            Cmd::RemoveNodeLabel {
+                node: theirs.clone(),
+                key: s("env"),
+            },
+        ),
+        (grant(&ours, "infra/deploy"), grant(&theirs, "infra/deploy")),
+        (
+            Cmd::RevokeRole {
+                node: ours.clone(),
+                role: s("infra/deploy"),
+                resource: None,
+            },
+            Cmd::RevokeRole {
+                node: ours.clone(),
+                role: s("deploy"),
+                resource: None,
+            },
+        ),
+        (
+            Cmd::DenyRole {
+                node: ours.clone(),
+                role: s("infra/deploy"),
+            },
+            Cmd::DenyRole {
+                node: theirs.clone(),
+                role: s("web/deploy"),
+            },
+        ),
+        (
+            Cmd::RemoveRoleDeny {
+                node: ours.clone(),
+                role: s("infra/deploy"),
+            },
+            Cmd::RemoveRoleDeny {
+                node: ours.clone(),
+                role: s("web/deploy"),
+            },
+        ),
+        (
+            Cmd::CreateGroup {
+                group: s("infra/builders"),
+                description: None,
+            },
+            Cmd::CreateGroup {
+                group: s("builders"),
+                description: None,
+            },
+        ),
+        (
+            Cmd::DeleteGroup {
+                group: s("infra/builders"),
+            },
+            Cmd::DeleteGroup {
+                group: s("web/builders"),
+            },
+        ),
+        (group("infra/builders"), group("web/builders")),
+        (
+            Cmd::GroupRoles {
+                group: s("infra/builders"),
+            },
+            Cmd::GroupRoles {
+                group: s("web/builders"),
+            },
+        ),
+        (
+            Cmd::AddGroupMember {
+                group: s("infra/builders"),
+                node: ours.clone(),
+            },
+            Cmd::AddGroupMember {
+                group: s("infra/builders"),
+                node: theirs.clone(),
+            },
+        ),
+        (
+            Cmd::RemoveGroupMember {
+                group: s("infra/builders"),
+                node: ours.clone(),
+            },
+            Cmd::RemoveGroupMember {
+                group: s("web/builders"),
+                node: ours.clone(),
+            },
+        ),
+        (
+            Cmd::GrantGroupRole {
+                group: s("infra/builders"),
+                role: s("infra/deploy"),
+            },
+            Cmd::GrantGroupRole {
+                group: s("infra/builders"),
+                role: s("web/deploy"),
+            },
+        ),
+        (
+            Cmd::RevokeGroupRole {
+                group: s("infra/builders"),
+                role: s("infra/deploy"),
+            },
+            Cmd::RevokeGroupRole {
+                group: s("web/builders"),
+                role: s("infra/deploy"),
+            },
+        ),
+        (
+            Cmd::Check {
+                checks: vec![format!("{ours}:infra/deploy").parse().unwrap()],
+            },
+            Cmd::Check {
+                checks: vec![format!("{theirs}:infra/deploy").parse().unwrap()],
+            },
+        ),
+    ];
+
+    for (within, outside) in cmds.iter() {
+        assert!(
+            arbiter.authorize(operator, within).await.unwrap(),
+            "{within:?}"
+        );
+        assert!(
+            !arbiter.authorize(operator, outside).await.unwrap(),
+            "{outside:?}"
+        );
+    }
+
+    // Commands that can't be confined to a tenant
+    let selector: LabelSelector = "env=prod".parse().unwrap();
+    let cmds = [
+        Cmd::SelectorGrants,
+        Cmd::GrantSelectorRole {
+            selector: selector.clone(),
+            role: s("infra/deploy"),
+        },
+        Cmd::RevokeSelectorRole {
+            selector,
+            role: s("infra/deploy"),
+        },
+        Cmd::Tenants,
+        Cmd::CreateInvite {
+            roles: vec![s("infra/deploy")],
+            ttl: 60,
+            uses: 1,
+        },
+        Cmd::Invites,
+        Cmd::DeleteInvite { id: 1 },
+        Cmd::ListRequests { all: false },
+        Cmd::Approve { id: 1, note: None },
+        Cmd::Deny { id: 1, note: None },
+        Cmd::PendingOps,
+        Cmd::AuditLog(AuditFilter {
+            node: None,
+            role: None,
+            caller: None,
+            since: None,
+            until: None,
+            limit: 50,
+            offset: 0,
+        }),
+        Cmd::Export,
+    ];
+
+    for cmd in cmds.iter() {
+        assert!(!arbiter.authorize(operator, cmd).await.unwrap(), "{cmd:?}");
+    }
+}
+
+#[tokio::test]
+async fn tenant_lifecycle() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    assert!(tenant_owns("infra", "infra/deploy"));
+    assert!(!tenant_owns("infra", "infra"));
+    assert!(!tenant_owns("infra", "infra/"));
+    assert!(!tenant_owns("infra", "infrastructure/deploy"));
+
+    for name in ["", "a/b", "a b"] {
+        assert!(matches!(
+            arbiter.create_tenant(name, None).await,
+            Err(Error::InvalidTenantError(_))
+        ));
+    }
+
+    arbiter
+        .create_tenant("infra", Some("Infra team"))
+        .await
+        .unwrap();
+    assert!(matches!(
+        arbiter.create_tenant("infra", None).await,
+        Err(Error::TenantExistsError)
+    ));
+    assert!(matches!(
+        arbiter.create_node("a", "node-a", false, Some("web")).await,
+        Err(Error::NoSuchTenantError)
+    ));
+
+    let node = arbiter
+        .create_node("a", "node-a", false, Some("infra"))
+        .await
+        .unwrap();
+    assert_eq!(node.tenant.as_deref(), Some("infra"));
+
+    // Superadmins would keep their global access inside a tenant
+    assert!(matches!(
+        arbiter
+            .create_node("b", "node-b", true, Some("infra"))
+            .await,
+        Err(Error::InvalidTenantError(_))
+    ));
+    assert!(matches!(
+        arbiter.update_node("node-a", None, Some(true), None).await,
+        Err(Error::InvalidTenantError(_))
+    ));
+    arbiter
+        .create_node("b", "node-b", true, None)
+        .await
+        .unwrap();
+    assert!(matches!(
+        arbiter.set_node_tenant("node-b", Some("infra")).await,
+        Err(Error::InvalidTenantError(_))
+    ));
+    arbiter.delete_node("node-b").await.unwrap();
+    assert!(matches!(
+        arbiter.delete_tenant("infra").await,
+        Err(Error::TenantInUseError)
+    ));
+
+    let snapshot = arbiter.export().await.unwrap();
+    assert_eq!(snapshot.tenants.len(), 1);
+    assert_eq!(snapshot.nodes[0].tenant.as_deref(), Some("infra"));
+
+    let node = arbiter.set_node_tenant("node-a", None).await.unwrap();
+    assert_eq!(node.tenant, None);
+    arbiter.delete_tenant("infra").await.unwrap();
+    assert!(arbiter.tenants().await.unwrap().is_empty());
+
+    let infra = TestInfra::new().await;
+    let target = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+    target.import(&snapshot, ImportMode::Replace).await.unwrap();
+    assert_eq!(target.export().await.unwrap(), snapshot);
+
+    let mut invalid = snapshot.clone();
+    invalid.nodes[0].superadmin = true;
+    assert!(matches!(
+        target.import(&invalid, ImportMode::Merge).await,
+        Err(Error::InvalidSnapshotError(_))
+    ));
+}
+
+#[tokio::test]
+async fn tenant_labels() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false).await.unwrap();
+
+    arbiter.create_tenant("infra", None).await.unwrap();
+
+    let mut rng = rand::thread_rng();
+    let [infra_admin, infra_node, other_node] =
+        [(); 3].map(|_| SecretKey::generate(&mut rng).public());
+
+    for (name, node, tenant) in [
+        ("infra-admin", infra_admin, Some("infra")),
+        ("infra-node", infra_node, Some("infra")),
+        ("other-node", other_node, None),
+    ] {
+        arbiter
+            .create_node(name, &format!("{node}"), false, tenant)
+            .await
+            .unwrap();
+    }
+    arbiter
+        .set_admin_tier(&format!("{infra_admin}"), Some(AdminTier::Operator))
+        .await
+        .unwrap();
+
+    let prod: LabelSelector = "env=prod".parse().unwrap();
+    arbiter.grant_selector_role(&prod, "deploy").await.unwrap();
+    arbiter
+        .grant_selector_role(&prod, "infra/deploy")
+        .await
+        .unwrap();
+
+    // The tenant admin can label its own nodes...
+    let infra_key = format!("{infra_node}");
+    let label: Label = "env=prod".parse().unwrap();
+    let cmd = Cmd::SetNodeLabel {
+        node: infra_key.clone(),
+        label: label.clone(),
+    };
+    assert!(arbiter.authorize(infra_admin, &cmd).await.unwrap());
+
+    // ...but that only brings in the selector grants its tenant owns
+    let other_key = format!("{other_node}");
+    for node in [&infra_key, &other_key] {
+        arbiter.set_node_label(node, &label).await.unwrap();
+    }
+    assert!(arbiter.has_role(&infra_key, "infra/deploy").await.unwrap());
+    assert!(!arbiter.has_role(&infra_key, "deploy").await.unwrap());
+    assert!(arbiter.has_role(&other_key, "deploy").await.unwrap());
+
+    let holders = arbiter
+        .role_nodes("deploy", true, None, 50, 0)
+        .await
+        .unwrap();
+    let holders: Vec<_> = holders.iter().map(|n| n.node.as_str()).collect();
+    assert_eq!(holders, vec![other_key.as_str()]);
+}
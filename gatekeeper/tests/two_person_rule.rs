@@ -0,0 +1,213 @@
+mod util;
+
+use std::time::Duration;
+
+use gatekeeper::{ALPN, Arbiter, AuditFilter, Client, Cmd, Error, ImportMode, Server, Snapshot};
+use iroh::{Endpoint, NodeAddr, SecretKey, Watcher, protocol::Router};
+use util::TestInfra;
+
+#[tokio::test]
+async fn two_person_rule() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false)
+        .await
+        .unwrap()
+        .with_two_person_rule(
+            vec!["delete-node".to_string(), "create-superadmin".to_string()],
+            Duration::from_secs(60),
+        );
+
+    let mut rng = rand::thread_rng();
+    let alice = SecretKey::generate(&mut rng).public();
+    let bob = SecretKey::generate(&mut rng).public();
+    let carol = SecretKey::generate(&mut rng).public();
+
+    arbiter
+        .create_node("alice", &format!("{alice}"), true, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("bob", &format!("{bob}"), true, None)
+        .await
+        .unwrap();
+    arbiter
+        .create_node("carol", &format!("{carol}"), false, None)
+        .await
+        .unwrap();
+
+    let delete = Cmd::DeleteNode {
+        node: format!("{carol}"),
+    };
+    let superadmin = Cmd::CreateNode {
+        name: "dave".to_string(),
+        node: "node-dave".to_string(),
+        superadmin: true,
+        tenant: None,
+    };
+    let plain = Cmd::CreateNode {
+        name: "dave".to_string(),
+        node: "node-dave".to_string(),
+        superadmin: false,
+        tenant: None,
+    };
+    for (cmd, held) in [
+        (&delete, true),
+        (&superadmin, true),
+        (&plain, false),
+        (&Cmd::Confirm { id: 1 }, false),
+    ] {
+        assert_eq!(arbiter.requires_confirmation(cmd).await.unwrap(), held);
+    }
+
+    // Rotating a superadmin's key hands its access to the new key
+    for (node, held) in [(alice, true), (carol, false)] {
+        let rotate = Cmd::RotateNodeKey {
+            node: format!("{node}"),
+            new_node: "node-new".to_string(),
+        };
+        assert_eq!(arbiter.requires_confirmation(&rotate).await.unwrap(), held);
+    }
+
+    // Imports can create superadmins or, when replacing, delete every node
+    let mut snapshot = arbiter.export().await.unwrap();
+    let import = |snapshot: &Snapshot, mode| Cmd::Import {
+        snapshot: snapshot.clone(),
+        mode,
+    };
+    let merge = import(&snapshot, ImportMode::Merge);
+    assert!(arbiter.requires_confirmation(&merge).await.unwrap());
+
+    snapshot.nodes.iter_mut().for_each(|n| n.superadmin = false);
+    let merge = import(&snapshot, ImportMode::Merge);
+    assert!(!arbiter.requires_confirmation(&merge).await.unwrap());
+    let replace = import(&snapshot, ImportMode::Replace);
+    assert!(arbiter.requires_confirmation(&replace).await.unwrap());
+
+    assert!(Cmd::is_rule("delete-node"));
+    assert!(Cmd::is_rule("create-superadmin"));
+    assert!(Cmd::is_rule("import"));
+    assert!(!Cmd::is_rule("delete_node"));
+
+    let op = arbiter.hold_op(alice, &delete).await.unwrap();
+    assert_eq!(op.cmd, "delete-node");
+    assert_eq!(op.requested_by, format!("{alice}"));
+    assert_eq!(arbiter.pending_ops().await.unwrap().len(), 1);
+
+    let res = arbiter.confirm_op(alice, op.id).await;
+    assert!(matches!(res, Err(Error::UnauthorizedError)));
+
+    let res = arbiter.cancel_op(carol, op.id).await;
+    assert!(matches!(res, Err(Error::UnauthorizedError)));
+
+    let (requester, cmd) = arbiter.confirm_op(bob, op.id).await.unwrap();
+    assert_eq!(requester, alice);
+    assert!(matches!(cmd, Cmd::DeleteNode { node } if node == format!("{carol}")));
+    assert!(arbiter.pending_ops().await.unwrap().is_empty());
+
+    let res = arbiter.confirm_op(bob, op.id).await;
+    assert!(matches!(res, Err(Error::NoSuchOpError)));
+
+    let op = arbiter.hold_op(alice, &superadmin).await.unwrap();
+    arbiter.cancel_op(alice, op.id).await.unwrap();
+    let res = arbiter.confirm_op(bob, op.id).await;
+    assert!(matches!(res, Err(Error::NoSuchOpError)));
+}
+
+#[tokio::test]
+async fn confirmed_ops_are_audited() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false)
+        .await
+        .unwrap()
+        .with_two_person_rule(vec!["delete-node".to_string()], Duration::from_secs(60));
+
+    let mut rng = rand::thread_rng();
+    let alice_sk = SecretKey::generate(&mut rng);
+    let bob_sk = SecretKey::generate(&mut rng);
+    let carol = SecretKey::generate(&mut rng).public();
+
+    for (name, node, superadmin) in [
+        ("alice", alice_sk.public(), true),
+        ("bob", bob_sk.public(), true),
+        ("carol", carol, false),
+    ] {
+        arbiter
+            .create_node(name, &format!("{node}"), superadmin, None)
+            .await
+            .unwrap();
+    }
+
+    let endpoint = Endpoint::builder().discovery_n0().bind().await.unwrap();
+    let server = Router::builder(endpoint)
+        .accept(ALPN, Server::new(arbiter))
+        .spawn();
+    let addr = server.endpoint().node_addr().initialized().await;
+
+    let alice = connect(alice_sk.clone(), addr.clone()).await;
+    let bob = connect(bob_sk, addr).await;
+
+    let res = alice.delete_node(carol).await;
+    let Err(Error::ConfirmationRequiredError(id)) = res else {
+        panic!("expected the deletion to be held, got {res:?}");
+    };
+    bob.confirm::<()>(id).await.unwrap();
+
+    let entries = bob
+        .audit_log(AuditFilter {
+            node: Some(format!("{carol}")),
+            role: None,
+            caller: None,
+            since: None,
+            until: None,
+            limit: 50,
+            offset: 0,
+        })
+        .await
+        .unwrap();
+    let rows: Vec<(&str, &str)> = entries
+        .iter()
+        .map(|e| (e.cmd.as_str(), e.outcome.as_str()))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("delete-node", "ok"),
+            ("delete-node", "ConfirmationRequiredError: 1")
+        ]
+    );
+    assert!(
+        entries
+            .iter()
+            .all(|e| e.caller == format!("{}", alice_sk.public()))
+    );
+}
+
+#[tokio::test]
+async fn confirm_window_too_long() {
+    let infra = TestInfra::new().await;
+    let arbiter = Arbiter::new(infra.db_path.clone(), false)
+        .await
+        .unwrap()
+        .with_two_person_rule(vec!["delete-node".to_string()], Duration::MAX);
+
+    let mut rng = rand::thread_rng();
+    let alice = SecretKey::generate(&mut rng).public();
+    let delete = Cmd::DeleteNode {
+        node: "node-carol".to_string(),
+    };
+
+    let res = arbiter.hold_op(alice, &delete).await;
+    assert!(matches!(res, Err(Error::InvalidTtlError(_))));
+    assert!(arbiter.pending_ops().await.unwrap().is_empty());
+}
+
+async fn connect(sk: SecretKey, addr: NodeAddr) -> Client {
+    let endpoint = Endpoint::builder()
+        .discovery_n0()
+        .secret_key(sk)
+        .bind()
+        .await
+        .unwrap();
+
+    Client::with_addr(endpoint, addr)
+}
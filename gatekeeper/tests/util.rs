@@ -1,3 +1,5 @@
+#![allow(clippy::to_string_in_format_args)]
+
 use std::path::PathBuf;
 
 use gatekeeper::{ALPN, Arbiter, Client, Server};
@@ -27,10 +29,12 @@ pub struct ClientServer {
     pub infra: TestInfra,
     pub client: Client,
     pub client_sk: SecretKey,
+    pub bootstrap_token: Option<String>,
     pub server: Router,
     pub server_sk: SecretKey,
 }
 
+#[allow(dead_code)]
 impl ClientServer {
     pub async fn new(infra: TestInfra, remote_setup: bool) -> Self {
         let mut rng = rand::thread_rng();
@@ -47,6 +51,7 @@ impl ClientServer {
         let arbiter = Arbiter::new(infra.db_path.clone(), remote_setup)
             .await
             .unwrap();
+        let bootstrap_token = arbiter.bootstrap_token().await;
 
         let server = Router::builder(server_endpoint)
             .accept(ALPN, Server::new(arbiter))
@@ -67,6 +72,7 @@ impl ClientServer {
             infra,
             client,
             client_sk,
+            bootstrap_token,
             server,
             server_sk,
         }
@@ -0,0 +1,81 @@
+mod util;
+
+use std::time::Duration;
+
+use gatekeeper::Client;
+use iroh::{Endpoint, SecretKey, Watcher};
+use util::{ClientServer, TestInfra};
+
+#[tokio::test]
+async fn whoami() {
+    let infra = TestInfra::new().await;
+    let client_server = ClientServer::new(infra, true).await;
+    let client = client_server.client;
+
+    let client_pk = client_server.client_sk.public();
+    let token = client_server.bootstrap_token.unwrap();
+    client
+        .bootstrap(token, "self".to_string(), client_pk)
+        .await
+        .unwrap();
+
+    let mut rng = rand::thread_rng();
+    let other_sk = SecretKey::generate(&mut rng);
+    let other_id = other_sk.public();
+
+    client
+        .create_node("other".to_string(), other_id, false, None)
+        .await
+        .unwrap();
+    client
+        .add_role_parent("read".to_string(), "write".to_string())
+        .await
+        .unwrap();
+    client
+        .grant_role(
+            other_id,
+            "write".to_string(),
+            None,
+            Some(Duration::from_secs(3600)),
+        )
+        .await
+        .unwrap();
+
+    let server_addr = client_server
+        .server
+        .endpoint()
+        .node_addr()
+        .initialized()
+        .await;
+
+    let other_client_endpoint = Endpoint::builder()
+        .discovery_n0()
+        .secret_key(other_sk.clone())
+        .bind()
+        .await
+        .unwrap();
+
+    let other_client = Client::with_addr(other_client_endpoint, server_addr.clone());
+
+    let whoami = other_client.whoami().await.unwrap();
+    assert_eq!(whoami.node.name, "other");
+    assert_eq!(whoami.roles.len(), 1);
+    assert_eq!(whoami.roles[0].role, "write");
+    assert!(whoami.roles[0].expires_at.is_some());
+
+    let mut effective: Vec<_> = whoami.effective_roles.iter().map(|g| &g.role).collect();
+    effective.sort();
+    assert_eq!(effective, vec!["read", "write"]);
+
+    assert!(other_client.roles(None).await.is_err());
+
+    let stranger_endpoint = Endpoint::builder()
+        .discovery_n0()
+        .secret_key(SecretKey::generate(&mut rng))
+        .bind()
+        .await
+        .unwrap();
+
+    let stranger = Client::with_addr(stranger_endpoint, server_addr);
+    assert!(stranger.whoami().await.is_err());
+}